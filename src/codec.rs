@@ -0,0 +1,207 @@
+// Compact binary encoding of pallet state, for node state transfer.
+//
+// Integers are fixed-width little-endian, lengths are u32 little-endian prefixes, enums are
+// a one-byte tag followed by their fields, and maps are written in ascending key order so
+// equal states encode to equal bytes.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+pub trait Encode {
+    fn encode_to(&self, out: &mut Vec<u8>);
+}
+
+pub trait Decode: Sized {
+    // Read a value from the front of `input`, advancing it past the bytes consumed
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str>;
+}
+
+// Take the next `len` bytes from `input`
+pub fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], &'static str> {
+    if input.len() < len {
+        return Err("Unexpected end of input");
+    }
+
+    let (head, rest) = input.split_at(len);
+    *input = rest;
+
+    Ok(head)
+}
+
+// Write a length prefix. A collection longer than u32::MAX has no valid encoding, and
+// truncating its length would corrupt everything after it, so that panics instead.
+pub fn encode_len(len: usize, out: &mut Vec<u8>) {
+    u32::try_from(len)
+        .expect("collection length exceeds the u32 length prefix")
+        .encode_to(out);
+}
+
+// Read a length prefix
+pub fn decode_len(input: &mut &[u8]) -> Result<usize, &'static str> {
+    u32::decode_from(input).map(|len| len as usize)
+}
+
+// Decode a whole buffer, rejecting bytes left over after the value
+pub fn decode_all<V: Decode>(mut input: &[u8]) -> Result<V, &'static str> {
+    let value = V::decode_from(&mut input)?;
+    if !input.is_empty() {
+        return Err("Trailing bytes");
+    }
+
+    Ok(value)
+}
+
+// Write a map as a length prefix and its entries in ascending key order
+pub fn encode_map<K: Encode + Ord, V: Encode>(map: &HashMap<K, V>, out: &mut Vec<u8>) {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+
+    encode_len(entries.len(), out);
+    for (key, value) in entries {
+        key.encode_to(out);
+        value.encode_to(out);
+    }
+}
+
+// Read a map written by `encode_map`, rejecting repeated keys
+pub fn decode_map<K: Decode + Eq + Hash, V: Decode>(
+    input: &mut &[u8],
+) -> Result<HashMap<K, V>, &'static str> {
+    let len = decode_len(input)?;
+    let mut map = HashMap::new();
+    for _ in 0..len {
+        let key = K::decode_from(input)?;
+        let value = V::decode_from(input)?;
+        if map.insert(key, value).is_some() {
+            return Err("Duplicate key");
+        }
+    }
+
+    Ok(map)
+}
+
+macro_rules! impl_integer_codec {
+    ($($int:ty),*) => {$(
+        impl Encode for $int {
+            fn encode_to(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl Decode for $int {
+            fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+                let bytes = take(input, std::mem::size_of::<$int>())?;
+                let mut buf = [0u8; std::mem::size_of::<$int>()];
+                buf.copy_from_slice(bytes);
+                Ok(<$int>::from_le_bytes(buf))
+            }
+        }
+    )*};
+}
+
+impl_integer_codec!(u8, u16, u32, u64, u128);
+
+impl Encode for bool {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        match u8::decode_from(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err("Invalid enum tag"),
+        }
+    }
+}
+
+impl<const N: usize> Encode for [u8; N] {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+}
+
+impl<const N: usize> Decode for [u8; N] {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        let mut buf = [0u8; N];
+        buf.copy_from_slice(take(input, N)?);
+        Ok(buf)
+    }
+}
+
+impl Encode for String {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        encode_len(self.len(), out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        let len = decode_len(input)?;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "Invalid UTF-8")
+    }
+}
+
+impl<V: Encode> Encode for Option<V> {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.encode_to(out);
+            }
+        }
+    }
+}
+
+impl<V: Decode> Decode for Option<V> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        match u8::decode_from(input)? {
+            0 => Ok(None),
+            1 => V::decode_from(input).map(Some),
+            _ => Err("Invalid enum tag"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primitive_round_trip() {
+        let mut out = Vec::new();
+        7u32.encode_to(&mut out);
+        u128::MAX.encode_to(&mut out);
+        "hé".to_string().encode_to(&mut out);
+        Some(true).encode_to(&mut out);
+        [9u8; 32].encode_to(&mut out);
+
+        let mut input = out.as_slice();
+        assert_eq!(u32::decode_from(&mut input), Ok(7));
+        assert_eq!(u128::decode_from(&mut input), Ok(u128::MAX));
+        assert_eq!(String::decode_from(&mut input), Ok("hé".to_string()));
+        assert_eq!(Option::<bool>::decode_from(&mut input), Ok(Some(true)));
+        assert_eq!(<[u8; 32]>::decode_from(&mut input), Ok([9u8; 32]));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_input_rejected() {
+        assert_eq!(decode_all::<u64>(&[1, 2, 3]), Err("Unexpected end of input"));
+        assert_eq!(decode_all::<u8>(&[1, 2]), Err("Trailing bytes"));
+        assert_eq!(decode_all::<bool>(&[2]), Err("Invalid enum tag"));
+        assert_eq!(decode_all::<String>(&[1, 0, 0, 0, 0xff]), Err("Invalid UTF-8"));
+    }
+
+    #[test]
+    #[should_panic(expected = "collection length exceeds the u32 length prefix")]
+    fn test_encode_len_rejects_oversized_length() {
+        let mut out = Vec::new();
+        encode_len(u32::MAX as usize + 1, &mut out);
+    }
+}
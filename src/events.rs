@@ -0,0 +1,112 @@
+use crate::governance::{GovernanceConfig, ProposalStatus};
+use crate::staking::StakingConfig;
+
+// Something that happened in the staking pallet
+pub enum StakingEvent<T: StakingConfig> {
+    Staked { who: T::AccountId, amount: T::Balance },
+    Unstaked { who: T::AccountId, amount: T::Balance },
+    // Carries the validator's backing after the nomination, so indexers can follow it
+    NominationAdded { nominator: T::AccountId, validator: T::AccountId, new_total_backing: T::Balance },
+}
+
+// Something that happened in the governance pallet
+pub enum GovernanceEvent<T: GovernanceConfig> {
+    Proposed { proposal_id: u32, creator: T::AccountId },
+    Voted { proposal_id: u32, voter: T::AccountId, choice: bool },
+    Finalized { proposal_id: u32, status: ProposalStatus },
+    // Queued deposit forfeitures could not all be moved to the treasury; they stay queued
+    ForfeituresFailed { error: &'static str },
+}
+
+// An event from any pallet, tagged with the pallet that emitted it
+pub enum RuntimeEvent<T: GovernanceConfig> {
+    Staking(StakingEvent<T>),
+    Governance(GovernanceEvent<T>),
+}
+
+// Implemented manually so cloning does not require `T: Clone`
+impl<T: StakingConfig> Clone for StakingEvent<T> {
+    fn clone(&self) -> Self {
+        match self {
+            StakingEvent::Staked { who, amount } => StakingEvent::Staked { who: who.clone(), amount: *amount },
+            StakingEvent::Unstaked { who, amount } => StakingEvent::Unstaked { who: who.clone(), amount: *amount },
+            StakingEvent::NominationAdded { nominator, validator, new_total_backing } => {
+                StakingEvent::NominationAdded {
+                    nominator: nominator.clone(),
+                    validator: validator.clone(),
+                    new_total_backing: *new_total_backing,
+                }
+            }
+        }
+    }
+}
+
+impl<T: GovernanceConfig> Clone for GovernanceEvent<T> {
+    fn clone(&self) -> Self {
+        match self {
+            GovernanceEvent::Proposed { proposal_id, creator } => {
+                GovernanceEvent::Proposed { proposal_id: *proposal_id, creator: creator.clone() }
+            }
+            GovernanceEvent::Voted { proposal_id, voter, choice } => {
+                GovernanceEvent::Voted { proposal_id: *proposal_id, voter: voter.clone(), choice: *choice }
+            }
+            GovernanceEvent::Finalized { proposal_id, status } => {
+                GovernanceEvent::Finalized { proposal_id: *proposal_id, status: status.clone() }
+            }
+            GovernanceEvent::ForfeituresFailed { error } => GovernanceEvent::ForfeituresFailed { error },
+        }
+    }
+}
+
+impl<T: GovernanceConfig> Clone for RuntimeEvent<T> {
+    fn clone(&self) -> Self {
+        match self {
+            RuntimeEvent::Staking(event) => RuntimeEvent::Staking(event.clone()),
+            RuntimeEvent::Governance(event) => RuntimeEvent::Governance(event.clone()),
+        }
+    }
+}
+
+// A single chronological stream of events from every pallet, in dispatch order
+pub struct EventBus<T: GovernanceConfig> {
+    events: Vec<RuntimeEvent<T>>,
+}
+
+impl<T: GovernanceConfig> Clone for EventBus<T> {
+    fn clone(&self) -> Self {
+        Self { events: self.events.clone() }
+    }
+}
+
+impl<T: GovernanceConfig> Default for EventBus<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: GovernanceConfig> EventBus<T> {
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+
+    // Append an event to the stream
+    pub fn deposit(&mut self, event: RuntimeEvent<T>) {
+        self.events.push(event);
+    }
+
+    // Events collected so far, oldest first
+    pub fn events(&self) -> &[RuntimeEvent<T>] {
+        &self.events
+    }
+
+    // Take every collected event, leaving the bus empty
+    pub fn drain(&mut self) -> Vec<RuntimeEvent<T>> {
+        std::mem::take(&mut self.events)
+    }
+
+    // Take up to `max` of the oldest events, leaving the rest in order for the next call
+    pub fn drain_paged(&mut self, max: usize) -> Vec<RuntimeEvent<T>> {
+        let count = max.min(self.events.len());
+        self.events.drain(..count).collect()
+    }
+}
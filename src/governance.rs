@@ -1,55 +1,903 @@
-use crate::staking::StakingConfig;
-use crate::system::SystemConfig;
-use std::collections::HashMap;
+#[cfg(feature = "codec")]
+use crate::codec::{decode_len, encode_len, Decode, Encode};
+use crate::math::{balance_as_u128, isqrt};
+use crate::staking::{
+    BalanceProvider, StakingConfig, StakingPallet, StakingParameter, StakingParameterKey,
+};
+use crate::system::{ensure_root, ensure_signed_by, has_reached, Origin, StorageVersion};
+use crate::treasury::TreasuryPallet;
+use num::traits::{CheckedAdd, CheckedMul, Zero};
+use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Index;
+use std::rc::Rc;
 
-pub trait GovernanceConfig: StakingConfig {}
+pub trait GovernanceConfig: StakingConfig {
+    // Percentage of the whole electorate that must vote yes when finalizing against an electorate
+    const ELECTORATE_APPROVAL_PERCENT: u32 = 50;
+    // Outcome of a proposal whose yes and no votes are equal
+    const TIE_BREAK: TieBreak = TieBreak::RejectOnTie;
+    // Amount reserved from the creator by `create_proposal_with_deposit`, unless
+    // `deposit_for` says otherwise
+    const PROPOSAL_DEPOSIT: Self::Balance;
+    // Blocks between finalization and the deposit being refunded
+    const REFUND_DELAY: Self::BlockNumber;
+    // Forfeit the deposit of a rejected proposal to the treasury instead of refunding it
+    const FORFEIT_REJECTED_DEPOSITS: bool = false;
+    // Bounds on proposal description length, in bytes
+    const MIN_DESCRIPTION_LEN: usize = 1;
+    const MAX_DESCRIPTION_LEN: usize = 256;
+    // Bound on the bytes of all stored descriptions together
+    const MAX_TOTAL_DESC_BYTES: usize = 64 * 1024;
+    // Blocks after creation before a proposal can be closed
+    const VOTING_PERIOD: Self::BlockNumber;
+    // Votes (including abstentions) a proposal needs before it can be approved
+    const MIN_QUORUM: u32 = 0;
+    // Percentage of yes/no votes that must be yes, exceeded strictly, for approval
+    const APPROVAL_THRESHOLD_PERCENT: u32 = 50;
+    // Quorum and approval threshold for Low and Critical proposals; Normal proposals use
+    // MIN_QUORUM and APPROVAL_THRESHOLD_PERCENT
+    const LOW_PRIORITY_QUORUM: u32 = Self::MIN_QUORUM;
+    const LOW_PRIORITY_THRESHOLD_PERCENT: u32 = Self::APPROVAL_THRESHOLD_PERCENT;
+    const CRITICAL_PRIORITY_QUORUM: u32 = Self::MIN_QUORUM;
+    const CRITICAL_PRIORITY_THRESHOLD_PERCENT: u32 = Self::APPROVAL_THRESHOLD_PERCENT;
+    // Yes votes a proposal needs for approval, however few no votes it has
+    const MIN_YES_VOTES: u32 = 0;
+    // Votes a single account may cast within one block, across all proposals
+    const MAX_VOTES_PER_BLOCK: u32 = 64;
+    // Bounds on the discussion attached to each proposal
+    const MAX_COMMENT_LEN: usize = 280;
+    const MAX_COMMENTS_PER_PROPOSAL: usize = 100;
+    // Blocks after voting starts before a proposal can be finalized at all, even early
+    const MIN_VOTING_BLOCKS: Self::BlockNumber;
+    // Blocks a creator must wait after creating a proposal before creating another
+    const PROPOSAL_COOLDOWN_BLOCKS: Self::BlockNumber;
+    // Active or scheduled proposals a single account may have at once
+    const MAX_OPEN_PER_ACCOUNT: u32 = 16;
+    // Active or scheduled proposals across all accounts
+    const MAX_ACTIVE_PROPOSALS: u32 = u32::MAX;
+    // Once MAX_ACTIVE_PROPOSALS is reached, let a deposit-backed proposal displace the open
+    // proposal of the lowest-staked creator if its own creator has strictly more stake
+    const BUMP_BY_STAKE: bool = false;
+    // Stake below which an account carries no weight in stake-weighted tallies; None admits
+    // every staker
+    const MIN_VOTER_STAKE: Option<Self::Balance> = None;
+    // Reputation a creator earns each time one of its proposals is approved
+    const REPUTATION_PER_APPROVAL: u32 = 0;
+    // Cap on any single account's stake-weighted vote; None leaves weights uncapped
+    const MAX_VOTE_WEIGHT: Option<Self::Balance> = None;
+    // How stake translates into vote weight for stake-weighted tallies, until changed by
+    // `set_voting_mode`
+    const VOTING_MODE: VotingMode = VotingMode::Linear;
+
+    // Deposit reserved for creating a proposal of `kind`, so high-impact proposals can cost
+    // more than discussion; PROPOSAL_DEPOSIT for every kind by default
+    fn deposit_for(_kind: &ProposalKind<Self>) -> Self::Balance
+    where
+        Self: Sized,
+    {
+        Self::PROPOSAL_DEPOSIT
+    }
+}
+
+// Decide a tally of `yes` and `no`, one per vote or weighed. `participation` counts the
+// accounts that voted yes, no or abstained, so abstentions count towards the quorum but not
+// towards the yes/no split. Approval needs the quorum and yes strictly above
+// `threshold_percent` of yes + no; a contested tally exactly on the threshold is approved
+// only if `approve_on_tie`.
+pub fn decide_status<B>(
+    yes: B,
+    no: B,
+    participation: u64,
+    quorum: u32,
+    threshold_percent: u32,
+    approve_on_tie: bool,
+) -> Result<ProposalStatus, &'static str>
+where
+    B: Zero + CheckedAdd + CheckedMul + From<u32> + Ord,
+{
+    let support = yes.checked_mul(&B::from(100u32)).ok_or("Overflow")?;
+    let bar = yes.checked_add(&no)
+        .and_then(|total| total.checked_mul(&B::from(threshold_percent)))
+        .ok_or("Overflow")?;
+    let quorum_met = participation >= quorum as u64;
+    let contested = !yes.is_zero() || !no.is_zero();
+
+    let approved = quorum_met && (support > bar || (contested && support == bar && approve_on_tie));
+    Ok(if approved { ProposalStatus::Approved } else { ProposalStatus::Rejected })
+}
+
+// Check `approving` out of `turnout` votes against the whole electorate, if one is given:
+// approval needs more than ELECTORATE_APPROVAL_PERCENT of it approving
+fn electorate_met<T: GovernanceConfig>(
+    approving: u32,
+    turnout: u32,
+    electorate: Option<u32>,
+) -> Result<bool, &'static str> {
+    match electorate {
+        Some(electorate) if turnout > electorate => Err("Electorate smaller than turnout"),
+        Some(electorate) => Ok(approving as u64 * 100
+            > electorate as u64 * T::ELECTORATE_APPROVAL_PERCENT as u64),
+        None => Ok(true),
+    }
+}
+
+// A value submitted to a ChooseParameter proposal: (voter, value, weight)
+type Submission<T> = (
+    <T as crate::system::SystemConfig>::AccountId,
+    <T as StakingConfig>::Balance,
+    <T as StakingConfig>::Balance,
+);
+
+// Stake-weighted median of (voter, value, weight) submissions: the smallest value at which
+// the cumulative weight reaches half the total. None if there is no weight at all.
+fn weighted_median<T: GovernanceConfig>(submissions: &[Submission<T>]) -> Option<T::Balance> {
+    let mut sorted: Vec<(T::Balance, T::Balance)> = submissions
+        .iter()
+        .map(|(_, value, weight)| (*value, *weight))
+        .collect();
+    sorted.sort_by_key(|(value, _)| *value);
+
+    let total = sorted.iter()
+        .try_fold(T::Balance::zero(), |total, (_, weight)| total.checked_add(weight))?;
+    if total.is_zero() {
+        return None;
+    }
+
+    let mut cumulative = T::Balance::zero();
+    for (value, weight) in sorted {
+        cumulative = cumulative.checked_add(&weight)?;
+        if cumulative.checked_add(&cumulative)? >= total {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+// Each account's free plus staked balance when a proposal was created
+type PowerSnapshot<T> = BTreeMap<<T as crate::system::SystemConfig>::AccountId, <T as StakingConfig>::Balance>;
+
+// Weight of a voter in stake-weighted tallies
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VotingMode {
+    // Every voter weighs one, regardless of stake
+    OneAccountOneVote,
+    // Weight equals stake
+    Linear,
+    // Weight is the square root of stake, damping large holders
+    Quadratic,
+}
+
+// Selects the quorum and approval threshold a proposal is finalized against
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ProposalPriority {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+impl ProposalPriority {
+    // The (quorum, threshold percent) this priority is decided by under `T`
+    pub fn rules<T: GovernanceConfig>(self) -> (u32, u32) {
+        match self {
+            ProposalPriority::Low => (T::LOW_PRIORITY_QUORUM, T::LOW_PRIORITY_THRESHOLD_PERCENT),
+            ProposalPriority::Normal => (T::MIN_QUORUM, T::APPROVAL_THRESHOLD_PERCENT),
+            ProposalPriority::Critical => {
+                (T::CRITICAL_PRIORITY_QUORUM, T::CRITICAL_PRIORITY_THRESHOLD_PERCENT)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum TieBreak {
+    RejectOnTie,
+    ApproveOnTie,
+    // Follow the creator's own vote; rejected if the creator did not vote
+    CreatorDecides,
+}
 
 pub struct Proposal<T: GovernanceConfig> {
     description: String,
     yes_votes: u32,
     no_votes: u32,
+    // Counted towards the quorum only
+    abstain_votes: u32,
     status: ProposalStatus,
     creator: T::AccountId,
+    deposit: T::Balance,
+    kind: ProposalKind<T>,
+    // Block at which the proposal was created
+    created_at: T::BlockNumber,
+    // Block from which votes are accepted
+    voting_start: T::BlockNumber,
+    // Block from which the proposal can be closed
+    deadline: T::BlockNumber,
+    // Proposal that must be approved for this one to be approved
+    depends_on: Option<u32>,
+    // Run by the runtime if the proposal is rejected; the approval action is `kind`
+    on_reject: Option<Rc<dyn ProposalAction<T>>>,
+    // Block at which the proposal was finalized or cancelled
+    closed_at: Option<T::BlockNumber>,
+    // Topics the proposal belongs to, used to route topic-scoped delegations
+    tags: BTreeSet<String>,
+    priority: ProposalPriority,
+    // Voting power fixed at creation by `create_proposal_with_snapshot`
+    power_snapshot: Option<PowerSnapshot<T>>,
+}
+
+// What remains of a proposal once `archive` drops it from the hot map
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProposalSummary {
+    pub proposal_id: u32,
+    pub status: ProposalStatus,
+    pub yes_votes: u32,
+    pub no_votes: u32,
+    pub abstain_votes: u32,
+}
+
+// What an approved proposal does when enacted by the runtime
+pub enum ProposalKind<T: GovernanceConfig> {
+    // Signalling only, nothing is enacted
+    Text,
+    // Change a staking parameter
+    SetParameter(StakingParameter<T>),
+    // Collectively choose a staking parameter: voters submit values, and finalization
+    // picks the stake-weighted median, turning the proposal into `SetParameter`
+    ChooseParameter(StakingParameterKey),
+    // Run arbitrary typed logic against the runtime
+    Action(Rc<dyn ProposalAction<T>>),
+    // Pay `amount` out of the treasury to `to`
+    Spend { to: T::AccountId, amount: T::Balance },
+}
+
+// Logic run by the runtime when an `Action` proposal is approved. `R` is the runtime type,
+// which is also the proposal's config, so actions get typed access to every pallet.
+// Actions are shared through `Rc` so proposals stay cheap to clone for snapshots.
+pub trait ProposalAction<R> {
+    fn execute(&self, runtime: &mut R) -> Result<(), &'static str>;
+}
+
+impl<T: GovernanceConfig> Clone for ProposalKind<T> {
+    fn clone(&self) -> Self {
+        match self {
+            ProposalKind::Text => ProposalKind::Text,
+            ProposalKind::SetParameter(parameter) => ProposalKind::SetParameter(parameter.clone()),
+            ProposalKind::ChooseParameter(key) => ProposalKind::ChooseParameter(*key),
+            ProposalKind::Action(action) => ProposalKind::Action(Rc::clone(action)),
+            ProposalKind::Spend { to, amount } => ProposalKind::Spend { to: to.clone(), amount: *amount },
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ProposalStatus {
+    // Created, but voting opens at `voting_start`
+    Scheduled,
     Active,
     Approved,
     Rejected,
+    // Withdrawn by its creator before finalization
+    Cancelled,
+}
+
+// Proof that a vote was accepted. `sequence` increases across all votes in the pallet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoteReceipt<AccountId> {
+    pub proposal_id: u32,
+    pub voter: AccountId,
+    pub choice: bool,
+    pub sequence: u64,
+}
+
+// The outcome of one vote in a `vote_batch`
+pub type VoteResult<AccountId> = Result<VoteReceipt<AccountId>, &'static str>;
+
+// Flat, display-friendly record of a proposal for audit export
+pub struct AuditRecord<T: GovernanceConfig> {
+    pub proposal_id: u32,
+    pub creator: T::AccountId,
+    pub status: ProposalStatus,
+    pub yes_votes: u32,
+    pub no_votes: u32,
+    // None if the deposit does not fit in a u128
+    pub deposit: Option<u128>,
+    pub created_at: T::BlockNumber,
 }
 
 pub struct GovernancePallet<T: GovernanceConfig> {
-    pub proposals: HashMap<u32, Proposal<T>>,
+    proposals: BTreeMap<u32, Proposal<T>>,
     pub votes: HashMap<(T::AccountId, u32), bool>, // (voter, proposal_id) -> vote_type
+    // Id of the next created proposal. Ids only ever increase: they are never reused, even
+    // after a proposal is cancelled or pruned, so an id always names the same proposal.
     next_proposal_id: u32,
+    // Current block, updated by `on_initialize`
+    current_block: T::BlockNumber,
+    // Deposits waiting to be returned: (creator, amount, block at which it matures)
+    pub refund_queue: Vec<(T::AccountId, T::Balance, T::BlockNumber)>,
+    // Deposits of rejected proposals waiting to be moved to the treasury: (creator, amount)
+    pub forfeit_queue: Vec<(T::AccountId, T::Balance)>,
+    // Accounts allowed to close any proposal, besides its creator
+    closers: BTreeSet<T::AccountId>,
+    // Sequence number of the next accepted vote
+    next_vote_sequence: u64,
+    // Bytes used by all stored descriptions
+    description_bytes: usize,
+    // Client-supplied idempotency keys and the proposal each one created
+    idempotency_keys: BTreeMap<[u8; 32], u32>,
+    // Values submitted to ChooseParameter proposals, per proposal
+    submissions: BTreeMap<u32, Vec<Submission<T>>>,
+    // Votes cast by each account in the current block, reset by `on_initialize`
+    votes_in_block: HashMap<T::AccountId, u32>,
+    // Accounts that abstained on each proposal: (voter, proposal_id)
+    abstentions: HashSet<(T::AccountId, u32)>,
+    // Summaries of proposals dropped from `proposals` by `archive`
+    archived: BTreeMap<u32, ProposalSummary>,
+    // Discussion attached to each proposal: proposal_id -> [(author, text)]
+    comments: BTreeMap<u32, Vec<(T::AccountId, String)>>,
+    // Vote delegations scoped to proposals carrying a tag: (delegator, topic) -> delegate
+    topic_delegations: BTreeMap<(T::AccountId, String), T::AccountId>,
+    // Voting mode in force; starts at VOTING_MODE
+    voting_mode: VotingMode,
+    // Reputation scores; each point adds one percent to the account's vote weight
+    reputation: BTreeMap<T::AccountId, u32>,
+    // Block of each creator's most recent proposal, for PROPOSAL_COOLDOWN_BLOCKS
+    last_proposed: HashMap<T::AccountId, T::BlockNumber>,
+    // Re-proposals and the rejected original each one was copied from: new id -> original id
+    reproposed_from: BTreeMap<u32, u32>,
+    // Outcome of each finalized proposal, per creator, in finalization order
+    creator_history: BTreeMap<T::AccountId, Vec<(u32, ProposalStatus)>>,
+    // Layout version of this state; anything below STORAGE_VERSION awaits `migrate`
+    storage_version: StorageVersion,
+}
+
+// A proposal as stored at storage version 1, before deposits, kinds and scheduling
+pub struct ProposalV1<T: GovernanceConfig> {
+    pub description: String,
+    pub yes_votes: u32,
+    pub no_votes: u32,
+    pub status: ProposalStatus,
+    pub creator: T::AccountId,
+}
+
+impl<T: GovernanceConfig> Clone for Proposal<T> {
+    fn clone(&self) -> Self {
+        Self {
+            description: self.description.clone(),
+            yes_votes: self.yes_votes,
+            no_votes: self.no_votes,
+            abstain_votes: self.abstain_votes,
+            status: self.status.clone(),
+            creator: self.creator.clone(),
+            deposit: self.deposit,
+            kind: self.kind.clone(),
+            created_at: self.created_at,
+            voting_start: self.voting_start,
+            deadline: self.deadline,
+            depends_on: self.depends_on,
+            on_reject: self.on_reject.clone(),
+            closed_at: self.closed_at,
+            tags: self.tags.clone(),
+            priority: self.priority,
+            power_snapshot: self.power_snapshot.clone(),
+        }
+    }
+}
+
+impl<T: GovernanceConfig> Proposal<T> {
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn creator(&self) -> &T::AccountId {
+        &self.creator
+    }
+
+    pub fn yes_votes(&self) -> u32 {
+        self.yes_votes
+    }
+
+    pub fn no_votes(&self) -> u32 {
+        self.no_votes
+    }
+
+    pub fn abstain_votes(&self) -> u32 {
+        self.abstain_votes
+    }
+
+    pub fn status(&self) -> &ProposalStatus {
+        &self.status
+    }
+
+    pub fn deposit(&self) -> T::Balance {
+        self.deposit
+    }
+
+    pub fn kind(&self) -> &ProposalKind<T> {
+        &self.kind
+    }
+
+    pub fn created_at(&self) -> T::BlockNumber {
+        self.created_at
+    }
+
+    pub fn voting_start(&self) -> T::BlockNumber {
+        self.voting_start
+    }
+
+    pub fn deadline(&self) -> T::BlockNumber {
+        self.deadline
+    }
+
+    pub fn depends_on(&self) -> Option<u32> {
+        self.depends_on
+    }
+
+    pub fn on_reject(&self) -> Option<&Rc<dyn ProposalAction<T>>> {
+        self.on_reject.as_ref()
+    }
+
+    pub fn closed_at(&self) -> Option<T::BlockNumber> {
+        self.closed_at
+    }
+
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    pub fn priority(&self) -> ProposalPriority {
+        self.priority
+    }
+
+    // Crate-internal setters for the fields that may change after creation. Setting the kind
+    // does not touch the deposit, so the caller settles `deposit_for` itself. Votes, status,
+    // creator, deposit, description and dependency go through the pallet so its bookkeeping
+    // and checks stay consistent.
+    pub(crate) fn set_kind(&mut self, kind: ProposalKind<T>) {
+        self.kind = kind;
+    }
+
+    pub(crate) fn set_deadline(&mut self, deadline: T::BlockNumber) {
+        self.deadline = deadline;
+    }
+}
+
+impl<T: GovernanceConfig> Clone for GovernancePallet<T> {
+    fn clone(&self) -> Self {
+        Self {
+            proposals: self.proposals.clone(),
+            votes: self.votes.clone(),
+            next_proposal_id: self.next_proposal_id,
+            current_block: self.current_block,
+            refund_queue: self.refund_queue.clone(),
+            forfeit_queue: self.forfeit_queue.clone(),
+            closers: self.closers.clone(),
+            next_vote_sequence: self.next_vote_sequence,
+            description_bytes: self.description_bytes,
+            idempotency_keys: self.idempotency_keys.clone(),
+            submissions: self.submissions.clone(),
+            votes_in_block: self.votes_in_block.clone(),
+            abstentions: self.abstentions.clone(),
+            archived: self.archived.clone(),
+            comments: self.comments.clone(),
+            topic_delegations: self.topic_delegations.clone(),
+            voting_mode: self.voting_mode,
+            reputation: self.reputation.clone(),
+            last_proposed: self.last_proposed.clone(),
+            reproposed_from: self.reproposed_from.clone(),
+            creator_history: self.creator_history.clone(),
+            storage_version: self.storage_version,
+        }
+    }
+}
+
+impl<T: GovernanceConfig> Default for GovernancePallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T: GovernanceConfig> GovernancePallet<T> {
+    // Current layout version of governance state
+    pub const STORAGE_VERSION: StorageVersion = StorageVersion(2);
+
     pub fn new() -> Self {
         Self {
-            proposals: HashMap::new(),
+            proposals: BTreeMap::new(),
             votes: HashMap::new(),
             next_proposal_id: 0,
+            current_block: T::BlockNumber::zero(),
+            refund_queue: Vec::new(),
+            forfeit_queue: Vec::new(),
+            closers: BTreeSet::new(),
+            next_vote_sequence: 0,
+            description_bytes: 0,
+            idempotency_keys: BTreeMap::new(),
+            submissions: BTreeMap::new(),
+            votes_in_block: HashMap::new(),
+            abstentions: HashSet::new(),
+            archived: BTreeMap::new(),
+            comments: BTreeMap::new(),
+            topic_delegations: BTreeMap::new(),
+            voting_mode: T::VOTING_MODE,
+            reputation: BTreeMap::new(),
+            last_proposed: HashMap::new(),
+            reproposed_from: BTreeMap::new(),
+            creator_history: BTreeMap::new(),
+            storage_version: Self::STORAGE_VERSION,
+        }
+    }
+
+    // Construct the pallet after checking the config constants for nonsensical values.
+    // `new` skips these checks.
+    pub fn new_checked() -> Result<Self, &'static str> {
+        // The threshold is exceeded strictly, so 100% could never be reached
+        if T::APPROVAL_THRESHOLD_PERCENT >= 100
+            || T::LOW_PRIORITY_THRESHOLD_PERCENT >= 100
+            || T::CRITICAL_PRIORITY_THRESHOLD_PERCENT >= 100
+        {
+            return Err("Approval threshold out of range");
+        }
+
+        if T::ELECTORATE_APPROVAL_PERCENT > 100 {
+            return Err("Electorate approval percent out of range");
+        }
+
+        if T::MIN_DESCRIPTION_LEN > T::MAX_DESCRIPTION_LEN
+            || T::MAX_DESCRIPTION_LEN > T::MAX_TOTAL_DESC_BYTES
+        {
+            return Err("Description bounds inconsistent");
+        }
+
+        if T::MAX_VOTES_PER_BLOCK == 0 || T::MAX_OPEN_PER_ACCOUNT == 0 || T::MAX_ACTIVE_PROPOSALS == 0 {
+            return Err("Rate limit is zero");
+        }
+
+        if T::MAX_VOTE_WEIGHT.is_some_and(|cap| cap.is_zero()) {
+            return Err("Vote weight cap is zero");
+        }
+
+        Ok(Self::new())
+    }
+
+    // Load proposals stored at version 1. Fields added since then take their defaults, and
+    // bookkeeping v1 did not keep (description budget, id counter, deadlines) is left for
+    // `migrate(1)` to rebuild.
+    pub fn from_v1(proposals: impl IntoIterator<Item = (u32, ProposalV1<T>)>) -> Self {
+        let mut pallet = Self::new();
+        pallet.storage_version = StorageVersion(1);
+
+        for (proposal_id, old) in proposals {
+            let proposal = Proposal {
+                description: old.description,
+                yes_votes: old.yes_votes,
+                no_votes: old.no_votes,
+                abstain_votes: 0,
+                status: old.status,
+                creator: old.creator,
+                deposit: T::Balance::zero(),
+                kind: ProposalKind::Text,
+                created_at: T::BlockNumber::zero(),
+                voting_start: T::BlockNumber::zero(),
+                deadline: T::BlockNumber::zero(),
+                depends_on: None,
+                on_reject: None,
+                closed_at: None,
+                tags: BTreeSet::new(),
+                priority: ProposalPriority::Normal,
+                power_snapshot: None,
+            };
+            pallet.proposals.insert(proposal_id, proposal);
+        }
+
+        pallet
+    }
+
+    // Get the layout version of this state
+    pub fn storage_version(&self) -> StorageVersion {
+        self.storage_version
+    }
+
+    // Upgrade state stored at version `from` to STORAGE_VERSION, one version at a time
+    #[must_use = "a failed migration leaves the state at its old version"]
+    pub fn migrate(&mut self, from: u16) -> Result<(), &'static str> {
+        if StorageVersion(from) != self.storage_version {
+            return Err("Storage version mismatch");
+        }
+
+        if self.storage_version > Self::STORAGE_VERSION {
+            return Err("Unknown storage version");
+        }
+
+        if self.storage_version == StorageVersion(1) {
+            // v2 tracks the description budget, the id counter and voting deadlines. Everything
+            // is computed before anything is written, so a failure leaves the v1 state intact.
+            let next_proposal_id = match self.proposals.last_key_value() {
+                Some((proposal_id, _)) => (*proposal_id).checked_add(1).ok_or("Overflow")?,
+                None => 0,
+            };
+            let deadlines = self.proposals
+                .values()
+                .map(|proposal| proposal.voting_start.checked_add(&T::VOTING_PERIOD).ok_or("Overflow"))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            self.description_bytes = self.proposals.values().map(|p| p.description.len()).sum();
+            self.next_proposal_id = next_proposal_id;
+            for (proposal, deadline) in self.proposals.values_mut().zip(deadlines) {
+                proposal.set_deadline(deadline);
+            }
+            self.storage_version = StorageVersion(2);
+        }
+
+        Ok(())
+    }
+
+    // Build a pallet from proposals imported at genesis, e.g. from another chain's export.
+    // New proposals are numbered above the highest imported id, so an id of u32::MAX is
+    // refused.
+    #[must_use = "a rejected import leaves no pallet to use"]
+    pub fn from_genesis(
+        proposals: impl IntoIterator<Item = (u32, Proposal<T>)>,
+    ) -> Result<Self, &'static str> {
+        let mut pallet = Self::new();
+        for (proposal_id, proposal) in proposals {
+            pallet.description_bytes += proposal.description.len();
+            if let Some(replaced) = pallet.proposals.insert(proposal_id, proposal) {
+                pallet.description_bytes -= replaced.description.len();
+            }
+        }
+
+        pallet.next_proposal_id = match pallet.proposals.last_key_value() {
+            Some((proposal_id, _)) => (*proposal_id).checked_add(1).ok_or("Overflow")?,
+            None => 0,
+        };
+
+        Ok(pallet)
+    }
+
+    // Get the id the next created proposal will receive
+    pub fn next_proposal_id(&self) -> u32 {
+        self.next_proposal_id
+    }
+
+    // Override the next proposal id; for tests and migrations only.
+    // Refuses ids that would collide with a stored proposal.
+    #[must_use = "a rejected id leaves the counter unchanged"]
+    pub fn set_next_proposal_id(&mut self, next_proposal_id: u32) -> Result<(), &'static str> {
+        if self.proposals.range(next_proposal_id..).next().is_some() {
+            return Err("Proposal id already in use");
+        }
+
+        self.next_proposal_id = next_proposal_id;
+        debug_assert!(self.proposal_ids_consistent());
+
+        Ok(())
+    }
+
+    // Invariant: every stored proposal id is below the next proposal id
+    pub fn proposal_ids_consistent(&self) -> bool {
+        self.proposals
+            .last_key_value()
+            .is_none_or(|(proposal_id, _)| *proposal_id < self.next_proposal_id)
+    }
+
+    // Block initialization hook: record the block being executed
+    pub fn on_initialize(&mut self, block: T::BlockNumber) {
+        self.current_block = block;
+        self.votes_in_block.clear();
+
+        // Open voting on scheduled proposals whose start block has arrived
+        for proposal in self.proposals.values_mut() {
+            if proposal.status == ProposalStatus::Scheduled && has_reached(block, proposal.voting_start) {
+                proposal.status = ProposalStatus::Active;
+            }
         }
     }
 
     // Create a new proposal
-    pub fn create_proposal(
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn create_proposal(
+        &mut self,
+        creator: T::AccountId,
+        description: String,
+    ) -> Result<u32, &'static str> {
+        self.create_scheduled_proposal(creator, description, self.current_block)
+    }
+
+    // `create_proposal` on behalf of `creator`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_proposal_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        creator: T::AccountId,
+        description: String,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_proposal(creator, description)
+    }
+
+    // Create a proposal, safe to retry: if a proposal was already created with the same
+    // idempotency key, its id is returned instead of creating a duplicate
+    #[must_use = "the proposal id or the creation error must be handled"]
+    pub(crate) fn create_proposal_with_key(
+        &mut self,
+        creator: T::AccountId,
+        description: String,
+        idempotency_key: Option<[u8; 32]>,
+    ) -> Result<u32, &'static str> {
+        let Some(key) = idempotency_key else {
+            return self.create_proposal(creator, description);
+        };
+
+        if let Some(proposal_id) = self.idempotency_keys.get(&key) {
+            return Ok(*proposal_id);
+        }
+
+        let proposal_id = self.create_proposal(creator, description)?;
+        self.idempotency_keys.insert(key, proposal_id);
+
+        Ok(proposal_id)
+    }
+
+    // `create_proposal_with_key` on behalf of `creator`, which must have signed the call
+    #[must_use = "the proposal id or the creation error must be handled"]
+    pub fn create_proposal_with_key_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        creator: T::AccountId,
+        description: String,
+        idempotency_key: Option<[u8; 32]>,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_proposal_with_key(creator, description, idempotency_key)
+    }
+
+    // Create a proposal that only opens for voting at `voting_start`
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn create_scheduled_proposal(
+        &mut self,
+        creator: T::AccountId,
+        description: String,
+        voting_start: T::BlockNumber,
+    ) -> Result<u32, &'static str> {
+        self.ensure_cooldown_elapsed(&creator)?;
+        self.insert_proposal(creator, description, voting_start)
+    }
+
+    // `create_scheduled_proposal` on behalf of `creator`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_scheduled_proposal_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        creator: T::AccountId,
+        description: String,
+        voting_start: T::BlockNumber,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_scheduled_proposal(creator, description, voting_start)
+    }
+
+    // Check that `creator` is past PROPOSAL_COOLDOWN_BLOCKS since its last proposal
+    fn ensure_cooldown_elapsed(&self, creator: &T::AccountId) -> Result<(), &'static str> {
+        if let Some(last) = self.last_proposed.get(creator) {
+            let ready_at = last.checked_add(&T::PROPOSAL_COOLDOWN_BLOCKS).ok_or("Overflow")?;
+            if !has_reached(self.current_block, ready_at) {
+                return Err("Proposal cooldown active");
+            }
+        }
+
+        Ok(())
+    }
+
+    // Copy a rejected proposal's description and kind into a new active proposal by the same
+    // creator, waiving PROPOSAL_COOLDOWN_BLOCKS. Each original can be re-proposed once, and a
+    // re-proposal cannot itself be re-proposed, so the waiver cannot be chained.
+    // Tags and priority are not carried over; the copy reserves a fresh `deposit_for` its kind.
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn repropose(
+        &mut self,
+        staking: &mut StakingPallet<T>,
+        who: T::AccountId,
+        original_id: u32,
+    ) -> Result<u32, &'static str> {
+        let original = self.proposals.get(&original_id)
+            .ok_or("Proposal does not exist")?;
+
+        if original.creator != who {
+            return Err("Only the creator can re-propose");
+        }
+
+        if original.status != ProposalStatus::Rejected {
+            return Err("Only rejected proposals can be re-proposed");
+        }
+
+        if self.reproposed_from.contains_key(&original_id) {
+            return Err("Cannot re-propose a re-proposal");
+        }
+
+        if self.reproposed_from.values().any(|id| *id == original_id) {
+            return Err("Already re-proposed");
+        }
+
+        let description = original.description.clone();
+        let kind = original.kind.clone();
+        let deposit = T::deposit_for(&kind);
+
+        staking.reserve(who.clone(), deposit)?;
+        let proposal_id = match self.insert_proposal(who.clone(), description, self.current_block) {
+            Ok(proposal_id) => proposal_id,
+            Err(error) => {
+                staking.unreserve(who, deposit)?;
+                return Err(error);
+            }
+        };
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.deposit = deposit;
+            proposal.kind = kind;
+        }
+        self.reproposed_from.insert(proposal_id, original_id);
+
+        Ok(proposal_id)
+    }
+
+    // `repropose` on behalf of `who`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn repropose_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        staking: &mut StakingPallet<T>,
+        who: T::AccountId,
+        original_id: u32,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &who)?;
+        self.repropose(staking, who, original_id)
+    }
+
+    // Get the rejected proposal a re-proposal was copied from
+    pub fn reproposed_from(&self, proposal_id: u32) -> Option<u32> {
+        self.reproposed_from.get(&proposal_id).copied()
+    }
+
+    fn insert_proposal(
         &mut self,
         creator: T::AccountId,
         description: String,
+        voting_start: T::BlockNumber,
     ) -> Result<u32, &'static str> {
+        self.ensure_insertable(&creator, &description, None)?;
+
         let proposal_id = self.next_proposal_id;
+        let deadline = voting_start.checked_add(&T::VOTING_PERIOD)
+            .ok_or("Overflow")?;
+        let status = if has_reached(self.current_block, voting_start) {
+            ProposalStatus::Active
+        } else {
+            ProposalStatus::Scheduled
+        };
 
+        self.description_bytes += description.len();
+        self.last_proposed.insert(creator.clone(), self.current_block);
         self.proposals.insert(
             proposal_id,
             Proposal {
                 description,
                 yes_votes: 0,
                 no_votes: 0,
-                status: ProposalStatus::Active,
-                creator
+                abstain_votes: 0,
+                status,
+                creator,
+                deposit: T::Balance::zero(),
+                kind: ProposalKind::Text,
+                created_at: self.current_block,
+                voting_start,
+                deadline,
+                depends_on: None,
+                on_reject: None,
+                closed_at: None,
+                tags: BTreeSet::new(),
+                priority: ProposalPriority::Normal,
+                power_snapshot: None,
             },
         );
 
@@ -58,99 +906,3955 @@ impl<T: GovernanceConfig> GovernancePallet<T> {
         Ok(proposal_id)
     }
 
-    // Vote on a proposal (true = yes, false = no)
-    pub fn vote(
-        &mut self,
-        voter: T::AccountId,
-        proposal_id: u32,
-        vote_type: bool,
+    // Check that `creator` may open a proposal with `description`, counting the slot and
+    // description bytes of the `displaced` proposal as already freed
+    fn ensure_insertable(
+        &self,
+        creator: &T::AccountId,
+        description: &str,
+        displaced: Option<u32>,
     ) -> Result<(), &'static str> {
-         let proposal = self.proposals.get_mut(&proposal_id)
-            .ok_or("Proposal does not exist")?;
-
-        if !matches!(proposal.status, ProposalStatus::Active) {
-            return Err("Cannot vote on finalized proposal");
+        if !T::is_valid_account(creator) {
+            return Err("Invalid account");
         }
 
-        let vote_key = (voter.clone(), proposal_id);
+        let displaced = displaced.and_then(|proposal_id| self.proposals.get(&proposal_id));
+        self.validate_description(description, displaced.map_or(0, |p| p.description.len()))?;
 
-        if self.votes.insert(vote_key, vote_type).is_some() {
-            return Err("Voter has already voted on this proposal");
+        if self.proposals_open_by(creator) >= T::MAX_OPEN_PER_ACCOUNT {
+            return Err("Too many open proposals");
         }
 
-        if vote_type {
-            proposal.yes_votes += 1;
-        } else {
-            proposal.no_votes += 1;
+        if self.open_proposals().saturating_sub(displaced.map_or(0, |_| 1)) >= T::MAX_ACTIVE_PROPOSALS {
+            return Err("Too many active proposals");
         }
 
         Ok(())
     }
 
-    // Get proposal details
-    pub fn get_proposal(&self, proposal_id: u32) -> Option<&Proposal<T>> {
-        self.proposals.get(&proposal_id)
+    // Get mutable access to a proposal for other pallets in the crate; only the
+    // `Proposal::set_*` setters are available through it
+    pub(crate) fn proposal_mut(&mut self, proposal_id: u32) -> Option<&mut Proposal<T>> {
+        self.proposals.get_mut(&proposal_id)
     }
 
-    // Finalize a proposal (changes status based on votes)
-    pub fn finalize_proposal(&mut self, proposal_id: u32) -> Result<ProposalStatus, &'static str> {
-        let proposal = self.proposals
-            .get_mut(&proposal_id)
-            .ok_or("Proposal does not exist")?;
-
-        if !matches!(proposal.status, ProposalStatus::Active) {
-            return Err("Cannot vote on finalized proposal");
-        }
-
-        proposal.status = if proposal.yes_votes > proposal.no_votes {
-            ProposalStatus::Approved
-        } else {
-            ProposalStatus::Rejected
-        };
+    // Count proposals from every account that are still active or scheduled
+    pub fn open_proposals(&self) -> u32 {
+        self.proposals
+            .values()
+            .filter(|proposal| matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled))
+            .count() as u32
+    }
 
-        Ok(proposal.status.clone())
+    // Count an account's proposals that are still active or scheduled
+    pub fn proposals_open_by(&self, who: &T::AccountId) -> u32 {
+        self.proposals
+            .values()
+            .filter(|proposal| {
+                proposal.creator == *who
+                    && matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled)
+            })
+            .count() as u32
     }
-}
 
-#[cfg(test)]
+    // Create a proposal that changes a staking parameter once approved and enacted
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn create_parameter_proposal(
+        &mut self,
+        creator: T::AccountId,
+        description: String,
+        parameter: StakingParameter<T>,
+    ) -> Result<u32, &'static str> {
+        let proposal_id = self.create_proposal(creator, description)?;
+        if let Some(proposal) = self.proposal_mut(proposal_id) {
+            proposal.set_kind(ProposalKind::SetParameter(parameter));
+        }
+
+        Ok(proposal_id)
+    }
+
+    // `create_parameter_proposal` on behalf of `creator`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_parameter_proposal_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        creator: T::AccountId,
+        description: String,
+        parameter: StakingParameter<T>,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_parameter_proposal(creator, description, parameter)
+    }
+
+    // Create a proposal that pays `amount` from the treasury to `to` once approved and enacted
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn create_spend_proposal(
+        &mut self,
+        creator: T::AccountId,
+        description: String,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<u32, &'static str> {
+        let proposal_id = self.create_proposal(creator, description)?;
+        if let Some(proposal) = self.proposal_mut(proposal_id) {
+            proposal.set_kind(ProposalKind::Spend { to, amount });
+        }
+
+        Ok(proposal_id)
+    }
+
+    // `create_spend_proposal` on behalf of `creator`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_spend_proposal_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        creator: T::AccountId,
+        description: String,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_spend_proposal(creator, description, to, amount)
+    }
+
+    // Create a proposal that runs `action` against the runtime when approved
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn create_action_proposal(
+        &mut self,
+        creator: T::AccountId,
+        description: String,
+        action: impl ProposalAction<T> + 'static,
+    ) -> Result<u32, &'static str> {
+        let proposal_id = self.create_proposal(creator, description)?;
+        if let Some(proposal) = self.proposal_mut(proposal_id) {
+            proposal.set_kind(ProposalKind::Action(Rc::new(action)));
+        }
+
+        Ok(proposal_id)
+    }
+
+    // `create_action_proposal` on behalf of `creator`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_action_proposal_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        creator: T::AccountId,
+        description: String,
+        action: impl ProposalAction<T> + 'static,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_action_proposal(creator, description, action)
+    }
+
+    // Attach an action for the runtime to run if the proposal is rejected, e.g. cleanup.
+    // Only the creator may set it, and only while the proposal is open.
+    #[must_use = "a rejected action is not attached"]
+    pub(crate) fn set_on_reject(
+        &mut self,
+        who: T::AccountId,
+        proposal_id: u32,
+        action: impl ProposalAction<T> + 'static,
+    ) -> Result<(), &'static str> {
+        let proposal = self.proposals.get_mut(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if proposal.creator != who {
+            return Err("Only the creator can set the reject action");
+        }
+
+        if !matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled) {
+            return Err("Cannot modify finalized proposal");
+        }
+
+        proposal.on_reject = Some(Rc::new(action));
+
+        Ok(())
+    }
+
+    // `set_on_reject` on behalf of `who`, which must have signed the call
+    #[must_use = "a rejected action is not attached"]
+    pub fn set_on_reject_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        who: T::AccountId,
+        proposal_id: u32,
+        action: impl ProposalAction<T> + 'static,
+    ) -> Result<(), &'static str> {
+        ensure_signed_by(&origin, &who)?;
+        self.set_on_reject(who, proposal_id, action)
+    }
+
+    // Create a proposal whose parameter value is chosen by the voters' submissions
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn create_choice_proposal(
+        &mut self,
+        creator: T::AccountId,
+        description: String,
+        key: StakingParameterKey,
+    ) -> Result<u32, &'static str> {
+        let proposal_id = self.create_proposal(creator, description)?;
+        if let Some(proposal) = self.proposal_mut(proposal_id) {
+            proposal.set_kind(ProposalKind::ChooseParameter(key));
+        }
+
+        Ok(proposal_id)
+    }
+
+    // `create_choice_proposal` on behalf of `creator`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_choice_proposal_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        creator: T::AccountId,
+        description: String,
+        key: StakingParameterKey,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_choice_proposal(creator, description, key)
+    }
+
+    // Submit a preferred value to a ChooseParameter proposal, weighted by the voter's stake
+    #[must_use = "a rejected submission is not counted"]
+    pub(crate) fn submit_value(
+        &mut self,
+        staking: &impl BalanceProvider<T>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        value: T::Balance,
+    ) -> Result<(), &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if !matches!(proposal.kind, ProposalKind::ChooseParameter(_)) {
+            return Err("Proposal does not accept value submissions");
+        }
+
+        if !matches!(proposal.status, ProposalStatus::Active) {
+            return Err("Cannot vote on finalized proposal");
+        }
+
+        let weight = self.vote_weight(staking, &voter);
+        let submissions = self.submissions.entry(proposal_id).or_default();
+        if submissions.iter().any(|(who, _, _)| *who == voter) {
+            return Err("Voter has already submitted a value");
+        }
+
+        submissions.push((voter, value, weight));
+
+        Ok(())
+    }
+
+    // `submit_value` on behalf of `voter`, which must have signed the call
+    #[must_use = "a rejected submission is not counted"]
+    pub fn submit_value_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        staking: &impl BalanceProvider<T>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        value: T::Balance,
+    ) -> Result<(), &'static str> {
+        ensure_signed_by(&origin, &voter)?;
+        self.submit_value(staking, voter, proposal_id, value)
+    }
+
+    // Create a proposal that can only be approved if `depends_on` was approved first
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn create_dependent_proposal(
+        &mut self,
+        creator: T::AccountId,
+        description: String,
+        depends_on: u32,
+    ) -> Result<u32, &'static str> {
+        if depends_on == self.next_proposal_id {
+            return Err("Proposal cannot depend on itself");
+        }
+
+        if !self.proposals.contains_key(&depends_on) {
+            return Err("Dependency does not exist");
+        }
+
+        let proposal_id = self.create_proposal(creator, description)?;
+        self.set_depends_on(proposal_id, Some(depends_on))?;
+
+        Ok(proposal_id)
+    }
+
+    // `create_dependent_proposal` on behalf of `creator`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_dependent_proposal_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        creator: T::AccountId,
+        description: String,
+        depends_on: u32,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_dependent_proposal(creator, description, depends_on)
+    }
+
+    // Make a proposal's approval wait on `depends_on`, or clear the dependency with None.
+    // The dependency must be another existing proposal.
+    pub(crate) fn set_depends_on(
+        &mut self,
+        proposal_id: u32,
+        depends_on: Option<u32>,
+    ) -> Result<(), &'static str> {
+        if let Some(dependency) = depends_on {
+            if dependency == proposal_id {
+                return Err("Proposal cannot depend on itself");
+            }
+
+            if !self.proposals.contains_key(&dependency) {
+                return Err("Dependency does not exist");
+            }
+        }
+
+        let proposal = self.proposals.get_mut(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+        proposal.depends_on = depends_on;
+
+        Ok(())
+    }
+
+    // Create a proposal whose stake-weighted tally uses every account's free plus staked
+    // balance as of now, so balances moved between accounts afterwards cannot buy votes
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn create_proposal_with_snapshot(
+        &mut self,
+        staking: &StakingPallet<T>,
+        creator: T::AccountId,
+        description: String,
+    ) -> Result<u32, &'static str> {
+        let mut snapshot = PowerSnapshot::<T>::new();
+        for (who, amount) in staking.free_balances.iter().chain(staking.staked_balances.iter()) {
+            if amount.is_zero() {
+                continue;
+            }
+            let power = snapshot.get(who).copied().unwrap_or(T::Balance::zero())
+                .checked_add(amount)
+                .ok_or("Overflow")?;
+            snapshot.insert(who.clone(), power);
+        }
+
+        let proposal_id = self.create_proposal(creator, description)?;
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.power_snapshot = Some(snapshot);
+        }
+
+        Ok(proposal_id)
+    }
+
+    // `create_proposal_with_snapshot` on behalf of `creator`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_proposal_with_snapshot_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        staking: &StakingPallet<T>,
+        creator: T::AccountId,
+        description: String,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_proposal_with_snapshot(staking, creator, description)
+    }
+
+    // Create a new text proposal, reserving its `deposit_for` from the creator's free balance
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn create_proposal_with_deposit(
+        &mut self,
+        staking: &mut StakingPallet<T>,
+        creator: T::AccountId,
+        description: String,
+    ) -> Result<u32, &'static str> {
+        self.create_kind_with_deposit(staking, creator, description, ProposalKind::Text)
+    }
+
+    // `create_proposal_with_deposit` on behalf of `creator`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_proposal_with_deposit_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        staking: &mut StakingPallet<T>,
+        creator: T::AccountId,
+        description: String,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_proposal_with_deposit(staking, creator, description)
+    }
+
+    // Create a new proposal of `kind`, reserving `deposit_for(kind)` from the creator's free
+    // balance. With BUMP_BY_STAKE, a full queue makes room by cancelling the open proposal
+    // of the lowest-staked creator (the newest on ties), if that creator stakes less.
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub(crate) fn create_kind_with_deposit(
+        &mut self,
+        staking: &mut StakingPallet<T>,
+        creator: T::AccountId,
+        description: String,
+        kind: ProposalKind<T>,
+    ) -> Result<u32, &'static str> {
+        let deposit = T::deposit_for(&kind);
+
+        let displaced = if T::BUMP_BY_STAKE && self.open_proposals() >= T::MAX_ACTIVE_PROPOSALS {
+            self.lowest_staked_open(staking)
+                .filter(|(_, stake)| *stake < staking.get_staked_balance_ref(&creator))
+                .map(|(proposal_id, _)| proposal_id)
+        } else {
+            None
+        };
+
+        // Every check runs before anyone is displaced, so a rejected proposal cancels nothing
+        self.ensure_cooldown_elapsed(&creator)?;
+        self.ensure_insertable(&creator, &description, displaced)?;
+
+        staking.reserve(creator.clone(), deposit)?;
+        if let Some(proposal_id) = displaced {
+            if let Err(error) = self.close_as_cancelled(staking, proposal_id) {
+                staking.unreserve(creator, deposit)?;
+                return Err(error);
+            }
+        }
+
+        // Hand the deposit back if the proposal cannot be created after all
+        let proposal_id = match self.create_proposal(creator.clone(), description) {
+            Ok(proposal_id) => proposal_id,
+            Err(error) => {
+                staking.unreserve(creator, deposit)?;
+                return Err(error);
+            }
+        };
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.deposit = deposit;
+            proposal.kind = kind;
+        }
+
+        Ok(proposal_id)
+    }
+
+    // `create_kind_with_deposit` on behalf of `creator`, which must have signed the call
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_kind_with_deposit_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        staking: &mut StakingPallet<T>,
+        creator: T::AccountId,
+        description: String,
+        kind: ProposalKind<T>,
+    ) -> Result<u32, &'static str> {
+        ensure_signed_by(&origin, &creator)?;
+        self.create_kind_with_deposit(staking, creator, description, kind)
+    }
+
+    // Check whether anyone has voted, abstained or submitted a value on a proposal
+    fn has_participation(&self, proposal_id: u32) -> bool {
+        self.proposals.get(&proposal_id).is_some_and(|proposal| {
+            proposal.yes_votes > 0 || proposal.no_votes > 0 || proposal.abstain_votes > 0
+        }) || self.submissions.get(&proposal_id).is_some_and(|submissions| !submissions.is_empty())
+    }
+
+    // Edit the description of an active proposal; only its creator may do so, before any votes
+    #[must_use = "a rejected edit leaves the description unchanged"]
+    pub(crate) fn edit_description(
+        &mut self,
+        who: T::AccountId,
+        proposal_id: u32,
+        new_description: String,
+    ) -> Result<(), &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if proposal.creator != who {
+            return Err("Only the creator can edit the proposal");
+        }
+
+        if !matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled) {
+            return Err("Cannot edit finalized proposal");
+        }
+
+        if self.has_participation(proposal_id) {
+            return Err("Cannot edit proposal after votes were cast");
+        }
+
+        let old_len = proposal.description.len();
+        self.validate_description(&new_description, old_len)?;
+
+        self.description_bytes = self.description_bytes - old_len + new_description.len();
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.description = new_description;
+        }
+
+        Ok(())
+    }
+
+    // `edit_description` on behalf of `who`, which must have signed the call
+    #[must_use = "a rejected edit leaves the description unchanged"]
+    pub fn edit_description_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        who: T::AccountId,
+        proposal_id: u32,
+        new_description: String,
+    ) -> Result<(), &'static str> {
+        ensure_signed_by(&origin, &who)?;
+        self.edit_description(who, proposal_id, new_description)
+    }
+
+    // Tag a proposal with a topic; only its creator may do so, before any votes, since tags
+    // decide where topic delegations route voting power
+    #[must_use = "a rejected tag leaves the proposal unchanged"]
+    pub(crate) fn tag_proposal(
+        &mut self,
+        who: T::AccountId,
+        proposal_id: u32,
+        tag: String,
+    ) -> Result<(), &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if proposal.creator != who {
+            return Err("Only the creator can tag the proposal");
+        }
+
+        if !matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled) {
+            return Err("Cannot tag finalized proposal");
+        }
+
+        if self.has_participation(proposal_id) {
+            return Err("Cannot tag proposal after votes were cast");
+        }
+
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.tags.insert(tag);
+        }
+
+        Ok(())
+    }
+
+    // `tag_proposal` on behalf of `who`, which must have signed the call
+    #[must_use = "a rejected tag leaves the proposal unchanged"]
+    pub fn tag_proposal_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        who: T::AccountId,
+        proposal_id: u32,
+        tag: String,
+    ) -> Result<(), &'static str> {
+        ensure_signed_by(&origin, &who)?;
+        self.tag_proposal(who, proposal_id, tag)
+    }
+
+    // Set the priority a proposal is finalized under; like tags, only its creator may do so,
+    // before any votes, so the rules cannot change once voting is underway
+    #[must_use = "a rejected priority change leaves the proposal unchanged"]
+    pub(crate) fn set_priority(
+        &mut self,
+        who: T::AccountId,
+        proposal_id: u32,
+        priority: ProposalPriority,
+    ) -> Result<(), &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if proposal.creator != who {
+            return Err("Only the creator can set the priority");
+        }
+
+        if !matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled) {
+            return Err("Cannot set priority of finalized proposal");
+        }
+
+        if self.has_participation(proposal_id) {
+            return Err("Cannot set priority after votes were cast");
+        }
+
+        if let Some(proposal) = self.proposals.get_mut(&proposal_id) {
+            proposal.priority = priority;
+        }
+
+        Ok(())
+    }
+
+    // `set_priority` on behalf of `who`, which must have signed the call
+    #[must_use = "a rejected priority change leaves the proposal unchanged"]
+    pub fn set_priority_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        who: T::AccountId,
+        proposal_id: u32,
+        priority: ProposalPriority,
+    ) -> Result<(), &'static str> {
+        ensure_signed_by(&origin, &who)?;
+        self.set_priority(who, proposal_id, priority)
+    }
+
+    // Cancel an active or scheduled proposal; only its creator may do so.
+    // The description is dropped to free its budget and any deposit is queued for refund.
+    // Votes on it are dropped and the stake they locked is released; conviction locks run
+    // to their own expiry.
+    #[must_use = "a rejected cancellation leaves the proposal unchanged"]
+    pub(crate) fn cancel_proposal(
+        &mut self,
+        staking: &mut StakingPallet<T>,
+        who: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<(), &'static str> {
+        let proposal = self.proposals.get_mut(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if proposal.creator != who {
+            return Err("Only the creator can cancel the proposal");
+        }
+
+        if !matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled) {
+            return Err("Cannot cancel finalized proposal");
+        }
+
+        self.close_as_cancelled(staking, proposal_id)
+    }
+
+    // `cancel_proposal` on behalf of `who`, which must have signed the call
+    #[must_use = "a rejected cancellation leaves the proposal unchanged"]
+    pub fn cancel_proposal_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        staking: &mut StakingPallet<T>,
+        who: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<(), &'static str> {
+        ensure_signed_by(&origin, &who)?;
+        self.cancel_proposal(staking, who, proposal_id)
+    }
+
+    // Get the open proposal whose creator has the least stake, preferring the newest on
+    // ties, with that stake
+    fn lowest_staked_open(&self, staking: &StakingPallet<T>) -> Option<(u32, T::Balance)> {
+        self.proposals
+            .iter()
+            .rev()
+            .filter(|(_, proposal)| matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled))
+            .map(|(proposal_id, proposal)| (*proposal_id, staking.get_staked_balance_ref(&proposal.creator)))
+            .reduce(|lowest, candidate| if candidate.1 < lowest.1 { candidate } else { lowest })
+    }
+
+    // Mark an open proposal cancelled: free its description budget, queue its deposit for
+    // refund, drop its votes, abstentions and submissions and release the stake the votes locked
+    fn close_as_cancelled(
+        &mut self,
+        staking: &mut StakingPallet<T>,
+        proposal_id: u32,
+    ) -> Result<(), &'static str> {
+        let proposal = self.proposals.get_mut(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if !proposal.deposit.is_zero() {
+            let refund_at = self.current_block.checked_add(&T::REFUND_DELAY)
+                .ok_or("Overflow")?;
+            self.refund_queue.push((proposal.creator.clone(), proposal.deposit, refund_at));
+        }
+
+        self.description_bytes -= proposal.description.len();
+        proposal.description = String::new();
+        proposal.closed_at = Some(self.current_block);
+        proposal.status = ProposalStatus::Cancelled;
+
+        self.votes.retain(|(voter, id), _| {
+            if *id != proposal_id {
+                return true;
+            }
+            staking.unlock_vote(voter.clone(), proposal_id);
+            false
+        });
+        self.abstentions.retain(|(_, id)| *id != proposal_id);
+        self.submissions.remove(&proposal_id);
+
+        Ok(())
+    }
+
+    // Remove a finalized or cancelled proposal from storage, freeing its description budget
+    // along with its votes, abstentions, submissions and the voters' vote locks
+    #[must_use = "a rejected prune leaves the proposal stored"]
+    pub fn prune_proposal(
+        &mut self,
+        staking: &mut StakingPallet<T>,
+        proposal_id: u32,
+    ) -> Result<(), &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled) {
+            return Err("Cannot prune open proposal");
+        }
+
+        self.description_bytes -= proposal.description.len();
+        self.proposals.remove(&proposal_id);
+        self.comments.remove(&proposal_id);
+        self.submissions.remove(&proposal_id);
+        self.abstentions.retain(|(_, id)| *id != proposal_id);
+        self.votes.retain(|(voter, id), _| {
+            if *id != proposal_id {
+                return true;
+            }
+            staking.unlock_vote(voter.clone(), proposal_id);
+            false
+        });
+
+        Ok(())
+    }
+
+    // Replace every proposal closed at least `retain_blocks` before `current_block` with a
+    // compact summary, bounding the growth of the hot proposal map. Proposals without a
+    // recorded close block (e.g. migrated from v1) count as closed at block zero.
+    // Returns the number of proposals archived; a summary is only stored once its proposal
+    // has been pruned.
+    #[must_use = "a failed prune stops archiving and must be handled"]
+    pub fn archive(
+        &mut self,
+        staking: &mut StakingPallet<T>,
+        current_block: T::BlockNumber,
+        retain_blocks: T::BlockNumber,
+    ) -> Result<usize, &'static str> {
+        let expired: Vec<u32> = self.proposals
+            .iter()
+            .filter(|(_, proposal)| {
+                !matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled)
+                    && proposal.closed_at
+                        .unwrap_or(T::BlockNumber::zero())
+                        .checked_add(&retain_blocks)
+                        .is_some_and(|expiry| has_reached(current_block, expiry))
+            })
+            .map(|(proposal_id, _)| *proposal_id)
+            .collect();
+
+        for proposal_id in expired.iter() {
+            let proposal = &self.proposals[proposal_id];
+            let summary = ProposalSummary {
+                proposal_id: *proposal_id,
+                status: proposal.status.clone(),
+                yes_votes: proposal.yes_votes,
+                no_votes: proposal.no_votes,
+                abstain_votes: proposal.abstain_votes,
+            };
+            self.prune_proposal(staking, *proposal_id)?;
+            self.archived.insert(*proposal_id, summary);
+        }
+
+        Ok(expired.len())
+    }
+
+    // Get the summary of an archived proposal
+    pub fn archived_summary(&self, proposal_id: u32) -> Option<&ProposalSummary> {
+        self.archived.get(&proposal_id)
+    }
+
+    // Attach a comment to a proposal's discussion
+    #[must_use = "a rejected comment is not stored"]
+    pub(crate) fn add_comment(
+        &mut self,
+        who: T::AccountId,
+        proposal_id: u32,
+        text: String,
+    ) -> Result<(), &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if matches!(proposal.status, ProposalStatus::Cancelled) {
+            return Err("Cannot comment on cancelled proposal");
+        }
+
+        if text.is_empty() {
+            return Err("Comment is empty");
+        }
+
+        if text.len() > T::MAX_COMMENT_LEN {
+            return Err("Comment too long");
+        }
+
+        let comments = self.comments.entry(proposal_id).or_default();
+        if comments.len() >= T::MAX_COMMENTS_PER_PROPOSAL {
+            return Err("Too many comments on proposal");
+        }
+
+        comments.push((who, text));
+
+        Ok(())
+    }
+
+    // `add_comment` on behalf of `who`, which must have signed the call
+    #[must_use = "a rejected comment is not stored"]
+    pub fn add_comment_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        who: T::AccountId,
+        proposal_id: u32,
+        text: String,
+    ) -> Result<(), &'static str> {
+        ensure_signed_by(&origin, &who)?;
+        self.add_comment(who, proposal_id, text)
+    }
+
+    // Get a proposal's comments, oldest first
+    pub fn comments_for(&self, proposal_id: u32) -> &[(T::AccountId, String)] {
+        self.comments.get(&proposal_id).map_or(&[], |comments| comments.as_slice())
+    }
+
+    // Get the bytes used by all stored descriptions
+    pub fn description_bytes(&self) -> usize {
+        self.description_bytes
+    }
+
+    // Check a description's length bounds, and that it fits the total budget once it
+    // replaces a description of `replacing` bytes
+    fn validate_description(
+        &self,
+        description: &str,
+        replacing: usize,
+    ) -> Result<(), &'static str> {
+        if description.len() < T::MIN_DESCRIPTION_LEN {
+            return Err("Description too short");
+        }
+
+        if description.len() > T::MAX_DESCRIPTION_LEN {
+            return Err("Description too long");
+        }
+
+        if self.description_bytes - replacing + description.len() > T::MAX_TOTAL_DESC_BYTES {
+            return Err("Description budget exceeded");
+        }
+
+        Ok(())
+    }
+
+    // Vote on a proposal (true = yes, false = no), returning a receipt for the accepted vote
+    #[must_use = "a rejected vote is not counted"]
+    pub(crate) fn vote(
+        &mut self,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+    ) -> Result<VoteReceipt<T::AccountId>, &'static str> {
+        self.admit_vote(&voter, proposal_id)?;
+
+        let proposal = self.proposals.get_mut(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+        self.votes.insert((voter.clone(), proposal_id), vote_type);
+
+        if vote_type {
+            proposal.yes_votes += 1;
+        } else {
+            proposal.no_votes += 1;
+        }
+
+        let sequence = self.next_vote_sequence;
+        self.next_vote_sequence += 1;
+
+        Ok(VoteReceipt {
+            proposal_id,
+            voter,
+            choice: vote_type,
+            sequence,
+        })
+    }
+
+    // Vote on behalf of `voter`, which must be the account that signed the call
+    #[must_use = "a rejected vote is not counted"]
+    pub fn vote_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+    ) -> Result<VoteReceipt<T::AccountId>, &'static str> {
+        ensure_signed_by(&origin, &voter)?;
+        self.vote(voter, proposal_id, vote_type)
+    }
+
+    // Vote only if the proposal already has at least `min_existing_votes` votes, counting
+    // abstentions, so a voter can avoid committing to a low-turnout proposal
+    #[must_use = "a rejected vote is not counted"]
+    pub(crate) fn vote_if(
+        &mut self,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+        min_existing_votes: u32,
+    ) -> Result<VoteReceipt<T::AccountId>, &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        let existing = proposal.yes_votes as u64 + proposal.no_votes as u64 + proposal.abstain_votes as u64;
+        if existing < min_existing_votes as u64 {
+            return Err("Participation precondition not met");
+        }
+
+        self.vote(voter, proposal_id, vote_type)
+    }
+
+    // `vote_if` on behalf of `voter`, which must have signed the call
+    #[must_use = "a rejected vote is not counted"]
+    pub fn vote_if_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+        min_existing_votes: u32,
+    ) -> Result<VoteReceipt<T::AccountId>, &'static str> {
+        ensure_signed_by(&origin, &voter)?;
+        self.vote_if(voter, proposal_id, vote_type, min_existing_votes)
+    }
+
+    // Preview the (yes, no) tally a new vote of `choice` would produce, without casting it.
+    // None if the proposal does not exist or is not open for voting.
+    pub fn preview_vote(&self, proposal_id: u32, choice: bool) -> Option<(u32, u32)> {
+        let proposal = self.proposals.get(&proposal_id)?;
+
+        if !matches!(proposal.status, ProposalStatus::Active) {
+            return None;
+        }
+
+        if choice {
+            Some((proposal.yes_votes.checked_add(1)?, proposal.no_votes))
+        } else {
+            Some((proposal.yes_votes, proposal.no_votes.checked_add(1)?))
+        }
+    }
+
+    // Apply several votes on one proposal as if they arrived together. So the outcome never
+    // depends on submission order, votes are applied in ascending account-id order (an
+    // account's repeated votes no before yes, so only the first counts); the results come
+    // back in that same order. A failing vote does not stop the rest. Voting for other
+    // accounts is root only.
+    #[must_use = "the per-vote results must be handled"]
+    pub fn vote_batch(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        proposal_id: u32,
+        mut votes: Vec<(T::AccountId, bool)>,
+    ) -> Result<Vec<VoteResult<T::AccountId>>, &'static str> {
+        ensure_root(&origin)?;
+        votes.sort();
+
+        Ok(votes
+            .into_iter()
+            .map(|(voter, vote_type)| self.vote(voter, proposal_id, vote_type))
+            .collect())
+    }
+
+    // Abstain on a proposal: counts towards its quorum but not towards the yes/no split
+    #[must_use = "a rejected abstention is not counted"]
+    pub(crate) fn abstain(&mut self, voter: T::AccountId, proposal_id: u32) -> Result<(), &'static str> {
+        self.admit_vote(&voter, proposal_id)?;
+
+        let proposal = self.proposals.get_mut(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+        proposal.abstain_votes += 1;
+        self.abstentions.insert((voter, proposal_id));
+
+        Ok(())
+    }
+
+    // `abstain` on behalf of `voter`, which must have signed the call
+    #[must_use = "a rejected abstention is not counted"]
+    pub fn abstain_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        voter: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<(), &'static str> {
+        ensure_signed_by(&origin, &voter)?;
+        self.abstain(voter, proposal_id)
+    }
+
+    // Check that `voter` may vote on the proposal now, and count it against the
+    // per-block limit
+    fn admit_vote(&mut self, voter: &T::AccountId, proposal_id: u32) -> Result<(), &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if matches!(proposal.status, ProposalStatus::Scheduled) {
+            return Err("Voting not yet started");
+        }
+
+        if !matches!(proposal.status, ProposalStatus::Active) {
+            return Err("Cannot vote on finalized proposal");
+        }
+
+        let vote_key = (voter.clone(), proposal_id);
+
+        if self.votes.contains_key(&vote_key) || self.abstentions.contains(&vote_key) {
+            return Err("Voter has already voted on this proposal");
+        }
+
+        let votes_in_block = self.votes_in_block.entry(voter.clone()).or_insert(0);
+        if *votes_in_block >= T::MAX_VOTES_PER_BLOCK {
+            return Err("Too many votes in this block");
+        }
+
+        *votes_in_block += 1;
+
+        Ok(())
+    }
+
+    // Vote on a proposal, locking the voter's whole staked balance until the vote is released
+    #[must_use = "a rejected vote is not counted"]
+    pub(crate) fn vote_with_stake(
+        &mut self,
+        staking: &mut StakingPallet<T>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+    ) -> Result<VoteReceipt<T::AccountId>, &'static str> {
+        let receipt = self.vote(voter.clone(), proposal_id, vote_type)?;
+
+        let staked = staking.get_staked_balance_ref(&voter);
+        staking.lock_for_vote(voter, proposal_id, staked)?;
+
+        Ok(receipt)
+    }
+
+    // `vote_with_stake` on behalf of `voter`, which must have signed the call
+    #[must_use = "a rejected vote is not counted"]
+    pub fn vote_with_stake_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        staking: &mut StakingPallet<T>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+    ) -> Result<VoteReceipt<T::AccountId>, &'static str> {
+        ensure_signed_by(&origin, &voter)?;
+        self.vote_with_stake(staking, voter, proposal_id, vote_type)
+    }
+
+    // Vote and lock the voter's whole stake for `lock_blocks` past the current block,
+    // regardless of when the proposal finalizes. Expired locks are released by
+    // `StakingPallet::process_vote_locks`.
+    #[must_use = "a rejected vote is not counted"]
+    pub(crate) fn vote_with_conviction(
+        &mut self,
+        staking: &mut StakingPallet<T>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+        lock_blocks: T::BlockNumber,
+    ) -> Result<VoteReceipt<T::AccountId>, &'static str> {
+        let expires_at = self.current_block.checked_add(&lock_blocks)
+            .ok_or("Overflow")?;
+        let staked = staking.get_staked_balance_ref(&voter);
+        if staked.is_zero() {
+            return Err("Insufficient staked balance");
+        }
+
+        let receipt = self.vote(voter.clone(), proposal_id, vote_type)?;
+        staking.lock_until(voter, staked, expires_at)?;
+
+        Ok(receipt)
+    }
+
+    // `vote_with_conviction` on behalf of `voter`, which must have signed the call
+    #[must_use = "a rejected vote is not counted"]
+    pub fn vote_with_conviction_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        staking: &mut StakingPallet<T>,
+        voter: T::AccountId,
+        proposal_id: u32,
+        vote_type: bool,
+        lock_blocks: T::BlockNumber,
+    ) -> Result<VoteReceipt<T::AccountId>, &'static str> {
+        ensure_signed_by(&origin, &voter)?;
+        self.vote_with_conviction(staking, voter, proposal_id, vote_type, lock_blocks)
+    }
+
+    // Release every vote lock on a finalized proposal.
+    // Returns the number of locks released.
+    #[must_use = "a failed release leaves the stake locked"]
+    pub fn release_vote_locks(
+        &self,
+        staking: &mut StakingPallet<T>,
+        proposal_id: u32,
+    ) -> Result<usize, &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if matches!(proposal.status, ProposalStatus::Active) {
+            return Err("Proposal is still active");
+        }
+
+        let voters: Vec<T::AccountId> = self.votes
+            .keys()
+            .filter(|(_, id)| *id == proposal_id)
+            .map(|(voter, _)| voter.clone())
+            .collect();
+
+        for voter in voters.iter() {
+            staking.unlock_vote(voter.clone(), proposal_id);
+        }
+
+        Ok(voters.len())
+    }
+
+    // Get proposal details
+    pub fn get_proposal(&self, proposal_id: u32) -> Option<&Proposal<T>> {
+        self.proposals.get(&proposal_id)
+    }
+
+    // Check whether a proposal is stored (archived and pruned proposals are not)
+    pub fn proposal_exists(&self, proposal_id: u32) -> bool {
+        self.proposals.contains_key(&proposal_id)
+    }
+
+    // Check whether a proposal exists and is open for votes
+    pub fn is_active(&self, proposal_id: u32) -> bool {
+        self.proposals.get(&proposal_id)
+            .is_some_and(|proposal| matches!(proposal.status, ProposalStatus::Active))
+    }
+
+    // Get the accounts that voted `choice` on a proposal, sorted (empty for unknown proposals)
+    pub fn voters_for(&self, proposal_id: u32, choice: bool) -> Vec<T::AccountId> {
+        let mut voters: Vec<T::AccountId> = self.votes
+            .iter()
+            .filter(|((_, id), vote)| *id == proposal_id && **vote == choice)
+            .map(|((voter, _), _)| voter.clone())
+            .collect();
+
+        voters.sort();
+        voters
+    }
+
+    // Get the voting mode in force
+    pub fn voting_mode(&self) -> VotingMode {
+        self.voting_mode
+    }
+
+    // Switch how stake translates into vote weight; root only. Refused while any proposal
+    // is active, so no tally or value submission mixes weights from two modes.
+    #[must_use = "a refused switch leaves the voting mode unchanged"]
+    pub fn set_voting_mode(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        mode: VotingMode,
+    ) -> Result<(), &'static str> {
+        ensure_root(&origin)?;
+
+        if self.proposals.values().any(|proposal| matches!(proposal.status, ProposalStatus::Active)) {
+            return Err("Cannot switch voting mode with active proposals");
+        }
+
+        self.voting_mode = mode;
+
+        Ok(())
+    }
+
+    // Get an account's reputation score, zero if it has none
+    pub fn reputation_of(&self, who: &T::AccountId) -> u32 {
+        self.reputation.get(who).copied().unwrap_or(0)
+    }
+
+    // Set an account's reputation score; root only
+    #[must_use = "a rejected change leaves the score unchanged"]
+    pub fn set_reputation(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        who: T::AccountId,
+        score: u32,
+    ) -> Result<(), &'static str> {
+        ensure_root(&origin)?;
+
+        if score == 0 {
+            self.reputation.remove(&who);
+        } else {
+            self.reputation.insert(who, score);
+        }
+
+        Ok(())
+    }
+
+    // Get an account's voting power under the current voting mode, raised one percent per
+    // reputation point and capped at MAX_VOTE_WEIGHT. Zero below MIN_VOTER_STAKE. The
+    // reputation bonus is dropped if it would overflow.
+    pub fn vote_weight(&self, staking: &impl BalanceProvider<T>, who: &T::AccountId) -> T::Balance {
+        self.weigh(who, staking.staked_balance(who))
+    }
+
+    // Turn the balance backing an account's vote into voting power, as `vote_weight` does
+    fn weigh(&self, who: &T::AccountId, balance: T::Balance) -> T::Balance {
+        if T::MIN_VOTER_STAKE.is_some_and(|min| balance < min) {
+            return T::Balance::zero();
+        }
+
+        let base = match self.voting_mode {
+            VotingMode::OneAccountOneVote => T::Balance::from(1u32),
+            VotingMode::Linear => balance,
+            VotingMode::Quadratic => isqrt(balance),
+        };
+
+        let percent = 100u32.saturating_add(self.reputation_of(who));
+        let weight = base.checked_mul(&T::Balance::from(percent))
+            .map(|scaled| scaled / T::Balance::from(100u32))
+            .unwrap_or(base);
+
+        match T::MAX_VOTE_WEIGHT {
+            Some(cap) => weight.min(cap),
+            None => weight,
+        }
+    }
+
+    // Get the voting power of every account with a nonzero stake together, by `vote_weight`,
+    // as the base for percentage quorums. None on overflow.
+    pub fn total_voting_power(&self, staking: &StakingPallet<T>) -> Option<T::Balance> {
+        staking.staked_balances
+            .iter()
+            .filter(|(_, stake)| !stake.is_zero())
+            .map(|(who, _)| who)
+            .try_fold(T::Balance::zero(), |total, who| {
+                total.checked_add(&self.vote_weight(staking, who))
+            })
+    }
+
+    // Get an account's voting power on a proposal created with a snapshot: its free plus
+    // staked balance at creation. None if the proposal has no snapshot.
+    pub fn snapshot_power(&self, proposal_id: u32, who: &T::AccountId) -> Option<T::Balance> {
+        let snapshot = self.proposals.get(&proposal_id)?.power_snapshot.as_ref()?;
+        Some(snapshot.get(who).copied().unwrap_or(T::Balance::zero()))
+    }
+
+    // Tally a proposal by each voter's `vote_weight` instead of one vote per account. On a
+    // proposal created with a snapshot, `snapshot_power` stands in for the live stake and goes
+    // through the same mode, reputation, minimum and cap.
+    // Accounts that did not vote or abstain themselves add their weight to their topic
+    // delegate's choice, if the delegate voted.
+    // Returns (yes weight, no weight), or None if the proposal does not exist or on overflow.
+    pub fn stake_weighted_tally(
+        &self,
+        staking: &impl BalanceProvider<T>,
+        proposal_id: u32,
+    ) -> Option<(T::Balance, T::Balance)> {
+        let snapshotted = self.proposals.get(&proposal_id)?.power_snapshot.is_some();
+        let power_of = |voter: &T::AccountId| {
+            if snapshotted {
+                self.weigh(voter, self.snapshot_power(proposal_id, voter).unwrap_or(T::Balance::zero()))
+            } else {
+                self.vote_weight(staking, voter)
+            }
+        };
+
+        let delegators: BTreeSet<&T::AccountId> = self.topic_delegations
+            .keys()
+            .map(|(delegator, _)| delegator)
+            .filter(|delegator| {
+                let key = ((*delegator).clone(), proposal_id);
+                !self.votes.contains_key(&key) && !self.abstentions.contains(&key)
+            })
+            .collect();
+
+        let weight_of = |choice| {
+            let direct = self.voters_for(proposal_id, choice);
+            let delegated = delegators.iter().copied().filter(|delegator| {
+                self.delegate_for(proposal_id, delegator).is_some_and(|delegate| {
+                    self.votes.get(&(delegate.clone(), proposal_id)) == Some(&choice)
+                })
+            });
+
+            direct.iter()
+                .chain(delegated)
+                .try_fold(T::Balance::zero(), |total, voter| total.checked_add(&power_of(voter)))
+        };
+
+        Some((weight_of(true)?, weight_of(false)?))
+    }
+
+    // Delegate an account's voting power on proposals tagged `topic` to `delegate`, replacing
+    // any earlier delegation for that topic. Voting directly on a proposal overrides it.
+    // Delegations are not transitive: the delegate's own delegations are not followed.
+    #[must_use = "a rejected delegation leaves voting power undelegated"]
+    pub(crate) fn delegate_topic(
+        &mut self,
+        who: T::AccountId,
+        topic: String,
+        delegate: T::AccountId,
+    ) -> Result<(), &'static str> {
+        if who == delegate {
+            return Err("Cannot delegate to yourself");
+        }
+
+        self.topic_delegations.insert((who, topic), delegate);
+
+        Ok(())
+    }
+
+    // `delegate_topic` on behalf of `who`, which must have signed the call
+    #[must_use = "a rejected delegation leaves voting power undelegated"]
+    pub fn delegate_topic_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        who: T::AccountId,
+        topic: String,
+        delegate: T::AccountId,
+    ) -> Result<(), &'static str> {
+        ensure_signed_by(&origin, &who)?;
+        self.delegate_topic(who, topic, delegate)
+    }
+
+    // Take back voting power delegated for `topic`
+    pub(crate) fn undelegate_topic(&mut self, who: T::AccountId, topic: String) {
+        self.topic_delegations.remove(&(who, topic));
+    }
+
+    // `undelegate_topic` on behalf of `who`, which must have signed the call
+    #[must_use = "a rejected origin leaves the delegation in place"]
+    pub fn undelegate_topic_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        who: T::AccountId,
+        topic: String,
+    ) -> Result<(), &'static str> {
+        ensure_signed_by(&origin, &who)?;
+        self.undelegate_topic(who, topic);
+
+        Ok(())
+    }
+
+    // Get the account voting on `who`'s behalf on a proposal: the delegate for the first of
+    // the proposal's tags, in tag order, that `who` has delegated
+    pub fn delegate_for(&self, proposal_id: u32, who: &T::AccountId) -> Option<&T::AccountId> {
+        self.proposals.get(&proposal_id)?
+            .tags
+            .iter()
+            .find_map(|tag| self.topic_delegations.get(&(who.clone(), tag.clone())))
+    }
+
+    // Allow an account to close proposals it did not create; root only
+    #[must_use = "a rejected origin leaves the closers unchanged"]
+    pub fn add_closer(&mut self, origin: Origin<T::AccountId>, who: T::AccountId) -> Result<(), &'static str> {
+        ensure_root(&origin)?;
+        self.closers.insert(who);
+
+        Ok(())
+    }
+
+    // Revoke an account's right to close proposals it did not create; root only
+    #[must_use = "a rejected origin leaves the closers unchanged"]
+    pub fn remove_closer(&mut self, origin: Origin<T::AccountId>, who: &T::AccountId) -> Result<(), &'static str> {
+        ensure_root(&origin)?;
+        self.closers.remove(who);
+
+        Ok(())
+    }
+
+    // Check whether `finalize_proposal` would accept a proposal at `current_block`
+    pub fn can_finalize(&self, proposal_id: u32, current_block: T::BlockNumber) -> bool {
+        self.ensure_finalizable(proposal_id, current_block).is_ok()
+    }
+
+    // Check that a proposal can be finalized at `current_block`: it is active, its minimum
+    // voting window has elapsed, and its deadline has passed or its quorum is already met.
+    // Submissions make up the participation of a choice proposal.
+    fn ensure_finalizable(
+        &self,
+        proposal_id: u32,
+        current_block: T::BlockNumber,
+    ) -> Result<(), &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if matches!(proposal.status, ProposalStatus::Scheduled) {
+            return Err("Voting not yet started");
+        }
+
+        if !matches!(proposal.status, ProposalStatus::Active) {
+            return Err("Cannot vote on finalized proposal");
+        }
+
+        let window_end = proposal.voting_start.checked_add(&T::MIN_VOTING_BLOCKS)
+            .ok_or("Overflow")?;
+        if !has_reached(current_block, window_end) {
+            return Err("Minimum voting window not elapsed");
+        }
+
+        let participation = match proposal.kind {
+            ProposalKind::ChooseParameter(_) => {
+                self.submissions.get(&proposal_id).map_or(0, Vec::len) as u64
+            }
+            _ => proposal.yes_votes as u64 + proposal.no_votes as u64 + proposal.abstain_votes as u64,
+        };
+        let (quorum, _) = proposal.priority.rules::<T>();
+        if !has_reached(current_block, proposal.deadline) && participation < quorum as u64 {
+            return Err("Voting period not ended");
+        }
+
+        Ok(())
+    }
+
+    // Close a proposal once its voting period has ended; only the creator or an
+    // authorized closer may do so. Returns the finalized status.
+    #[must_use = "the resulting status or finalization error must be handled"]
+    pub(crate) fn close_proposal(
+        &mut self,
+        who: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<ProposalStatus, &'static str> {
+        let proposal = self.proposals.get(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if proposal.creator != who && !self.closers.contains(&who) {
+            return Err("Not authorized to close proposal");
+        }
+
+        if !has_reached(self.current_block, proposal.deadline) {
+            return Err("Voting period not ended");
+        }
+
+        self.finalize_proposal(proposal_id)
+    }
+
+    // `close_proposal` on behalf of `who`, which must have signed the call
+    #[must_use = "the resulting status or finalization error must be handled"]
+    pub fn close_proposal_signed(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        who: T::AccountId,
+        proposal_id: u32,
+    ) -> Result<ProposalStatus, &'static str> {
+        ensure_signed_by(&origin, &who)?;
+        self.close_proposal(who, proposal_id)
+    }
+
+    // Finalize a proposal (changes status based on votes)
+    #[must_use = "the resulting status or finalization error must be handled"]
+    pub fn finalize_proposal(&mut self, proposal_id: u32) -> Result<ProposalStatus, &'static str> {
+        self.finalize(proposal_id, None, None)
+    }
+
+    // Finalize a proposal with yes and no weighed by `stake_weighted_tally`, so the voting
+    // mode, weight cap, reputation, delegations and power snapshot decide the outcome.
+    // The quorum and yes floor still count accounts.
+    #[must_use = "the resulting status or finalization error must be handled"]
+    pub fn finalize_proposal_weighted(
+        &mut self,
+        staking: &impl BalanceProvider<T>,
+        proposal_id: u32,
+    ) -> Result<ProposalStatus, &'static str> {
+        if !self.proposals.contains_key(&proposal_id) {
+            return Err("Proposal does not exist");
+        }
+
+        let tally = self.stake_weighted_tally(staking, proposal_id).ok_or("Overflow")?;
+        self.finalize(proposal_id, None, Some(tally))
+    }
+
+    // Finalize a proposal against the size of the whole electorate (all eligible voters).
+    // Approval additionally requires yes votes above ELECTORATE_APPROVAL_PERCENT of the
+    // electorate, so non-voters and abstentions effectively count against the proposal.
+    #[must_use = "the resulting status or finalization error must be handled"]
+    pub fn finalize_proposal_with_electorate(
+        &mut self,
+        proposal_id: u32,
+        electorate: u32,
+    ) -> Result<ProposalStatus, &'static str> {
+        self.finalize(proposal_id, Some(electorate), None)
+    }
+
+    // Close a proposal by its tally and record the outcome in the creator's history; an
+    // approval earns the creator REPUTATION_PER_APPROVAL
+    fn finalize(
+        &mut self,
+        proposal_id: u32,
+        electorate: Option<u32>,
+        weighted: Option<(T::Balance, T::Balance)>,
+    ) -> Result<ProposalStatus, &'static str> {
+        let status = self.close_by_tally(proposal_id, electorate, weighted)?;
+
+        if let Some(creator) = self.proposals.get(&proposal_id).map(|p| p.creator.clone()) {
+            if status == ProposalStatus::Approved && T::REPUTATION_PER_APPROVAL > 0 {
+                let score = self.reputation_of(&creator).saturating_add(T::REPUTATION_PER_APPROVAL);
+                self.reputation.insert(creator.clone(), score);
+            }
+            self.creator_history.entry(creator).or_default().push((proposal_id, status.clone()));
+        }
+
+        Ok(status)
+    }
+
+    // Get the outcome of each of `who`'s finalized proposals, in finalization order
+    pub fn creator_history(&self, who: &T::AccountId) -> Vec<(u32, ProposalStatus)> {
+        self.creator_history.get(who).cloned().unwrap_or_default()
+    }
+
+    // Close a proposal by its tally: yes and no are `weighted` if given, one per vote otherwise
+    fn close_by_tally(
+        &mut self,
+        proposal_id: u32,
+        electorate: Option<u32>,
+        weighted: Option<(T::Balance, T::Balance)>,
+    ) -> Result<ProposalStatus, &'static str> {
+        // A dependent proposal is rejected unless its dependency is already approved
+        let dependency_met = match self.proposals.get(&proposal_id).and_then(|p| p.depends_on) {
+            Some(dependency) => self.proposals
+                .get(&dependency)
+                .is_some_and(|d| d.status == ProposalStatus::Approved),
+            None => true,
+        };
+
+        self.ensure_finalizable(proposal_id, self.current_block)?;
+        let proposal = self.proposals
+            .get_mut(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        let refund_at = self.current_block.checked_add(&T::REFUND_DELAY)
+            .ok_or("Overflow")?;
+        let (quorum, threshold_percent) = proposal.priority.rules::<T>();
+
+        // Choice proposals are decided by their submissions rather than yes/no votes; every
+        // submission counts as a yes towards the electorate, quorum and yes floor
+        if let ProposalKind::ChooseParameter(key) = proposal.kind {
+            let submissions = self.submissions.get(&proposal_id);
+            let submitted = submissions.map_or(0, Vec::len) as u32;
+            let electorate_met = electorate_met::<T>(submitted, submitted, electorate)?;
+            let median = submissions.and_then(|submissions| weighted_median::<T>(submissions));
+
+            proposal.closed_at = Some(self.current_block);
+            proposal.status = match median {
+                Some(value) if dependency_met
+                    && electorate_met
+                    && submitted >= quorum
+                    && submitted >= T::MIN_YES_VOTES =>
+                {
+                    proposal.kind = ProposalKind::SetParameter(StakingParameter::with_value(key, value));
+                    ProposalStatus::Approved
+                }
+                _ => ProposalStatus::Rejected,
+            };
+
+            let status = proposal.status.clone();
+            let (creator, deposit) = (proposal.creator.clone(), proposal.deposit);
+            self.settle_deposit(creator, deposit, &status, refund_at);
+
+            return Ok(status);
+        }
+
+        let electorate_met = electorate_met::<T>(
+            proposal.yes_votes,
+            proposal.yes_votes.saturating_add(proposal.no_votes),
+            electorate,
+        )?;
+
+        let (yes, no) = weighted.unwrap_or((proposal.yes_votes.into(), proposal.no_votes.into()));
+        let participation = proposal.yes_votes as u64
+            + proposal.no_votes as u64
+            + proposal.abstain_votes as u64;
+        let approve_on_tie = match T::TIE_BREAK {
+            TieBreak::RejectOnTie => false,
+            TieBreak::ApproveOnTie => true,
+            TieBreak::CreatorDecides => self.votes
+                .get(&(proposal.creator.clone(), proposal_id))
+                .copied()
+                .unwrap_or(false),
+        };
+        let majority = decide_status(yes, no, participation, quorum, threshold_percent, approve_on_tie)?
+            == ProposalStatus::Approved;
+
+        let yes_floor_met = proposal.yes_votes >= T::MIN_YES_VOTES;
+
+        proposal.closed_at = Some(self.current_block);
+        proposal.status = if majority && electorate_met && dependency_met && yes_floor_met {
+            ProposalStatus::Approved
+        } else {
+            ProposalStatus::Rejected
+        };
+
+        let status = proposal.status.clone();
+        let (creator, deposit) = (proposal.creator.clone(), proposal.deposit);
+        self.settle_deposit(creator, deposit, &status, refund_at);
+
+        Ok(status)
+    }
+
+    // Queue a closed proposal's deposit: forfeited to the treasury if it was rejected under
+    // FORFEIT_REJECTED_DEPOSITS, otherwise refunded once `refund_at` is reached
+    fn settle_deposit(
+        &mut self,
+        creator: T::AccountId,
+        deposit: T::Balance,
+        status: &ProposalStatus,
+        refund_at: T::BlockNumber,
+    ) {
+        if deposit.is_zero() {
+            return;
+        }
+
+        if T::FORFEIT_REJECTED_DEPOSITS && *status == ProposalStatus::Rejected {
+            self.forfeit_queue.push((creator, deposit));
+        } else {
+            self.refund_queue.push((creator, deposit, refund_at));
+        }
+    }
+
+    // Set a proposal's outcome directly, bypassing the tally; root only, for emergencies
+    #[must_use = "the forced status or error must be handled"]
+    pub fn force_finalize(
+        &mut self,
+        origin: Origin<T::AccountId>,
+        proposal_id: u32,
+        status: ProposalStatus,
+    ) -> Result<ProposalStatus, &'static str> {
+        ensure_root(&origin)?;
+
+        if !matches!(status, ProposalStatus::Approved | ProposalStatus::Rejected) {
+            return Err("Forced status must be Approved or Rejected");
+        }
+
+        let proposal = self.proposals.get_mut(&proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        if !matches!(proposal.status, ProposalStatus::Active | ProposalStatus::Scheduled) {
+            return Err("Proposal already finalized");
+        }
+
+        let refund_at = self.current_block.checked_add(&T::REFUND_DELAY)
+            .ok_or("Overflow")?;
+
+        proposal.closed_at = Some(self.current_block);
+        proposal.status = status.clone();
+        let (creator, deposit) = (proposal.creator.clone(), proposal.deposit);
+        self.creator_history
+            .entry(creator.clone())
+            .or_default()
+            .push((proposal_id, status.clone()));
+        self.settle_deposit(creator, deposit, &status, refund_at);
+
+        Ok(status)
+    }
+
+    // Move every forfeited deposit from its creator's reserved balance into the treasury.
+    // A forfeiture that fails stays queued for the next run; the first failure is returned
+    // once every other forfeiture has been moved. Returns the number moved.
+    #[must_use = "a failed forfeiture run must be handled"]
+    pub fn process_forfeitures(
+        &mut self,
+        staking: &mut StakingPallet<T>,
+        treasury: &mut TreasuryPallet<T>,
+    ) -> Result<usize, &'static str> {
+        let mut moved = 0;
+        let mut first_error = None;
+        for (who, amount) in std::mem::take(&mut self.forfeit_queue) {
+            match staking.slash_reserved(who.clone(), amount, treasury) {
+                Ok(()) => moved += 1,
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                    self.forfeit_queue.push((who, amount));
+                }
+            }
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(moved),
+        }
+    }
+
+    // Return every matured deposit to its creator's free balance.
+    // A refund that fails stays queued for the next run; the first failure is returned once
+    // every other matured refund has been paid. Returns the number of refunds processed.
+    #[must_use = "a failed refund run must be handled"]
+    pub fn process_refunds(
+        &mut self,
+        current_block: T::BlockNumber,
+        staking: &mut StakingPallet<T>,
+    ) -> Result<usize, &'static str> {
+        let mut refunded = 0;
+        let mut first_error = None;
+        for (who, amount, refund_at) in std::mem::take(&mut self.refund_queue) {
+            if !has_reached(current_block, refund_at) {
+                self.refund_queue.push((who, amount, refund_at));
+                continue;
+            }
+
+            match staking.unreserve(who.clone(), amount) {
+                Ok(()) => refunded += 1,
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                    self.refund_queue.push((who, amount, refund_at));
+                }
+            }
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(refunded),
+        }
+    }
+}
+
+impl<T: GovernanceConfig> GovernancePallet<T>
+where
+    T::Balance: TryInto<u128>,
+{
+    // Export every proposal as an audit record, in ascending id order
+    pub fn export_audit(&self) -> Vec<AuditRecord<T>> {
+        self.proposals
+            .iter()
+            .map(|(proposal_id, proposal)| Self::audit_record(*proposal_id, proposal))
+            .collect()
+    }
+
+    // Export only proposals created at or after `block`, for incremental indexing, ordered
+    // by creation block and then id
+    pub fn export_audit_since(&self, block: T::BlockNumber) -> Vec<AuditRecord<T>> {
+        let mut records: Vec<AuditRecord<T>> = self.proposals
+            .iter()
+            .filter(|(_, proposal)| has_reached(proposal.created_at, block))
+            .map(|(proposal_id, proposal)| Self::audit_record(*proposal_id, proposal))
+            .collect();
+
+        records.sort_by(|a, b| {
+            a.created_at.partial_cmp(&b.created_at)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.proposal_id.cmp(&b.proposal_id))
+        });
+        records
+    }
+
+    fn audit_record(proposal_id: u32, proposal: &Proposal<T>) -> AuditRecord<T> {
+        AuditRecord {
+            proposal_id,
+            creator: proposal.creator.clone(),
+            status: proposal.status.clone(),
+            yes_votes: proposal.yes_votes,
+            no_votes: proposal.no_votes,
+            deposit: balance_as_u128(proposal.deposit),
+            created_at: proposal.created_at,
+        }
+    }
+}
+
+impl<'a, T: GovernanceConfig> IntoIterator for &'a GovernancePallet<T> {
+    type Item = (&'a u32, &'a Proposal<T>);
+    type IntoIter = btree_map::Iter<'a, u32, Proposal<T>>;
+
+    // Iterate proposals in ascending id order
+    fn into_iter(self) -> Self::IntoIter {
+        self.proposals.iter()
+    }
+}
+
+impl<T: GovernanceConfig> Index<u32> for GovernancePallet<T> {
+    type Output = Proposal<T>;
+
+    fn index(&self, proposal_id: u32) -> &Self::Output {
+        self.proposals
+            .get(&proposal_id)
+            .unwrap_or_else(|| panic!("Proposal {} does not exist", proposal_id))
+    }
+}
+
+#[cfg(feature = "codec")]
+impl Encode for ProposalStatus {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            ProposalStatus::Scheduled => 0,
+            ProposalStatus::Active => 1,
+            ProposalStatus::Approved => 2,
+            ProposalStatus::Rejected => 3,
+            ProposalStatus::Cancelled => 4,
+        });
+    }
+}
+
+#[cfg(feature = "codec")]
+impl Decode for ProposalStatus {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        match u8::decode_from(input)? {
+            0 => Ok(ProposalStatus::Scheduled),
+            1 => Ok(ProposalStatus::Active),
+            2 => Ok(ProposalStatus::Approved),
+            3 => Ok(ProposalStatus::Rejected),
+            4 => Ok(ProposalStatus::Cancelled),
+            _ => Err("Invalid enum tag"),
+        }
+    }
+}
+
+// Action kinds and on_reject hooks are code rather than data: they encode as a marker that
+// `decode` refuses, so such a proposal never comes back silently changed
+#[cfg(feature = "codec")]
+impl<T: GovernanceConfig> Encode for Proposal<T>
+where
+    T::AccountId: Encode,
+    T::Balance: Encode,
+    T::BlockNumber: Encode,
+{
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.description.encode_to(out);
+        self.yes_votes.encode_to(out);
+        self.no_votes.encode_to(out);
+        self.abstain_votes.encode_to(out);
+        self.status.encode_to(out);
+        self.creator.encode_to(out);
+        self.deposit.encode_to(out);
+        match &self.kind {
+            ProposalKind::Text => out.push(0),
+            ProposalKind::SetParameter(StakingParameter::MinStake(value)) => {
+                out.push(1);
+                value.encode_to(out);
+            }
+            ProposalKind::ChooseParameter(StakingParameterKey::MinStake) => out.push(2),
+            ProposalKind::Action(_) => out.push(3),
+            ProposalKind::Spend { to, amount } => {
+                out.push(4);
+                to.encode_to(out);
+                amount.encode_to(out);
+            }
+        }
+        self.created_at.encode_to(out);
+        self.voting_start.encode_to(out);
+        self.deadline.encode_to(out);
+        self.depends_on.encode_to(out);
+        self.on_reject.is_some().encode_to(out);
+        self.closed_at.encode_to(out);
+        encode_len(self.tags.len(), out);
+        for tag in self.tags.iter() {
+            tag.encode_to(out);
+        }
+        out.push(self.priority as u8);
+        self.power_snapshot.is_some().encode_to(out);
+        if let Some(snapshot) = &self.power_snapshot {
+            encode_len(snapshot.len(), out);
+            for (who, power) in snapshot.iter() {
+                who.encode_to(out);
+                power.encode_to(out);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "codec")]
+impl<T: GovernanceConfig> Decode for Proposal<T>
+where
+    T::AccountId: Decode,
+    T::Balance: Decode,
+    T::BlockNumber: Decode,
+{
+    fn decode_from(input: &mut &[u8]) -> Result<Self, &'static str> {
+        let description = String::decode_from(input)?;
+        let yes_votes = u32::decode_from(input)?;
+        let no_votes = u32::decode_from(input)?;
+        let abstain_votes = u32::decode_from(input)?;
+        let status = ProposalStatus::decode_from(input)?;
+        let creator = T::AccountId::decode_from(input)?;
+        let deposit = T::Balance::decode_from(input)?;
+        let kind = match u8::decode_from(input)? {
+            0 => ProposalKind::Text,
+            1 => ProposalKind::SetParameter(StakingParameter::MinStake(T::Balance::decode_from(input)?)),
+            2 => ProposalKind::ChooseParameter(StakingParameterKey::MinStake),
+            3 => return Err("Action proposals cannot be decoded"),
+            4 => ProposalKind::Spend {
+                to: T::AccountId::decode_from(input)?,
+                amount: T::Balance::decode_from(input)?,
+            },
+            _ => return Err("Invalid enum tag"),
+        };
+        let created_at = T::BlockNumber::decode_from(input)?;
+        let voting_start = T::BlockNumber::decode_from(input)?;
+        let deadline = T::BlockNumber::decode_from(input)?;
+        let depends_on = Option::<u32>::decode_from(input)?;
+        if bool::decode_from(input)? {
+            return Err("Action proposals cannot be decoded");
+        }
+        let closed_at = Option::<T::BlockNumber>::decode_from(input)?;
+        let mut tags = BTreeSet::new();
+        for _ in 0..decode_len(input)? {
+            tags.insert(String::decode_from(input)?);
+        }
+        let priority = match u8::decode_from(input)? {
+            0 => ProposalPriority::Low,
+            1 => ProposalPriority::Normal,
+            2 => ProposalPriority::Critical,
+            _ => return Err("Invalid enum tag"),
+        };
+        let power_snapshot = if bool::decode_from(input)? {
+            let mut snapshot = BTreeMap::new();
+            for _ in 0..decode_len(input)? {
+                let who = T::AccountId::decode_from(input)?;
+                if snapshot.insert(who, T::Balance::decode_from(input)?).is_some() {
+                    return Err("Duplicate key");
+                }
+            }
+            Some(snapshot)
+        } else {
+            None
+        };
+
+        Ok(Proposal {
+            description,
+            yes_votes,
+            no_votes,
+            abstain_votes,
+            status,
+            creator,
+            deposit,
+            kind,
+            created_at,
+            voting_start,
+            deadline,
+            depends_on,
+            on_reject: None,
+            closed_at,
+            tags,
+            priority,
+            power_snapshot,
+        })
+    }
+}
+
+#[cfg(feature = "codec")]
+impl<T: GovernanceConfig> GovernancePallet<T>
+where
+    T::AccountId: Encode + Decode,
+    T::Balance: Encode + Decode,
+    T::BlockNumber: Encode + Decode,
+{
+    // Encode the stored proposals and the next proposal id. Votes, comments, the refund
+    // queue and the rest of the pallet's bookkeeping are not included.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.next_proposal_id.encode_to(&mut out);
+        encode_len(self.proposals.len(), &mut out);
+        for (proposal_id, proposal) in self.proposals.iter() {
+            proposal_id.encode_to(&mut out);
+            proposal.encode_to(&mut out);
+        }
+
+        out
+    }
+
+    // Rebuild a pallet from `encode` output, as `from_genesis` would from the proposals
+    pub fn decode(bytes: &[u8]) -> Result<Self, &'static str> {
+        let mut input = bytes;
+        let next_proposal_id = u32::decode_from(&mut input)?;
+        let mut proposals = Vec::new();
+        for _ in 0..decode_len(&mut input)? {
+            let proposal_id = u32::decode_from(&mut input)?;
+            proposals.push((proposal_id, Proposal::decode_from(&mut input)?));
+        }
+
+        if !input.is_empty() {
+            return Err("Trailing bytes");
+        }
+
+        let mut pallet = Self::from_genesis(proposals)?;
+        pallet.set_next_proposal_id(next_proposal_id)?;
+
+        Ok(pallet)
+    }
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
     use crate::Runtime;
 
+    crate::test_runtime!(ApproveOnTieRuntime {
+        AccountId = u64,
+        Balance = u64,
+        TIE_BREAK: TieBreak = TieBreak::ApproveOnTie,
+    });
+    crate::test_runtime!(CreatorDecidesRuntime {
+        AccountId = u64,
+        Balance = u64,
+        TIE_BREAK: TieBreak = TieBreak::CreatorDecides,
+    });
+
+    // Create a proposal by `creator` (account 1) with a 2-2 vote split
+    fn tied_proposal<T: GovernanceConfig<AccountId = u64>>(
+        governance: &mut GovernancePallet<T>,
+        creator_vote: bool,
+    ) -> u32 {
+        let proposal_id = governance
+            .create_proposal(1, "Tied proposal".to_string())
+            .unwrap();
+
+        governance.vote(1, proposal_id, creator_vote).unwrap();
+        governance.vote(2, proposal_id, !creator_vote).unwrap();
+        governance.vote(3, proposal_id, true).unwrap();
+        governance.vote(4, proposal_id, false).unwrap();
+
+        proposal_id
+    }
+
+    #[test]
+    fn test_governance_should_work() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        // Create a proposal
+        let proposal_id = governance
+            .create_proposal(alice, "Increase validator rewards".to_string())
+            .unwrap();
+
+        // Cast votes
+        governance.vote(alice, proposal_id, true).unwrap(); // Yes vote
+        governance.vote(bob, proposal_id, true).unwrap(); // Yes vote
+        governance.vote(charlie, proposal_id, false).unwrap(); // No vote
+
+        // Check proposal status before finalization
+        let proposal = governance.get_proposal(proposal_id).unwrap();
+        assert_eq!(proposal.yes_votes, 2);
+        assert_eq!(proposal.no_votes, 1);
+        assert_eq!(proposal.description, "Increase validator rewards".to_string());
+        assert_eq!(proposal.creator, alice);
+
+        // Finalize proposal
+        let status = governance.finalize_proposal(proposal_id).unwrap();
+        assert!(matches!(status, ProposalStatus::Approved));
+
+        // Check proposal is now approved
+        let finalized_proposal = governance.get_proposal(proposal_id).unwrap();
+        assert!(matches!(
+            finalized_proposal.status,
+            ProposalStatus::Approved
+        ));
+    }
+
+    #[test]
+    fn test_finalize_with_electorate_requires_turnout() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let proposal_id = governance
+            .create_proposal(alice, "Low turnout proposal".to_string())
+            .unwrap();
+
+        // 2 yes vs 1 no is a majority of voters...
+        governance.vote(alice, proposal_id, true).unwrap();
+        governance.vote(bob, proposal_id, true).unwrap();
+        governance.vote(charlie, proposal_id, false).unwrap();
+
+        // ...but only 2 of 10 eligible voters said yes
+        let status = governance
+            .finalize_proposal_with_electorate(proposal_id, 10)
+            .unwrap();
+        assert!(matches!(status, ProposalStatus::Rejected));
+
+        // The same votes pass when the electorate is small enough
+        let proposal_id = governance
+            .create_proposal(alice, "High turnout proposal".to_string())
+            .unwrap();
+        governance.vote(alice, proposal_id, true).unwrap();
+        governance.vote(bob, proposal_id, true).unwrap();
+        governance.vote(charlie, proposal_id, false).unwrap();
+
+        let status = governance
+            .finalize_proposal_with_electorate(proposal_id, 3)
+            .unwrap();
+        assert!(matches!(status, ProposalStatus::Approved));
+    }
+
+    #[test]
+    fn test_finalize_with_electorate_smaller_than_turnout_fails() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let proposal_id = governance
+            .create_proposal(1, "Proposal".to_string())
+            .unwrap();
+        governance.vote(1, proposal_id, true).unwrap();
+        governance.vote(2, proposal_id, true).unwrap();
+
+        assert_eq!(
+            governance.finalize_proposal_with_electorate(proposal_id, 1).err(),
+            Some("Electorate smaller than turnout")
+        );
+    }
+
+    // Results of mutating calls are `#[must_use]`: propagate them with `?` or inspect them
+    #[test]
+    fn test_governance_results_are_handled() {
+        fn propose_and_vote(
+            governance: &mut GovernancePallet<Runtime>,
+            who: u64,
+        ) -> Result<u32, &'static str> {
+            let proposal_id = governance.create_proposal(who, "Proposal".to_string())?;
+            governance.vote(who, proposal_id, true)?;
+            Ok(proposal_id)
+        }
+
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = propose_and_vote(&mut governance, 1).unwrap();
+
+        // A duplicate vote is reported to the caller rather than silently ignored
+        assert_eq!(
+            governance.vote(1, proposal_id, false),
+            Err("Voter has already voted on this proposal")
+        );
+    }
+
+    #[test]
+    fn test_tie_rejects_by_default() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = tied_proposal(&mut governance, true);
+
+        let status = governance.finalize_proposal(proposal_id).unwrap();
+        assert!(matches!(status, ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_tie_approves_with_approve_on_tie() {
+        let mut governance = GovernancePallet::<ApproveOnTieRuntime>::new();
+        let proposal_id = tied_proposal(&mut governance, false);
+
+        let status = governance.finalize_proposal(proposal_id).unwrap();
+        assert!(matches!(status, ProposalStatus::Approved));
+    }
+
+    #[test]
+    fn test_no_votes_is_not_a_tie() {
+        let mut governance = GovernancePallet::<ApproveOnTieRuntime>::new();
+        let proposal_id = governance.create_proposal(1, "Silent".to_string()).unwrap();
+        governance.abstain(2, proposal_id).unwrap();
+
+        assert_eq!(governance.finalize_proposal(proposal_id), Ok(ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_tie_follows_creator_with_creator_decides() {
+        let mut governance = GovernancePallet::<CreatorDecidesRuntime>::new();
+
+        let proposal_id = tied_proposal(&mut governance, true);
+        let status = governance.finalize_proposal(proposal_id).unwrap();
+        assert!(matches!(status, ProposalStatus::Approved));
+
+        let proposal_id = tied_proposal(&mut governance, false);
+        let status = governance.finalize_proposal(proposal_id).unwrap();
+        assert!(matches!(status, ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_deposit_refunded_after_delay() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        staking.set_balance(alice, 150).unwrap();
+
+        governance.on_initialize(5);
+        let proposal_id = governance
+            .create_proposal_with_deposit(&mut staking, alice, "Deposit proposal".to_string())
+            .unwrap();
+        assert_eq!(staking.get_free_balance(alice), 50u64);
+        assert_eq!(staking.get_reserved_balance(alice), 100u64);
+
+        governance.vote(alice, proposal_id, true).unwrap();
+        governance.finalize_proposal(proposal_id).unwrap();
+
+        // Finalized at block 5 with REFUND_DELAY = 10: nothing matures before block 15
+        assert_eq!(governance.process_refunds(14, &mut staking), Ok(0));
+        assert_eq!(staking.get_free_balance(alice), 50u64);
+
+        assert_eq!(governance.process_refunds(15, &mut staking), Ok(1));
+        assert_eq!(staking.get_free_balance(alice), 150u64);
+        assert_eq!(staking.get_reserved_balance(alice), 0u64);
+        assert!(governance.refund_queue.is_empty());
+    }
+
+    #[test]
+    fn test_failed_refund_stays_queued() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        staking.set_balance(2, 100).unwrap();
+        staking.reserve(2, 40).unwrap();
+        // Account 1 has nothing reserved, so its refund cannot be paid
+        governance.refund_queue.push((1, 30, 0));
+        governance.refund_queue.push((2, 40, 0));
+        governance.refund_queue.push((2, 10, 50));
+
+        assert_eq!(governance.process_refunds(10, &mut staking), Err("Insufficient reserved balance"));
+        // The refund behind the failed one was still paid
+        assert_eq!(staking.get_free_balance(2), 100u64);
+        assert_eq!(governance.refund_queue, vec![(1, 30, 0), (2, 10, 50)]);
+    }
+
+    #[test]
+    fn test_deposit_requires_free_balance() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        staking.set_balance(1, 99).unwrap();
+
+        assert_eq!(
+            governance.create_proposal_with_deposit(&mut staking, 1, "Proposal".to_string()),
+            Err("Insufficient balance")
+        );
+        assert!(!governance.proposal_exists(0));
+    }
+
+    #[test]
+    fn test_description_length_bounds() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        assert_eq!(
+            governance.create_proposal(1, String::new()),
+            Err("Description too short")
+        );
+        assert_eq!(
+            governance.create_proposal(1, "x".repeat(257)),
+            Err("Description too long")
+        );
+        assert!(governance.create_proposal(1, "x".repeat(256)).is_ok());
+    }
+
+    #[test]
+    fn test_edit_description_before_votes() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let proposal_id = governance
+            .create_proposal(alice, "Incraese rewards".to_string())
+            .unwrap();
+
+        assert_eq!(
+            governance.edit_description(bob, proposal_id, "Hijacked".to_string()),
+            Err("Only the creator can edit the proposal")
+        );
+        assert_eq!(
+            governance.edit_description(alice, proposal_id, String::new()),
+            Err("Description too short")
+        );
+
+        governance
+            .edit_description(alice, proposal_id, "Increase rewards".to_string())
+            .unwrap();
+        assert_eq!(
+            governance.get_proposal(proposal_id).unwrap().description(),
+            "Increase rewards"
+        );
+    }
+
+    #[test]
+    fn test_edit_description_rejected_after_vote() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let proposal_id = governance
+            .create_proposal(alice, "Original".to_string())
+            .unwrap();
+        governance.vote(bob, proposal_id, true).unwrap();
+
+        assert_eq!(
+            governance.edit_description(alice, proposal_id, "Changed".to_string()),
+            Err("Cannot edit proposal after votes were cast")
+        );
+        assert_eq!(governance.get_proposal(proposal_id).unwrap().description(), "Original");
+    }
+
+    #[test]
+    fn test_abstentions_and_submissions_lock_proposal_details() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        staking.set_balance(2, 100).unwrap();
+        staking.stake(2, 100).unwrap();
+
+        let abstained = governance.create_proposal(1, "Abstained".to_string()).unwrap();
+        governance.abstain(2, abstained).unwrap();
+        let chosen = governance
+            .create_choice_proposal(1, "Chosen".to_string(), StakingParameterKey::MinStake)
+            .unwrap();
+        governance.submit_value(&staking, 2, chosen, 10).unwrap();
+
+        for proposal_id in [abstained, chosen] {
+            assert_eq!(
+                governance.edit_description(1, proposal_id, "Changed".to_string()),
+                Err("Cannot edit proposal after votes were cast")
+            );
+            assert_eq!(
+                governance.tag_proposal(1, proposal_id, "staking".to_string()),
+                Err("Cannot tag proposal after votes were cast")
+            );
+            assert_eq!(
+                governance.set_priority(1, proposal_id, ProposalPriority::Critical),
+                Err("Cannot set priority after votes were cast")
+            );
+        }
+    }
+
+    #[test]
+    fn test_voters_for_groups_by_choice() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let proposal_id = governance.create_proposal(1, "Mixed".to_string()).unwrap();
+        let other_id = governance.create_proposal(1, "Other".to_string()).unwrap();
+
+        governance.vote(4, proposal_id, true).unwrap();
+        governance.vote(2, proposal_id, false).unwrap();
+        governance.vote(1, proposal_id, true).unwrap();
+        governance.vote(3, proposal_id, false).unwrap();
+        governance.vote(5, other_id, true).unwrap();
+
+        assert_eq!(governance.voters_for(proposal_id, true), vec![1, 4]);
+        assert_eq!(governance.voters_for(proposal_id, false), vec![2, 3]);
+        assert!(governance.voters_for(99, true).is_empty());
+    }
+
+    #[test]
+    fn test_vote_locks_stake_until_released() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        staking.set_balance(alice, 1000).unwrap();
+        staking.stake(alice, 500).unwrap();
+
+        let proposal_id = governance.create_proposal(alice, "Locked".to_string()).unwrap();
+        governance.vote_with_stake(&mut staking, alice, proposal_id, true).unwrap();
+        assert_eq!(staking.locked_for_voting(&alice), 500u64);
+
+        // The whole stake backs the vote, so it cannot be unstaked
+        assert_eq!(staking.unstake(alice, 1), Err("Stake locked by active vote"));
+
+        // Locks cannot be released while the proposal is still active
+        assert_eq!(
+            governance.release_vote_locks(&mut staking, proposal_id),
+            Err("Proposal is still active")
+        );
+
+        // Adding stake on top of the lock keeps the extra unlocked
+        staking.stake(alice, 100).unwrap();
+        assert!(staking.unstake(alice, 100).is_ok());
+
+        governance.finalize_proposal(proposal_id).unwrap();
+        assert_eq!(governance.release_vote_locks(&mut staking, proposal_id), Ok(1));
+        assert!(staking.unstake(alice, 500).is_ok());
+    }
+
+    #[test]
+    fn test_iterate_and_index_proposals() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        for description in ["First", "Second", "Third"] {
+            governance.create_proposal(1, description.to_string()).unwrap();
+        }
+
+        let ids: Vec<u32> = (&governance).into_iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+
+        let mut descriptions = Vec::new();
+        for (_, proposal) in &governance {
+            descriptions.push(proposal.description().to_string());
+        }
+        assert_eq!(descriptions, vec!["First", "Second", "Third"]);
+
+        assert_eq!(governance[1].description(), "Second");
+    }
+
+    #[test]
+    #[should_panic(expected = "Proposal 7 does not exist")]
+    fn test_index_missing_proposal_panics() {
+        let governance = GovernancePallet::<Runtime>::new();
+        let _ = &governance[7];
+    }
+
+    #[test]
+    fn test_close_proposal_requires_authorization() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let proposal_id = governance.create_proposal(alice, "Close me".to_string()).unwrap();
+        governance.on_initialize(100);
+
+        assert_eq!(
+            governance.close_proposal(bob, proposal_id),
+            Err("Not authorized to close proposal")
+        );
+        assert!(matches!(
+            governance.get_proposal(proposal_id).unwrap().status(),
+            ProposalStatus::Active
+        ));
+    }
+
+    #[test]
+    fn test_close_proposal_after_deadline() {
+        let alice = 1u64;
+        let closer = 9u64;
+        let mut governance = GovernancePallet::<Runtime>::new();
+        governance.add_closer(Origin::Root, closer).unwrap();
+
+        governance.on_initialize(5);
+        let proposal_id = governance.create_proposal(alice, "Close me".to_string()).unwrap();
+        governance.vote(alice, proposal_id, true).unwrap();
+
+        // VOTING_PERIOD = 20: the proposal cannot close before block 25
+        governance.on_initialize(24);
+        assert_eq!(
+            governance.close_proposal(closer, proposal_id),
+            Err("Voting period not ended")
+        );
+
+        governance.on_initialize(25);
+        let status = governance.close_proposal(closer, proposal_id).unwrap();
+        assert!(matches!(status, ProposalStatus::Approved));
+    }
+
+    // A 20-block voting period that a quorum of two can cut short after five blocks
+    crate::test_runtime!(ReadinessRuntime {
+        AccountId = u64,
+        Balance = u64,
+        VOTING_PERIOD: u64 = 20,
+        MIN_VOTING_BLOCKS: u64 = 5,
+        MIN_QUORUM: u32 = 2,
+    });
+
+    #[test]
+    fn test_can_finalize() {
+        let mut governance = GovernancePallet::<ReadinessRuntime>::new();
+        let quiet = governance.create_proposal(1, "Ready later".to_string()).unwrap();
+        let busy = governance.create_proposal(2, "Ready early".to_string()).unwrap();
+        governance.vote(1, busy, true).unwrap();
+        governance.vote(2, busy, true).unwrap();
+
+        // Without the quorum, only the deadline makes a proposal ready
+        assert!(!governance.can_finalize(quiet, 19));
+        assert!(governance.can_finalize(quiet, 20));
+        // With it, the minimum voting window is all that is left
+        assert!(!governance.can_finalize(busy, 4));
+        assert!(governance.can_finalize(busy, 5));
+        assert!(!governance.can_finalize(99, 20));
+
+        // finalize_proposal agrees at every step
+        governance.on_initialize(4);
+        assert_eq!(governance.finalize_proposal(busy), Err("Minimum voting window not elapsed"));
+        governance.on_initialize(19);
+        assert_eq!(governance.finalize_proposal(quiet), Err("Voting period not ended"));
+        assert_eq!(governance.finalize_proposal(busy), Ok(ProposalStatus::Approved));
+        assert!(!governance.can_finalize(busy, 20));
+        governance.on_initialize(20);
+        assert_eq!(governance.finalize_proposal(quiet), Ok(ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_dependent_proposal_validation() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        assert_eq!(
+            governance.create_dependent_proposal(1, "Self".to_string(), 0),
+            Err("Proposal cannot depend on itself")
+        );
+        assert_eq!(
+            governance.create_dependent_proposal(1, "Missing".to_string(), 5),
+            Err("Dependency does not exist")
+        );
+
+        // Setting a dependency later goes through the same checks
+        let first = governance.create_proposal(1, "First".to_string()).unwrap();
+        let second = governance.create_proposal(1, "Second".to_string()).unwrap();
+        assert_eq!(governance.set_depends_on(first, Some(first)), Err("Proposal cannot depend on itself"));
+        assert_eq!(governance.set_depends_on(first, Some(9)), Err("Dependency does not exist"));
+        assert_eq!(governance.set_depends_on(9, Some(first)), Err("Proposal does not exist"));
+        assert_eq!(governance.set_depends_on(second, Some(first)), Ok(()));
+        assert_eq!(governance[second].depends_on(), Some(first));
+        assert_eq!(governance.set_depends_on(second, None), Ok(()));
+        assert_eq!(governance[second].depends_on(), None);
+    }
+
+    #[test]
+    fn test_dependent_proposal_rejected_with_dependency() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let first = governance.create_proposal(1, "First step".to_string()).unwrap();
+        let second = governance
+            .create_dependent_proposal(1, "Second step".to_string(), first)
+            .unwrap();
+        assert_eq!(governance.get_proposal(second).unwrap().depends_on(), Some(first));
+
+        governance.vote(1, first, false).unwrap();
+        governance.vote(1, second, true).unwrap();
+        governance.vote(2, second, true).unwrap();
+
+        assert_eq!(governance.finalize_proposal(first), Ok(ProposalStatus::Rejected));
+        // Unanimous support does not help once the dependency is rejected
+        assert_eq!(governance.finalize_proposal(second), Ok(ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_dependent_proposal_approved_after_dependency() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let first = governance.create_proposal(1, "First step".to_string()).unwrap();
+        let second = governance
+            .create_dependent_proposal(1, "Second step".to_string(), first)
+            .unwrap();
+        governance.vote(1, first, true).unwrap();
+        governance.vote(1, second, true).unwrap();
+
+        assert_eq!(governance.finalize_proposal(first), Ok(ProposalStatus::Approved));
+        assert_eq!(governance.finalize_proposal(second), Ok(ProposalStatus::Approved));
+    }
+
+    #[test]
+    fn test_scheduled_proposal_opens_at_start_block() {
+        let alice = 1u64;
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let proposal_id = governance
+            .create_scheduled_proposal(alice, "Campaign".to_string(), 10)
+            .unwrap();
+        let proposal = governance.get_proposal(proposal_id).unwrap();
+        assert_eq!(proposal.status(), &ProposalStatus::Scheduled);
+        assert_eq!(proposal.voting_start(), 10);
+        assert_eq!(proposal.deadline(), 30);
+
+        governance.on_initialize(9);
+        assert_eq!(governance.vote(alice, proposal_id, true), Err("Voting not yet started"));
+        assert_eq!(governance.finalize_proposal(proposal_id), Err("Voting not yet started"));
+
+        governance.on_initialize(10);
+        assert_eq!(
+            governance.get_proposal(proposal_id).unwrap().status(),
+            &ProposalStatus::Active
+        );
+        assert!(governance.vote(alice, proposal_id, true).is_ok());
+    }
+
+    #[test]
+    fn test_export_audit() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        staking.set_balance(1, 500).unwrap();
+
+        governance.create_proposal(2, "Free".to_string()).unwrap();
+        let paid = governance
+            .create_proposal_with_deposit(&mut staking, 1, "Paid".to_string())
+            .unwrap();
+        governance.vote(3, paid, true).unwrap();
+        governance.finalize_proposal(paid).unwrap();
+
+        let records = governance.export_audit();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].creator, 2);
+        assert_eq!(records[0].deposit, Some(0));
+        assert_eq!(records[1].proposal_id, paid);
+        assert_eq!(records[1].status, ProposalStatus::Approved);
+        assert_eq!(records[1].yes_votes, 1);
+        assert_eq!(records[1].deposit, Some(100));
+    }
+
+    #[test]
+    fn test_creator_history_records_outcomes() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let approved = governance.create_proposal(1, "Pass".to_string()).unwrap();
+        let rejected = governance.create_proposal(1, "Fail".to_string()).unwrap();
+        governance.create_proposal(1, "Open".to_string()).unwrap();
+        governance.vote(2, approved, true).unwrap();
+        governance.vote(2, rejected, false).unwrap();
+
+        governance.finalize_proposal(rejected).unwrap();
+        governance.finalize_proposal(approved).unwrap();
+
+        assert_eq!(
+            governance.creator_history(&1),
+            vec![(rejected, ProposalStatus::Rejected), (approved, ProposalStatus::Approved)]
+        );
+        assert!(governance.creator_history(&2).is_empty());
+    }
+
+    #[test]
+    fn test_export_audit_since() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        governance.create_proposal(1, "Old".to_string()).unwrap();
+        governance.on_initialize(5);
+        let fifth = governance.create_proposal(2, "Fifth".to_string()).unwrap();
+        governance.on_initialize(9);
+        let ninth = governance.create_proposal(3, "Ninth".to_string()).unwrap();
+        let also_ninth = governance.create_proposal(1, "Also ninth".to_string()).unwrap();
+
+        let records = governance.export_audit_since(5);
+        let ids: Vec<u32> = records.iter().map(|record| record.proposal_id).collect();
+        assert_eq!(ids, vec![fifth, ninth, also_ninth]);
+        assert_eq!(records[0].created_at, 5);
+        assert_eq!(governance.export_audit_since(10).len(), 0);
+        assert_eq!(governance.export_audit_since(0).len(), 4);
+    }
+
+    #[test]
+    fn test_vote_receipts_are_sequenced() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let first = governance.create_proposal(1, "First".to_string()).unwrap();
+        let second = governance.create_proposal(1, "Second".to_string()).unwrap();
+
+        let a = governance.vote(1, first, true).unwrap();
+        let b = governance.vote(2, second, false).unwrap();
+        let c = governance.vote(3, first, false).unwrap();
+
+        assert_eq!(
+            a,
+            VoteReceipt { proposal_id: first, voter: 1, choice: true, sequence: 0 }
+        );
+        assert_eq!(b.proposal_id, second);
+        assert!(!b.choice);
+        assert!(a.sequence < b.sequence && b.sequence < c.sequence);
+
+        // A rejected vote does not consume a sequence number or change the recorded vote
+        assert!(governance.vote(1, first, false).is_err());
+        assert_eq!(governance.vote(4, second, true).unwrap().sequence, 3);
+        assert_eq!(governance.voters_for(first, true), vec![1]);
+    }
+
+    #[test]
+    fn test_description_budget() {
+        let alice = 1u64;
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+
+        // MAX_TOTAL_DESC_BYTES = 1024 fits four maximum-length descriptions
+        let ids: Vec<u32> = (0..4)
+            .map(|_| governance.create_proposal(alice, "x".repeat(256)).unwrap())
+            .collect();
+        assert_eq!(governance.description_bytes(), 1024);
+        assert_eq!(
+            governance.create_proposal(alice, "y".to_string()),
+            Err("Description budget exceeded")
+        );
+
+        // Cancelling frees the budget
+        governance.cancel_proposal(&mut staking, alice, ids[0]).unwrap();
+        assert_eq!(governance.description_bytes(), 768);
+        assert!(governance.create_proposal(alice, "y".repeat(256)).is_ok());
+
+        // So does pruning a finalized proposal
+        governance.finalize_proposal(ids[1]).unwrap();
+        governance.prune_proposal(&mut staking, ids[1]).unwrap();
+        assert!(!governance.proposal_exists(ids[1]));
+        assert_eq!(governance.description_bytes(), 768);
+    }
+
+    #[test]
+    fn test_cancel_and_prune_rules() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        let proposal_id = governance.create_proposal(alice, "Cancel me".to_string()).unwrap();
+
+        assert_eq!(
+            governance.prune_proposal(&mut staking, proposal_id),
+            Err("Cannot prune open proposal")
+        );
+        assert_eq!(
+            governance.cancel_proposal(&mut staking, bob, proposal_id),
+            Err("Only the creator can cancel the proposal")
+        );
+
+        governance.cancel_proposal(&mut staking, alice, proposal_id).unwrap();
+        assert_eq!(
+            governance.get_proposal(proposal_id).unwrap().status(),
+            &ProposalStatus::Cancelled
+        );
+        assert_eq!(
+            governance.cancel_proposal(&mut staking, alice, proposal_id),
+            Err("Cannot cancel finalized proposal")
+        );
+        assert_eq!(governance.vote(bob, proposal_id, true), Err("Cannot vote on finalized proposal"));
+    }
+
+    #[test]
+    fn test_force_finalize_overrides_tally() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = governance.create_proposal(1, "Emergency".to_string()).unwrap();
+        governance.vote(1, proposal_id, true).unwrap();
+        governance.vote(2, proposal_id, true).unwrap();
+
+        assert_eq!(
+            governance.force_finalize(Origin::Root, proposal_id, ProposalStatus::Active),
+            Err("Forced status must be Approved or Rejected")
+        );
+
+        // Rejected despite the yes majority
+        assert_eq!(
+            governance.force_finalize(Origin::Root, proposal_id, ProposalStatus::Rejected),
+            Ok(ProposalStatus::Rejected)
+        );
+        assert_eq!(
+            governance.get_proposal(proposal_id).unwrap().status(),
+            &ProposalStatus::Rejected
+        );
+
+        assert_eq!(
+            governance.force_finalize(Origin::Root, proposal_id, ProposalStatus::Approved),
+            Err("Proposal already finalized")
+        );
+    }
+
+    #[test]
+    fn test_idempotent_create_proposal() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let key = Some([7u8; 32]);
+
+        let first = governance
+            .create_proposal_with_key(1, "Retry me".to_string(), key)
+            .unwrap();
+        let retry = governance
+            .create_proposal_with_key(1, "Retry me".to_string(), key)
+            .unwrap();
+        assert_eq!(first, retry);
+        assert_eq!((&governance).into_iter().count(), 1);
+
+        // Without a key, or with a different key, a new proposal is created
+        let unkeyed = governance
+            .create_proposal_with_key(1, "Retry me".to_string(), None)
+            .unwrap();
+        let other = governance
+            .create_proposal_with_key(1, "Retry me".to_string(), Some([8u8; 32]))
+            .unwrap();
+        assert_ne!(unkeyed, first);
+        assert_ne!(other, first);
+        assert_eq!((&governance).into_iter().count(), 3);
+    }
+
+    #[test]
+    fn test_decide_status_table() {
+        use ProposalStatus::{Approved, Rejected};
+
+        // (yes, no, abstain, quorum, threshold_percent, expected)
+        let cases = [
+            // Simple majority
+            (0, 0, 0, 0, 50, Rejected),
+            (1, 0, 0, 0, 50, Approved),
+            (0, 1, 0, 0, 50, Rejected),
+            (1, 1, 0, 0, 50, Rejected),
+            (2, 1, 0, 0, 50, Approved),
+            (1, 2, 0, 0, 50, Rejected),
+            (51, 49, 0, 0, 50, Approved),
+            (50, 50, 0, 0, 50, Rejected),
+            (49, 51, 0, 0, 50, Rejected),
+            (u32::MAX, u32::MAX - 1, 0, 0, 50, Approved),
+            // Quorum
+            (3, 0, 0, 3, 50, Approved),
+            (2, 0, 0, 3, 50, Rejected),
+            (2, 1, 0, 3, 50, Approved),
+            (2, 0, 1, 3, 50, Approved),
+            (1, 0, 2, 3, 50, Approved),
+            (0, 0, 3, 3, 50, Rejected),
+            (5, 4, 0, 10, 50, Rejected),
+            (5, 4, 1, 10, 50, Approved),
+            (10, 0, 0, 11, 50, Rejected),
+            // Abstentions never change the yes/no split
+            (1, 1, 100, 0, 50, Rejected),
+            (2, 1, 100, 0, 50, Approved),
+            (60, 40, 1000, 0, 60, Rejected),
+            // Supermajority thresholds
+            (2, 1, 0, 0, 66, Approved),
+            (66, 34, 0, 0, 66, Rejected),
+            (67, 33, 0, 0, 66, Approved),
+            (3, 1, 0, 0, 75, Rejected),
+            (4, 1, 0, 0, 75, Approved),
+            (9, 1, 0, 0, 90, Rejected),
+            (10, 0, 0, 0, 90, Approved),
+            (99, 1, 0, 0, 99, Rejected),
+            (100, 0, 0, 0, 99, Approved),
+            // Unanimity can never pass a 100% strict threshold
+            (10, 0, 0, 0, 100, Rejected),
+            // A zero threshold needs at least one yes
+            (0, 5, 0, 0, 0, Rejected),
+            (1, 5, 0, 0, 0, Approved),
+            // Minority thresholds
+            (1, 3, 0, 0, 20, Approved),
+            (1, 4, 0, 0, 20, Rejected),
+            // Quorum combined with a supermajority
+            (7, 3, 0, 10, 66, Approved),
+            (7, 3, 0, 11, 66, Rejected),
+            (7, 3, 1, 11, 66, Approved),
+            (6, 3, 1, 10, 66, Approved),
+            (6, 4, 0, 10, 66, Rejected),
+        ];
+
+        for (yes, no, abstain, quorum, threshold, expected) in cases {
+            let participation = yes as u64 + no as u64 + abstain as u64;
+            assert_eq!(
+                decide_status(yes as u64, no as u64, participation, quorum, threshold, false),
+                Ok(expected),
+                "yes={} no={} abstain={} quorum={} threshold={}",
+                yes,
+                no,
+                abstain,
+                quorum,
+                threshold
+            );
+        }
+
+        // Ties and weighed tallies
+        assert_eq!(decide_status(50u64, 50, 100, 0, 50, true), Ok(Approved));
+        assert_eq!(decide_status(0u64, 0, 3, 0, 50, true), Ok(Rejected));
+        assert_eq!(decide_status(5_000u64, 4_000, 2, 3, 50, false), Ok(Rejected));
+        assert_eq!(decide_status(5_000u64, 4_000, 3, 3, 50, false), Ok(Approved));
+        assert_eq!(decide_status(u64::MAX, 1, 2, 0, 50, false), Err("Overflow"));
+    }
+
+    #[test]
+    fn test_choice_proposal_picks_weighted_median() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        for (who, stake) in [(1u64, 100u64), (2, 300), (3, 150)] {
+            staking.set_balance(who, stake).unwrap();
+            staking.stake(who, stake).unwrap();
+        }
+
+        let proposal_id = governance
+            .create_choice_proposal(1, "Choose minimum stake".to_string(), StakingParameterKey::MinStake)
+            .unwrap();
+        governance.submit_value(&staking, 1, proposal_id, 10).unwrap();
+        governance.submit_value(&staking, 2, proposal_id, 50).unwrap();
+        governance.submit_value(&staking, 3, proposal_id, 90).unwrap();
+        assert_eq!(
+            governance.submit_value(&staking, 3, proposal_id, 10),
+            Err("Voter has already submitted a value")
+        );
+
+        // Weights 100 / 300 / 150: half of 550 is reached at value 50
+        assert_eq!(governance.finalize_proposal(proposal_id), Ok(ProposalStatus::Approved));
+        assert!(matches!(
+            governance.get_proposal(proposal_id).unwrap().kind(),
+            ProposalKind::SetParameter(StakingParameter::MinStake(50))
+        ));
+    }
+
+    #[test]
+    fn test_choice_proposal_without_submissions_rejected() {
+        let staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+
+        let text_id = governance.create_proposal(1, "Text".to_string()).unwrap();
+        assert_eq!(
+            governance.submit_value(&staking, 1, text_id, 10),
+            Err("Proposal does not accept value submissions")
+        );
+
+        let proposal_id = governance
+            .create_choice_proposal(1, "Choose".to_string(), StakingParameterKey::MinStake)
+            .unwrap();
+        assert_eq!(governance.finalize_proposal(proposal_id), Ok(ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_choice_proposal_refunds_deposit() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        staking.set_balance(1, 2_000).unwrap();
+        staking.stake(1, 500).unwrap();
+
+        let kind = ProposalKind::ChooseParameter(StakingParameterKey::MinStake);
+        let proposal_id = governance
+            .create_kind_with_deposit(&mut staking, 1, "Choose".to_string(), kind)
+            .unwrap();
+        governance.submit_value(&staking, 1, proposal_id, 40).unwrap();
+
+        assert_eq!(governance.finalize_proposal(proposal_id), Ok(ProposalStatus::Approved));
+        assert_eq!(governance.refund_queue, vec![(1, 100, 10)]);
+    }
+
+    #[test]
+    fn test_choice_proposal_needs_submission_turnout() {
+        let mut staking = StakingPallet::<QuorumRuntime>::new();
+        let mut governance = GovernancePallet::<QuorumRuntime>::new();
+        for who in 1..=3u64 {
+            staking.set_balance(who, 100).unwrap();
+            staking.stake(who, 100).unwrap();
+        }
+
+        // A single submission falls short of the quorum of three
+        let lone_id = governance
+            .create_choice_proposal(1, "Lone".to_string(), StakingParameterKey::MinStake)
+            .unwrap();
+        governance.submit_value(&staking, 1, lone_id, 10).unwrap();
+        assert_eq!(governance.finalize_proposal(lone_id), Ok(ProposalStatus::Rejected));
+
+        // Three submissions meet the quorum but not half of an electorate of ten
+        let full_id = governance
+            .create_choice_proposal(1, "Full".to_string(), StakingParameterKey::MinStake)
+            .unwrap();
+        for who in 1..=3u64 {
+            governance.submit_value(&staking, who, full_id, 10).unwrap();
+        }
+        assert_eq!(
+            governance.finalize_proposal_with_electorate(full_id, 2),
+            Err("Electorate smaller than turnout")
+        );
+        assert_eq!(
+            governance.finalize_proposal_with_electorate(full_id, 10),
+            Ok(ProposalStatus::Rejected)
+        );
+    }
+
+    crate::test_runtime!(RateLimitedRuntime {
+        AccountId = u64,
+        Balance = u64,
+        MAX_VOTES_PER_BLOCK: u32 = 2,
+    });
+
+    #[test]
+    fn test_votes_per_block_limited_and_reset() {
+        let mut governance = GovernancePallet::<RateLimitedRuntime>::new();
+        let ids: Vec<u32> = (0..4)
+            .map(|i| governance.create_proposal(1, format!("Proposal {}", i)).unwrap())
+            .collect();
+
+        governance.vote(1, ids[0], true).unwrap();
+        governance.vote(1, ids[1], true).unwrap();
+        assert_eq!(governance.vote(1, ids[2], true), Err("Too many votes in this block"));
+        // The limit is per account
+        assert!(governance.vote(2, ids[2], true).is_ok());
+
+        governance.on_initialize(1);
+        assert!(governance.vote(1, ids[2], true).is_ok());
+        assert!(governance.vote(1, ids[3], false).is_ok());
+    }
+
+    #[test]
+    fn test_add_comments() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        let proposal_id = governance.create_proposal(1, "Discuss".to_string()).unwrap();
+
+        governance.add_comment(2, proposal_id, "Looks good".to_string()).unwrap();
+        governance.add_comment(3, proposal_id, "Needs work".to_string()).unwrap();
+        assert_eq!(
+            governance.comments_for(proposal_id),
+            &[(2, "Looks good".to_string()), (3, "Needs work".to_string())]
+        );
+
+        assert_eq!(
+            governance.add_comment(2, proposal_id, "x".repeat(281)),
+            Err("Comment too long")
+        );
+        assert_eq!(governance.add_comment(2, 99, "Hello".to_string()), Err("Proposal does not exist"));
+
+        governance.cancel_proposal(&mut staking, 1, proposal_id).unwrap();
+        assert_eq!(
+            governance.add_comment(2, proposal_id, "Too late".to_string()),
+            Err("Cannot comment on cancelled proposal")
+        );
+        assert!(governance.comments_for(42).is_empty());
+    }
+
+    crate::test_runtime!(QuietRuntime {
+        AccountId = u64,
+        Balance = u64,
+        MAX_COMMENTS_PER_PROPOSAL: usize = 2,
+    });
+
+    #[test]
+    fn test_comments_per_proposal_capped() {
+        let mut governance = GovernancePallet::<QuietRuntime>::new();
+        let first = governance.create_proposal(1, "First".to_string()).unwrap();
+        let second = governance.create_proposal(1, "Second".to_string()).unwrap();
+
+        governance.add_comment(2, first, "One".to_string()).unwrap();
+        governance.add_comment(3, first, "Two".to_string()).unwrap();
+        assert_eq!(
+            governance.add_comment(4, first, "Three".to_string()),
+            Err("Too many comments on proposal")
+        );
+        assert_eq!(governance.comments_for(first).len(), 2);
+
+        // The cap is per proposal
+        assert!(governance.add_comment(4, second, "Three".to_string()).is_ok());
+    }
+
+    crate::test_runtime!(DeliberateRuntime {
+        AccountId = u64,
+        Balance = u64,
+        MIN_VOTING_BLOCKS: u64 = 3,
+    });
+
+    #[test]
+    fn test_finalize_waits_for_min_voting_window() {
+        let mut governance = GovernancePallet::<DeliberateRuntime>::new();
+        governance.on_initialize(5);
+        let proposal_id = governance.create_proposal(1, "No flash votes".to_string()).unwrap();
+        governance.vote(1, proposal_id, true).unwrap();
+
+        assert_eq!(governance.finalize_proposal(proposal_id), Err("Minimum voting window not elapsed"));
+        governance.on_initialize(7);
+        assert_eq!(governance.finalize_proposal(proposal_id), Err("Minimum voting window not elapsed"));
+
+        governance.on_initialize(8);
+        assert_eq!(governance.finalize_proposal(proposal_id), Ok(ProposalStatus::Approved));
+    }
+
+    crate::test_runtime!(QuotaRuntime {
+        AccountId = u64,
+        Balance = u64,
+        MAX_OPEN_PER_ACCOUNT: u32 = 2,
+    });
+
+    #[test]
+    fn test_open_proposals_per_account_limited() {
+        let mut governance = GovernancePallet::<QuotaRuntime>::new();
+        let mut staking = StakingPallet::<QuotaRuntime>::new();
+        let first = governance.create_proposal(1, "First".to_string()).unwrap();
+        let second = governance.create_scheduled_proposal(1, "Second".to_string(), 10).unwrap();
+        assert_eq!(governance.proposals_open_by(&1), 2);
+
+        assert_eq!(governance.create_proposal(1, "Third".to_string()), Err("Too many open proposals"));
+        // Other accounts have their own quota
+        assert!(governance.create_proposal(2, "Other".to_string()).is_ok());
+
+        governance.finalize_proposal(first).unwrap();
+        let third = governance.create_proposal(1, "Third".to_string()).unwrap();
+        assert_eq!(governance.create_proposal(1, "Fourth".to_string()), Err("Too many open proposals"));
+
+        governance.cancel_proposal(&mut staking, 1, second).unwrap();
+        assert!(governance.create_proposal(1, "Fourth".to_string()).is_ok());
+        assert_eq!(governance.proposals_open_by(&1), 2);
+        assert!(governance.proposal_exists(third));
+    }
+
+    crate::test_runtime!(CooldownRuntime {
+        AccountId = u64,
+        Balance = u64,
+        PROPOSAL_COOLDOWN_BLOCKS: u64 = 10,
+        fn deposit_for(kind: &ProposalKind<Self>) -> u64 {
+            match kind {
+                ProposalKind::SetParameter(_) => 50,
+                _ => Self::PROPOSAL_DEPOSIT,
+            }
+        },
+    });
+
+    #[test]
+    fn test_repropose_waives_cooldown_once() {
+        let mut staking = StakingPallet::<CooldownRuntime>::new();
+        let mut governance = GovernancePallet::<CooldownRuntime>::new();
+        let original = governance
+            .create_parameter_proposal(1, "Raise".to_string(), StakingParameter::MinStake(50))
+            .unwrap();
+        assert_eq!(
+            governance.repropose(&mut staking, 1, original),
+            Err("Only rejected proposals can be re-proposed")
+        );
+        governance.vote(2, original, false).unwrap();
+        assert_eq!(governance.finalize_proposal(original), Ok(ProposalStatus::Rejected));
+
+        assert_eq!(governance.create_proposal(1, "Again".to_string()), Err("Proposal cooldown active"));
+        assert_eq!(governance.repropose(&mut staking, 2, original), Err("Only the creator can re-propose"));
+
+        // The copy pays the parameter change's deposit like a fresh proposal would
+        assert_eq!(governance.repropose(&mut staking, 1, original), Err("Insufficient balance"));
+        staking.set_balance(1, 80).unwrap();
+        let copy = governance.repropose(&mut staking, 1, original).unwrap();
+        assert_eq!(staking.get_reserved_balance(1), 50u64);
+        assert_eq!(governance[copy].deposit(), 50u64);
+        assert_ne!(copy, original);
+        assert_eq!(governance[copy].status(), &ProposalStatus::Active);
+        assert_eq!(governance[copy].description(), "Raise");
+        assert!(matches!(
+            governance[copy].kind(),
+            ProposalKind::SetParameter(StakingParameter::MinStake(50))
+        ));
+        assert_eq!(governance.reproposed_from(copy), Some(original));
+        assert_eq!(governance.repropose(&mut staking, 1, original), Err("Already re-proposed"));
+
+        governance.vote(2, copy, false).unwrap();
+        governance.finalize_proposal(copy).unwrap();
+        assert_eq!(governance.repropose(&mut staking, 1, copy), Err("Cannot re-propose a re-proposal"));
+
+        governance.on_initialize(10);
+        assert!(governance.create_proposal(1, "Later".to_string()).is_ok());
+    }
+
+    crate::test_runtime!(CappedRuntime {
+        AccountId = u64,
+        Balance = u64,
+        MAX_VOTE_WEIGHT: Option<u64> = Some(1_000),
+    });
+
+    #[test]
+    fn test_vote_weight_capped() {
+        let mut staking = StakingPallet::<CappedRuntime>::new();
+        let mut governance = GovernancePallet::<CappedRuntime>::new();
+        for (who, stake) in [(1u64, 1_000_000u64), (2, 600), (3, 600)] {
+            staking.set_balance(who, stake).unwrap();
+            staking.stake(who, stake).unwrap();
+        }
+
+        let proposal_id = governance.create_proposal(1, "Whale".to_string()).unwrap();
+        governance.vote(1, proposal_id, true).unwrap();
+        governance.vote(2, proposal_id, false).unwrap();
+        governance.vote(3, proposal_id, false).unwrap();
+
+        assert_eq!(governance.vote_weight(&staking, &1), 1_000);
+        assert_eq!(governance.stake_weighted_tally(&staking, proposal_id), Some((1_000, 1_200)));
+        assert_eq!(governance.stake_weighted_tally(&staking, 99), None);
+
+        // Uncapped, the whale's whole stake counts
+        let mut uncapped = StakingPallet::<Runtime>::new();
+        uncapped.set_balance(1, 1_000_000).unwrap();
+        uncapped.stake(1, 1_000_000).unwrap();
+        assert_eq!(GovernancePallet::<Runtime>::new().vote_weight(&uncapped, &1), 1_000_000);
+    }
+
     #[test]
-    fn test_governance_should_work() {
-        let alice = 1u64;
-        let bob = 2u64;
-        let charlie = 3u64;
+    fn test_genesis_import_seeds_next_proposal_id() {
+        let mut source = GovernancePallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        for description in ["Zero", "One", "Two"] {
+            source.create_proposal(1, description.to_string()).unwrap();
+        }
+        source.cancel_proposal(&mut staking, 1, 0).unwrap();
+        source.cancel_proposal(&mut staking, 1, 1).unwrap();
+        source.prune_proposal(&mut staking, 0).unwrap();
+
+        let imported = (&source).into_iter().map(|(id, proposal)| (*id, proposal.clone()));
+        let mut governance = GovernancePallet::<Runtime>::from_genesis(imported).unwrap();
+        assert_eq!(governance.next_proposal_id(), 3);
+        assert_eq!(governance.description_bytes(), "Two".len());
+        assert!(governance.proposal_ids_consistent());
+
+        let proposal_id = governance.create_proposal(2, "Fresh".to_string()).unwrap();
+        assert_eq!(proposal_id, 3);
+        assert_eq!(governance[2].description(), "Two");
+
+        assert_eq!(governance.set_next_proposal_id(3), Err("Proposal id already in use"));
+        assert!(governance.set_next_proposal_id(10).is_ok());
+        assert_eq!(governance.create_proposal(2, "Later".to_string()), Ok(10));
+
+        // No id is left above u32::MAX for new proposals
+        let last = (u32::MAX, governance[2].clone());
+        assert_eq!(GovernancePallet::<Runtime>::from_genesis([last]).err(), Some("Overflow"));
+    }
+
+    #[test]
+    fn test_migrate_v1_proposals() {
+        let v1 = vec![
+            (0, ProposalV1::<Runtime> {
+                description: "Old".to_string(),
+                yes_votes: 3,
+                no_votes: 1,
+                status: ProposalStatus::Approved,
+                creator: 1,
+            }),
+            (4, ProposalV1::<Runtime> {
+                description: "Still open".to_string(),
+                yes_votes: 0,
+                no_votes: 2,
+                status: ProposalStatus::Active,
+                creator: 2,
+            }),
+        ];
+
+        let mut governance = GovernancePallet::<Runtime>::from_v1(v1);
+        assert_eq!(governance.storage_version(), StorageVersion(1));
+        assert_eq!(governance.migrate(2), Err("Storage version mismatch"));
+
+        governance.migrate(1).unwrap();
+        assert_eq!(governance.storage_version(), GovernancePallet::<Runtime>::STORAGE_VERSION);
+        assert_eq!(governance.description_bytes(), "Old".len() + "Still open".len());
+        assert_eq!(governance.next_proposal_id(), 5);
+
+        let open = &governance[4];
+        assert_eq!(open.no_votes(), 2);
+        assert!(open.deposit().is_zero());
+        assert!(matches!(open.kind(), ProposalKind::Text));
+        assert_eq!(open.deadline(), 20);
+
+        // Migrated state is current, so a second run is a no-op
+        assert!(governance.migrate(2).is_ok());
+        assert_eq!(governance.create_proposal(1, "New".to_string()), Ok(5));
+
+        // A v1 proposal at u32::MAX leaves no next id; the state stays at v1
+        let last = ProposalV1::<Runtime> {
+            description: "Last".to_string(),
+            yes_votes: 0,
+            no_votes: 0,
+            status: ProposalStatus::Rejected,
+            creator: 1,
+        };
+        let mut governance = GovernancePallet::<Runtime>::from_v1([(u32::MAX, last)]);
+        assert_eq!(governance.migrate(1), Err("Overflow"));
+        assert_eq!(governance.storage_version(), StorageVersion(1));
+        assert_eq!(governance.description_bytes(), 0);
+    }
+
+    crate::test_runtime!(QuorumRuntime {
+        AccountId = u64,
+        Balance = u64,
+        MIN_QUORUM: u32 = 3,
+    });
+
+    crate::test_runtime!(PriorityRuntime {
+        AccountId = u64,
+        Balance = u64,
+        CRITICAL_PRIORITY_QUORUM: u32 = 4,
+        CRITICAL_PRIORITY_THRESHOLD_PERCENT: u32 = 66,
+    });
+
+    #[test]
+    fn test_critical_priority_needs_supermajority() {
+        let mut governance = GovernancePallet::<PriorityRuntime>::new();
+        let normal = governance.create_proposal(1, "Routine".to_string()).unwrap();
+        let critical = governance.create_proposal(1, "Upgrade".to_string()).unwrap();
+        assert_eq!(
+            governance.set_priority(2, critical, ProposalPriority::Critical),
+            Err("Only the creator can set the priority")
+        );
+        governance.set_priority(1, critical, ProposalPriority::Critical).unwrap();
+
+        // Three to two on both: a simple majority, but short of two thirds
+        for proposal_id in [normal, critical] {
+            for voter in 1..=3 {
+                governance.vote(voter, proposal_id, true).unwrap();
+            }
+            for voter in 4..=5 {
+                governance.vote(voter, proposal_id, false).unwrap();
+            }
+        }
+        assert_eq!(
+            governance.set_priority(1, normal, ProposalPriority::Low),
+            Err("Cannot set priority after votes were cast")
+        );
+
+        assert_eq!(governance.finalize_proposal(normal), Ok(ProposalStatus::Approved));
+        assert_eq!(governance.finalize_proposal(critical), Ok(ProposalStatus::Rejected));
+        assert_eq!(governance.get_proposal(critical).unwrap().priority(), ProposalPriority::Critical);
+    }
 
+    #[test]
+    fn test_abstentions_count_towards_quorum_only() {
+        let mut governance = GovernancePallet::<QuorumRuntime>::new();
+
+        // One yes vote alone misses the quorum of three
+        let short = governance.create_proposal(1, "Short".to_string()).unwrap();
+        governance.vote(1, short, true).unwrap();
+        assert_eq!(governance.finalize_proposal(short), Ok(ProposalStatus::Rejected));
+
+        // Two abstentions push the same yes vote over the quorum
+        let pushed = governance.create_proposal(1, "Pushed".to_string()).unwrap();
+        governance.vote(1, pushed, true).unwrap();
+        governance.abstain(2, pushed).unwrap();
+        governance.abstain(3, pushed).unwrap();
+        assert_eq!(governance.abstain(3, pushed), Err("Voter has already voted on this proposal"));
+        assert_eq!(governance.vote(3, pushed, true), Err("Voter has already voted on this proposal"));
+        assert_eq!(governance[pushed].abstain_votes(), 2);
+        assert_eq!(governance.finalize_proposal(pushed), Ok(ProposalStatus::Approved));
+
+        // With the quorum met, the yes/no split still decides
+        let split = governance.create_proposal(1, "Split".to_string()).unwrap();
+        governance.vote(1, split, true).unwrap();
+        governance.vote(2, split, false).unwrap();
+        governance.vote(3, split, false).unwrap();
+        for voter in 4..8 {
+            governance.abstain(voter, split).unwrap();
+        }
+        assert_eq!(governance.finalize_proposal(split), Ok(ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_proposal_setter_changes_only_its_field() {
         let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = governance.create_proposal(1, "Extend me".to_string()).unwrap();
+        governance.vote(2, proposal_id, true).unwrap();
+        assert_eq!(governance[proposal_id].deadline(), 20);
+
+        governance.proposal_mut(proposal_id).unwrap().set_deadline(30);
+        assert!(governance.proposal_mut(99).is_none());
+
+        let proposal = &governance[proposal_id];
+        assert_eq!(proposal.deadline(), 30);
+        assert_eq!(proposal.description(), "Extend me");
+        assert_eq!(proposal.yes_votes(), 1);
+        assert_eq!(proposal.status(), &ProposalStatus::Active);
+        assert_eq!(proposal.voting_start(), 0);
+        assert_eq!(proposal.depends_on(), None);
+    }
+
+    #[test]
+    fn test_vote_batch_order_independent() {
+        let batch = vec![(4u64, false), (1, true), (3, true), (2, false), (1, false)];
+        let mut reversed = batch.clone();
+        reversed.reverse();
+
+        let mut outcomes = Vec::new();
+        for votes in [batch, reversed] {
+            let mut governance = GovernancePallet::<Runtime>::new();
+            let proposal_id = governance.create_proposal(1, "Batched".to_string()).unwrap();
+            assert_eq!(
+                governance.vote_batch(Origin::Signed(1), proposal_id, votes.clone()),
+                Err("Root origin required")
+            );
+            let results = governance.vote_batch(Origin::Root, proposal_id, votes).unwrap();
+            let proposal = &governance[proposal_id];
+            outcomes.push((results, proposal.yes_votes(), proposal.no_votes()));
+        }
+
+        assert_eq!(outcomes[0], outcomes[1]);
+        let (results, yes, no) = &outcomes[0];
+        // Account 1 voted twice: its no is applied first and its yes is rejected
+        assert_eq!((*yes, *no), (1, 3));
+        let voters: Vec<u64> = results.iter().flatten().map(|receipt| receipt.voter).collect();
+        assert_eq!(voters, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_vote_if_requires_existing_participation() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = governance.create_proposal(1, "Turnout".to_string()).unwrap();
+        governance.vote(1, proposal_id, true).unwrap();
+
+        assert_eq!(
+            governance.vote_if(3, proposal_id, true, 2),
+            Err("Participation precondition not met")
+        );
+        assert_eq!(governance[proposal_id].yes_votes(), 1);
+
+        governance.abstain(2, proposal_id).unwrap();
+        let receipt = governance.vote_if(3, proposal_id, false, 2).unwrap();
+        assert_eq!(receipt.voter, 3);
+        assert_eq!(governance[proposal_id].no_votes(), 1);
+        assert_eq!(governance.vote_if(4, 99, true, 0), Err("Proposal does not exist"));
+    }
+
+    #[test]
+    fn test_preview_vote_matches_actual_tally() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = governance.create_proposal(1, "Preview".to_string()).unwrap();
+        governance.vote(1, proposal_id, true).unwrap();
+
+        let preview = governance.preview_vote(proposal_id, false);
+        assert_eq!(preview, Some((1, 1)));
+        assert_eq!(governance[proposal_id].no_votes(), 0);
+
+        governance.vote(2, proposal_id, false).unwrap();
+        let proposal = &governance[proposal_id];
+        assert_eq!(preview, Some((proposal.yes_votes(), proposal.no_votes())));
+
+        governance.finalize_proposal(proposal_id).unwrap();
+        assert_eq!(governance.preview_vote(proposal_id, true), None);
+        assert_eq!(governance.preview_vote(99, true), None);
+    }
+
+    #[test]
+    fn test_archive_summarizes_old_proposals() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        staking.set_balance(1, 100).unwrap();
+        staking.stake(1, 100).unwrap();
+        let old = governance.create_proposal(1, "Old".to_string()).unwrap();
+        let recent = governance.create_proposal(1, "Recent".to_string()).unwrap();
+        let open = governance.create_proposal(1, "Open".to_string()).unwrap();
+        governance.vote_with_stake(&mut staking, 1, old, true).unwrap();
+        governance.vote(2, old, false).unwrap();
+        governance.vote(3, old, true).unwrap();
+        governance.abstain(4, old).unwrap();
+
+        governance.on_initialize(5);
+        governance.finalize_proposal(old).unwrap();
+        governance.on_initialize(40);
+        governance.finalize_proposal(recent).unwrap();
+        assert_eq!(governance[old].closed_at(), Some(5));
+
+        // 100 blocks of retention at block 105: only the proposal closed at 5 has expired
+        assert_eq!(governance.archive(&mut staking, 105, 100), Ok(1));
+        assert!(!governance.proposal_exists(old));
+        // Its votes, abstentions and vote locks go with it
+        assert!(governance.voters_for(old, true).is_empty());
+        assert!(!governance.abstentions.contains(&(4, old)));
+        assert!(staking.vote_locks.is_empty());
+        assert_eq!(
+            governance.archived_summary(old),
+            Some(&ProposalSummary {
+                proposal_id: old,
+                status: ProposalStatus::Approved,
+                yes_votes: 2,
+                no_votes: 1,
+                abstain_votes: 1,
+            })
+        );
+        assert!(governance.proposal_exists(recent));
+        assert!(governance.proposal_exists(open));
+        assert_eq!(governance.description_bytes(), "Recent".len() + "Open".len());
+        assert_eq!(governance.archived_summary(recent), None);
+    }
+
+    // Treats account 0 as the zero address
+    crate::test_runtime!(ZeroAddressRuntime {
+        AccountId = u64,
+        Balance = u64,
+        fn is_valid_account(who: &u64) -> bool {
+            *who != 0
+        },
+    });
+
+    #[test]
+    fn test_invalid_account_rejected() {
+        let mut staking = StakingPallet::<ZeroAddressRuntime>::new();
+        let mut governance = GovernancePallet::<ZeroAddressRuntime>::new();
+
+        assert_eq!(staking.set_balance(0, 100), Err("Invalid account"));
+        assert_eq!(staking.get_free_balance(0), 0);
+        assert_eq!(staking.stake(0, 0), Err("Invalid account"));
+        assert_eq!(governance.create_proposal(0, "Burn".to_string()), Err("Invalid account"));
+
+        assert!(staking.set_balance(1, 100).is_ok());
+        assert!(staking.stake(1, 50).is_ok());
+        assert!(governance.create_proposal(1, "Fine".to_string()).is_ok());
+
+        // An invalid referrer is refused before anything is staked
+        assert_eq!(staking.stake_with_referral(1, 50, 0), Err("Invalid account"));
+        assert_eq!(staking.get_staked_balance(1), 50);
+    }
+
+    #[test]
+    fn test_spend_to_invalid_account_leaves_treasury_unchanged() {
+        let mut staking = StakingPallet::<ZeroAddressRuntime>::new();
+        let mut governance = GovernancePallet::<ZeroAddressRuntime>::new();
+        let mut treasury = TreasuryPallet::<ZeroAddressRuntime>::new();
+        treasury.deposit(100).unwrap();
+        let proposal_id = governance.create_spend_proposal(1, "Grant".to_string(), 0, 60).unwrap();
+        governance.vote(1, proposal_id, true).unwrap();
+        governance.finalize_proposal(proposal_id).unwrap();
+
+        assert_eq!(
+            treasury.spend(&Origin::Root, &governance, proposal_id, &mut staking),
+            Err("Invalid account")
+        );
+        assert_eq!(treasury.balance(), 100);
+
+        // The proposal was not marked as spent by the failed attempt
+        assert_eq!(
+            treasury.spend(&Origin::Root, &governance, proposal_id, &mut staking),
+            Err("Invalid account")
+        );
+    }
+
+    crate::test_runtime!(ReputationRuntime {
+        AccountId = u64,
+        Balance = u64,
+        REPUTATION_PER_APPROVAL: u32 = 25,
+    });
+
+    #[test]
+    fn test_snapshot_power_ignores_later_transfers() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        staking.set_balance(alice, 800).unwrap();
+        staking.stake(alice, 300).unwrap();
+        staking.set_balance(bob, 100).unwrap();
 
-        // Create a proposal
         let proposal_id = governance
-            .create_proposal(alice, "Increase validator rewards".to_string())
+            .create_proposal_with_snapshot(&staking, alice, "Fixed".to_string())
             .unwrap();
+        let live = governance.create_proposal(alice, "Live".to_string()).unwrap();
+        assert_eq!(governance.snapshot_power(live, &alice), None);
 
-        // Cast votes
-        governance.vote(alice, proposal_id, true).unwrap(); // Yes vote
-        governance.vote(bob, proposal_id, true).unwrap(); // Yes vote
-        governance.vote(charlie, proposal_id, false).unwrap(); // No vote
+        // Alice hands most of her free balance to Bob after creation
+        staking.set_balance(alice, 100).unwrap();
+        staking.set_balance(bob, 500).unwrap();
+        staking.stake(bob, 500).unwrap();
 
-        // Check proposal status before finalization
-        let proposal = governance.get_proposal(proposal_id).unwrap();
-        assert_eq!(proposal.yes_votes, 2);
-        assert_eq!(proposal.no_votes, 1);
-        assert_eq!(proposal.description, "Increase validator rewards".to_string());
-        assert_eq!(proposal.creator, alice);
+        assert_eq!(governance.snapshot_power(proposal_id, &alice), Some(800));
+        assert_eq!(governance.snapshot_power(proposal_id, &bob), Some(100));
+        assert_eq!(governance.snapshot_power(proposal_id, &3), Some(0));
 
-        // Finalize proposal
-        let status = governance.finalize_proposal(proposal_id).unwrap();
-        assert!(matches!(status, ProposalStatus::Approved));
+        governance.vote(alice, proposal_id, true).unwrap();
+        governance.vote(bob, proposal_id, false).unwrap();
+        assert_eq!(governance.stake_weighted_tally(&staking, proposal_id), Some((800, 100)));
+    }
 
-        // Check proposal is now approved
-        let finalized_proposal = governance.get_proposal(proposal_id).unwrap();
+    #[test]
+    fn test_snapshot_power_is_capped() {
+        let (whale, minnow) = (1u64, 2u64);
+        let mut staking = StakingPallet::<CappedRuntime>::new();
+        let mut governance = GovernancePallet::<CappedRuntime>::new();
+        staking.set_balance(whale, 1_000_000).unwrap();
+        staking.set_balance(minnow, 600).unwrap();
+
+        let proposal_id = governance
+            .create_proposal_with_snapshot(&staking, whale, "Whale".to_string())
+            .unwrap();
+        governance.vote(whale, proposal_id, true).unwrap();
+        governance.vote(minnow, proposal_id, false).unwrap();
+
+        // The snapshot records the whole balance, but the tally caps it like live stake
+        assert_eq!(governance.snapshot_power(proposal_id, &whale), Some(1_000_000));
+        assert_eq!(governance.stake_weighted_tally(&staking, proposal_id), Some((1_000, 600)));
+    }
+
+    #[test]
+    fn test_reputation_scales_vote_weight() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut staking = StakingPallet::<ReputationRuntime>::new();
+        let mut governance = GovernancePallet::<ReputationRuntime>::new();
+        for who in [alice, bob] {
+            staking.set_balance(who, 1_000).unwrap();
+            staking.stake(who, 400).unwrap();
+        }
+        assert_eq!(governance.vote_weight(&staking, &alice), governance.vote_weight(&staking, &bob));
+
+        // An approved proposal earns its creator reputation
+        let proposal_id = governance.create_proposal(alice, "Earn".to_string()).unwrap();
+        governance.vote(bob, proposal_id, true).unwrap();
+        assert_eq!(governance.finalize_proposal(proposal_id), Ok(ProposalStatus::Approved));
+        assert_eq!(governance.reputation_of(&alice), 25);
+        assert_eq!(governance.reputation_of(&bob), 0);
+        assert_eq!(governance.vote_weight(&staking, &alice), 500);
+        assert_eq!(governance.vote_weight(&staking, &bob), 400);
+
+        assert_eq!(governance.set_reputation(Origin::Signed(bob), bob, 50), Err("Root origin required"));
+        governance.set_reputation(Origin::Root, bob, 50).unwrap();
+        assert_eq!(governance.vote_weight(&staking, &bob), 600);
+    }
+
+    crate::test_runtime!(QuadraticRuntime {
+        AccountId = u64,
+        Balance = u64,
+        VOTING_MODE: VotingMode = VotingMode::Quadratic,
+    });
+
+    crate::test_runtime!(HeadcountRuntime {
+        AccountId = u64,
+        Balance = u64,
+        VOTING_MODE: VotingMode = VotingMode::OneAccountOneVote,
+    });
+
+    // A whale with 1_000_000 staked votes yes; four holders of 10_000 each vote no
+    fn whale_vote<T: GovernanceConfig<AccountId = u64, Balance = u64>>(
+    ) -> (StakingPallet<T>, GovernancePallet<T>, u32) {
+        let mut staking = StakingPallet::<T>::new();
+        let mut governance = GovernancePallet::<T>::new();
+        for (who, stake) in [(1u64, 1_000_000u64), (2, 10_000), (3, 10_000), (4, 10_000), (5, 10_000)] {
+            staking.set_balance(who, stake).unwrap();
+            staking.stake(who, stake).unwrap();
+        }
+
+        let proposal_id = governance.create_proposal(1, "Whale".to_string()).unwrap();
+        for who in 1..=5u64 {
+            governance.vote(who, proposal_id, who == 1).unwrap();
+        }
+
+        (staking, governance, proposal_id)
+    }
+
+    fn whale_tally<T: GovernanceConfig<AccountId = u64, Balance = u64>>() -> Option<(u64, u64)> {
+        let (staking, governance, proposal_id) = whale_vote::<T>();
+        governance.stake_weighted_tally(&staking, proposal_id)
+    }
+
+    #[test]
+    fn test_voting_modes_weigh_whale_differently() {
+        assert_eq!(whale_tally::<Runtime>(), Some((1_000_000, 40_000)));
+        // sqrt(1_000_000) = 1_000 against 4 * sqrt(10_000) = 400
+        assert_eq!(whale_tally::<QuadraticRuntime>(), Some((1_000, 400)));
+        assert_eq!(whale_tally::<HeadcountRuntime>(), Some((1, 4)));
+    }
+
+    #[test]
+    fn test_weighted_finalization_decides_by_stake() {
+        let (staking, mut governance, proposal_id) = whale_vote::<Runtime>();
+        assert_eq!(
+            governance.finalize_proposal_weighted(&staking, proposal_id),
+            Ok(ProposalStatus::Approved)
+        );
+
+        // Counting heads, the four small holders outvote the whale
+        let (staking, mut governance, proposal_id) = whale_vote::<HeadcountRuntime>();
+        assert_eq!(
+            governance.finalize_proposal_weighted(&staking, proposal_id),
+            Ok(ProposalStatus::Rejected)
+        );
+        assert_eq!(governance.finalize_proposal_weighted(&staking, 99), Err("Proposal does not exist"));
+    }
+
+    #[test]
+    fn test_origins_checked() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = governance.create_proposal(1, "Signed".to_string()).unwrap();
+
+        assert_eq!(
+            governance.vote_signed(Origin::Signed(2), 1, proposal_id, true),
+            Err("Signer does not match account")
+        );
+        assert_eq!(
+            governance.vote_signed(Origin::Root, 1, proposal_id, true),
+            Err("Signed origin required")
+        );
+        assert_eq!(governance[proposal_id].yes_votes(), 0);
+        assert!(governance.vote_signed(Origin::Signed(1), 1, proposal_id, true).is_ok());
+
+        assert_eq!(
+            governance.create_proposal_signed(Origin::Signed(2), 1, "Forged".to_string()),
+            Err("Signer does not match account")
+        );
+        let mut staking = StakingPallet::<Runtime>::new();
+        assert_eq!(
+            governance.cancel_proposal_signed(Origin::Root, &mut staking, 1, proposal_id),
+            Err("Signed origin required")
+        );
+        assert_eq!(governance[proposal_id].status(), &ProposalStatus::Active);
+
+        assert_eq!(
+            governance.force_finalize(Origin::Signed(1), proposal_id, ProposalStatus::Approved),
+            Err("Root origin required")
+        );
+        assert_eq!(governance.add_closer(Origin::Signed(1), 1), Err("Root origin required"));
+        assert_eq!(governance.remove_closer(Origin::Signed(1), &1), Err("Root origin required"));
+        assert_eq!(
+            governance.force_finalize(Origin::Root, proposal_id, ProposalStatus::Approved),
+            Ok(ProposalStatus::Approved)
+        );
+    }
+
+    crate::test_runtime!(UnanimousRuntime {
+        AccountId = u64,
+        Balance = u64,
+        APPROVAL_THRESHOLD_PERCENT: u32 = 101,
+    });
+
+    crate::test_runtime!(InvertedDescriptionRuntime {
+        AccountId = u64,
+        Balance = u64,
+        MIN_DESCRIPTION_LEN: usize = 10,
+        MAX_DESCRIPTION_LEN: usize = 5,
+    });
+
+    #[test]
+    fn test_new_checked_validates_config() {
+        assert!(GovernancePallet::<Runtime>::new_checked().is_ok());
+        assert!(GovernancePallet::<QuadraticRuntime>::new_checked().is_ok());
+
+        assert_eq!(
+            GovernancePallet::<UnanimousRuntime>::new_checked().err(),
+            Some("Approval threshold out of range")
+        );
+        assert_eq!(
+            GovernancePallet::<InvertedDescriptionRuntime>::new_checked().err(),
+            Some("Description bounds inconsistent")
+        );
+    }
+
+    #[test]
+    fn test_topic_delegation_routes_only_tagged_proposals() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        let (alice, delegate) = (1u64, 2u64);
+        for who in [alice, delegate] {
+            staking.set_balance(who, 1_000).unwrap();
+        }
+        staking.stake(alice, 300).unwrap();
+        staking.stake(delegate, 100).unwrap();
+
+        let treasury = governance.create_proposal(delegate, "Fund grants".to_string()).unwrap();
+        governance.tag_proposal(delegate, treasury, "treasury".to_string()).unwrap();
+        let runtime = governance.create_proposal(delegate, "Upgrade".to_string()).unwrap();
+        governance.tag_proposal(delegate, runtime, "runtime".to_string()).unwrap();
+
+        assert_eq!(
+            governance.delegate_topic(alice, "treasury".to_string(), alice),
+            Err("Cannot delegate to yourself")
+        );
+        governance.delegate_topic(alice, "treasury".to_string(), delegate).unwrap();
+        assert_eq!(governance.delegate_for(treasury, &alice), Some(&delegate));
+        assert_eq!(governance.delegate_for(runtime, &alice), None);
+
+        governance.vote(delegate, treasury, true).unwrap();
+        governance.vote(delegate, runtime, true).unwrap();
+        governance.vote(alice, runtime, false).unwrap();
+
+        // Alice's stake follows the delegate on treasury but only her own vote on runtime
+        assert_eq!(governance.stake_weighted_tally(&staking, treasury), Some((400, 0)));
+        assert_eq!(governance.stake_weighted_tally(&staking, runtime), Some((100, 300)));
+
+        // A direct vote overrides the delegation
+        governance.vote(alice, treasury, false).unwrap();
+        assert_eq!(governance.stake_weighted_tally(&staking, treasury), Some((100, 300)));
+    }
+
+    // Stake held outside any StakingPallet
+    struct MockBalances(HashMap<u64, u64>);
+
+    impl BalanceProvider<Runtime> for MockBalances {
+        fn staked_balance(&self, who: &u64) -> u64 {
+            self.0.get(who).copied().unwrap_or(0)
+        }
+
+        fn free_balance(&self, _who: &u64) -> u64 {
+            0
+        }
+    }
+
+    #[test]
+    fn test_stake_weighted_tally_with_mock_balances() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let balances = MockBalances(HashMap::from([(1, 700), (2, 200), (3, 50)]));
+        let proposal_id = governance.create_proposal(1, "Mocked".to_string()).unwrap();
+
+        governance.vote(1, proposal_id, true).unwrap();
+        governance.vote(2, proposal_id, false).unwrap();
+        governance.vote(3, proposal_id, false).unwrap();
+
+        assert_eq!(governance.vote_weight(&balances, &2), 200);
+        assert_eq!(governance.stake_weighted_tally(&balances, proposal_id), Some((700, 250)));
+    }
+
+    #[test]
+    fn test_proposal_exists_and_is_active() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let proposal_id = governance.create_proposal(1, "Predicates".to_string()).unwrap();
+
+        assert!(governance.proposal_exists(proposal_id));
+        assert!(governance.is_active(proposal_id));
+        assert!(!governance.proposal_exists(proposal_id + 1));
+        assert!(!governance.is_active(proposal_id + 1));
+
+        governance.finalize_proposal(proposal_id).unwrap();
+        assert!(governance.proposal_exists(proposal_id));
+        assert!(!governance.is_active(proposal_id));
+    }
+
+    #[test]
+    fn test_cancel_releases_vote_locks() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let mut staking = StakingPallet::<Runtime>::new();
+        let (alice, bob) = (1u64, 2u64);
+        staking.set_balance(bob, 500).unwrap();
+        staking.stake(bob, 500).unwrap();
+
+        let proposal_id = governance.create_proposal(alice, "Withdrawn".to_string()).unwrap();
+        governance.vote_with_stake(&mut staking, bob, proposal_id, true).unwrap();
+        governance.abstain(alice, proposal_id).unwrap();
+        assert_eq!(staking.unstake(bob, 500), Err("Stake locked by active vote"));
+
+        governance.cancel_proposal(&mut staking, alice, proposal_id).unwrap();
+        assert!(governance.voters_for(proposal_id, true).is_empty());
+        assert!(!governance.votes.contains_key(&(bob, proposal_id)));
+        assert_eq!(staking.locked_for_voting(&bob), 0u64);
+        assert!(staking.unstake(bob, 500).is_ok());
+
+        // Submissions go too, as they would on pruning
+        let choice = governance
+            .create_choice_proposal(alice, "Withdrawn choice".to_string(), StakingParameterKey::MinStake)
+            .unwrap();
+        governance.submit_value(&staking, bob, choice, 10).unwrap();
+        governance.cancel_proposal(&mut staking, alice, choice).unwrap();
+        assert!(!governance.submissions.contains_key(&choice));
+    }
+
+    #[test]
+    fn test_switch_voting_mode_at_runtime() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        for (who, stake) in [(1u64, 900u64), (2, 100), (3, 100)] {
+            staking.set_balance(who, stake).unwrap();
+            staking.stake(who, stake).unwrap();
+        }
+        assert_eq!(governance.voting_mode(), VotingMode::Linear);
+
+        let first = governance.create_proposal(1, "By stake".to_string()).unwrap();
+        assert_eq!(
+            governance.set_voting_mode(Origin::Signed(1), VotingMode::OneAccountOneVote),
+            Err("Root origin required")
+        );
+        assert_eq!(
+            governance.set_voting_mode(Origin::Root, VotingMode::OneAccountOneVote),
+            Err("Cannot switch voting mode with active proposals")
+        );
+        for who in 1..=3u64 {
+            governance.vote(who, first, who == 1).unwrap();
+        }
+        assert_eq!(governance.stake_weighted_tally(&staking, first), Some((900, 200)));
+        assert_eq!(
+            governance.finalize_proposal_weighted(&staking, first),
+            Ok(ProposalStatus::Approved)
+        );
+
+        governance.set_voting_mode(Origin::Root, VotingMode::OneAccountOneVote).unwrap();
+        let second = governance.create_proposal(1, "By head".to_string()).unwrap();
+        for who in 1..=3u64 {
+            governance.vote(who, second, who == 1).unwrap();
+        }
+        assert_eq!(governance.stake_weighted_tally(&staking, second), Some((1, 2)));
+        // The same votes now lose: the switch changes the outcome, not just the tally
+        assert_eq!(
+            governance.finalize_proposal_weighted(&staking, second),
+            Ok(ProposalStatus::Rejected)
+        );
+    }
+
+    #[cfg(feature = "codec")]
+    #[test]
+    fn test_codec_round_trip() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let text = governance.create_proposal(1, "Text".to_string()).unwrap();
+        governance.tag_proposal(1, text, "runtime".to_string()).unwrap();
+        governance.vote(2, text, true).unwrap();
+        let parameter = governance
+            .create_parameter_proposal(1, "Raise".to_string(), StakingParameter::MinStake(50))
+            .unwrap();
+        governance.set_priority(1, parameter, ProposalPriority::Critical).unwrap();
+        staking.set_balance(3, 40).unwrap();
+        let snapshotted = governance
+            .create_proposal_with_snapshot(&staking, 1, "Fixed".to_string())
+            .unwrap();
+        let cancelled = governance.create_proposal(1, "Gone".to_string()).unwrap();
+        governance.cancel_proposal(&mut staking, 1, cancelled).unwrap();
+
+        let bytes = governance.encode();
+        let decoded = GovernancePallet::<Runtime>::decode(&bytes).unwrap();
+        assert_eq!(decoded.encode(), bytes);
+        assert_eq!(decoded.next_proposal_id(), governance.next_proposal_id());
+        assert_eq!(decoded.description_bytes(), governance.description_bytes());
+        assert_eq!(decoded[text].yes_votes(), 1);
+        assert!(decoded[text].tags().contains("runtime"));
         assert!(matches!(
-            finalized_proposal.status,
-            ProposalStatus::Approved
+            decoded[parameter].kind(),
+            ProposalKind::SetParameter(StakingParameter::MinStake(50))
         ));
+        assert_eq!(decoded[parameter].priority(), ProposalPriority::Critical);
+        assert_eq!(decoded.snapshot_power(snapshotted, &3), Some(40));
+        assert_eq!(decoded.snapshot_power(text, &3), None);
+        assert_eq!(decoded[cancelled].status(), &ProposalStatus::Cancelled);
+    }
+
+    #[cfg(feature = "codec")]
+    #[test]
+    fn test_codec_rejects_malformed_bytes() {
+        let mut governance = GovernancePallet::<Runtime>::new();
+        governance.create_proposal(1, "Text".to_string()).unwrap();
+        let bytes = governance.encode();
+
+        assert_eq!(
+            GovernancePallet::<Runtime>::decode(&bytes[..bytes.len() - 1]).err(),
+            Some("Unexpected end of input")
+        );
+        assert_eq!(GovernancePallet::<Runtime>::decode(&[]).err(), Some("Unexpected end of input"));
+
+        // A proposal stored at u32::MAX leaves no id for the next one
+        let mut last_id = bytes.clone();
+        last_id[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(GovernancePallet::<Runtime>::decode(&last_id).err(), Some("Overflow"));
+    }
+
+    #[test]
+    fn test_deposit_scales_with_proposal_kind() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        staking.set_balance(1, 600).unwrap();
+        let raise = || ProposalKind::SetParameter(StakingParameter::MinStake(50));
+        assert!(Runtime::deposit_for(&raise()) > Runtime::deposit_for(&ProposalKind::Text));
+
+        let text = governance.create_proposal_with_deposit(&mut staking, 1, "Talk".to_string()).unwrap();
+        assert_eq!(governance[text].deposit(), 100);
+        assert_eq!(staking.get_free_balance(1), 500u64);
+
+        // 500 left is not enough for a parameter change
+        assert_eq!(
+            governance.create_kind_with_deposit(&mut staking, 1, "Raise".to_string(), raise()),
+            Err("Insufficient balance")
+        );
+
+        staking.set_balance(1, 1_000).unwrap();
+        let parameter = governance
+            .create_kind_with_deposit(&mut staking, 1, "Raise".to_string(), raise())
+            .unwrap();
+        assert_eq!(governance[parameter].deposit(), 1_000);
+        assert!(matches!(governance[parameter].kind(), ProposalKind::SetParameter(_)));
+        assert_eq!(staking.get_reserved_balance(1), 1_100u64);
+    }
+
+    crate::test_runtime!(YesFloorRuntime {
+        AccountId = u64,
+        Balance = u64,
+        MIN_YES_VOTES: u32 = 10,
+    });
+
+    #[test]
+    fn test_min_yes_votes_floor() {
+        let mut governance = GovernancePallet::<YesFloorRuntime>::new();
+        let short = governance.create_proposal(1, "Five yes".to_string()).unwrap();
+        let enough = governance.create_proposal(1, "Ten yes".to_string()).unwrap();
+        for voter in 1..=10u64 {
+            if voter <= 5 {
+                governance.vote(voter, short, true).unwrap();
+            }
+            governance.vote(voter, enough, true).unwrap();
+        }
+        governance.vote(11, enough, false).unwrap();
+
+        // 5 yes / 0 no clears the ratio but not the floor
+        assert_eq!(governance.finalize_proposal(short), Ok(ProposalStatus::Rejected));
+        assert_eq!(governance.finalize_proposal(enough), Ok(ProposalStatus::Approved));
+    }
+
+    // Two open proposals at most, and richer creators may displace poorer ones
+    crate::test_runtime!(BumpRuntime {
+        AccountId = u64,
+        Balance = u64,
+        PROPOSAL_DEPOSIT: u64 = 10,
+        MAX_ACTIVE_PROPOSALS: u32 = 2,
+        BUMP_BY_STAKE: bool = true,
+    });
+
+    #[test]
+    fn test_higher_stake_displaces_lowest_staked_proposal() {
+        let mut staking = StakingPallet::<BumpRuntime>::new();
+        let mut governance = GovernancePallet::<BumpRuntime>::new();
+        for (who, stake) in [(1u64, 100u64), (2, 300), (3, 500), (4, 50)] {
+            staking.set_balance(who, stake + 10).unwrap();
+            staking.stake(who, stake).unwrap();
+        }
+
+        let low = governance.create_proposal_with_deposit(&mut staking, 1, "Low".to_string()).unwrap();
+        let mid = governance.create_proposal_with_deposit(&mut staking, 2, "Mid".to_string()).unwrap();
+        assert_eq!(governance.create_proposal(3, "Plain".to_string()), Err("Too many active proposals"));
+
+        // Account 4 stakes less than every creator, so nothing is displaced
+        assert_eq!(
+            governance.create_proposal_with_deposit(&mut staking, 4, "Lower".to_string()),
+            Err("Too many active proposals")
+        );
+        assert_eq!(staking.get_reserved_balance(4), 0u64);
+
+        // A proposal that fails its own checks displaces nobody
+        assert_eq!(
+            governance.create_proposal_with_deposit(&mut staking, 3, String::new()),
+            Err("Description too short")
+        );
+        assert!(governance.is_active(low));
+        assert_eq!(staking.get_reserved_balance(3), 0u64);
+
+        let high = governance.create_proposal_with_deposit(&mut staking, 3, "High".to_string()).unwrap();
+        assert_eq!(governance[low].status(), &ProposalStatus::Cancelled);
+        assert!(governance.is_active(mid));
+        assert!(governance.is_active(high));
+
+        // The displaced creator gets its deposit back
+        assert_eq!(governance.process_refunds(0, &mut staking), Ok(1));
+        assert_eq!(staking.get_free_balance(1), 10u64);
+        assert_eq!(staking.get_reserved_balance(1), 0u64);
+    }
+
+    crate::test_runtime!(MinVoterRuntime {
+        AccountId = u64,
+        Balance = u64,
+        MIN_VOTER_STAKE: Option<u64> = Some(100),
+        MAX_VOTE_WEIGHT: Option<u64> = Some(1_000),
+    });
+
+    #[test]
+    fn test_total_voting_power_excludes_small_stakers() {
+        let mut staking = StakingPallet::<MinVoterRuntime>::new();
+        let governance = GovernancePallet::<MinVoterRuntime>::new();
+        for (who, stake) in [(1u64, 5_000u64), (2, 400), (3, 99)] {
+            staking.set_balance(who, stake).unwrap();
+            staking.stake(who, stake).unwrap();
+        }
+
+        assert_eq!(governance.vote_weight(&staking, &3), 0);
+        // The whale is capped at 1_000 and account 3 is below the minimum
+        assert_eq!(governance.total_voting_power(&staking), Some(1_400));
+        assert_eq!(staking.total_staked(), Some(5_499));
     }
 }
@@ -2,23 +2,714 @@ pub use governance::GovernanceConfig;
 pub use staking::StakingConfig;
 pub use system::SystemConfig;
 
+#[cfg(feature = "codec")]
+pub mod codec;
+pub mod events;
 pub mod governance;
+pub mod math;
+pub mod prng;
 pub mod staking;
 pub mod system;
+pub mod testing;
+pub mod treasury;
 
-pub struct Runtime;
+use events::{EventBus, GovernanceEvent, RuntimeEvent, StakingEvent};
+use governance::{GovernancePallet, ProposalKind, ProposalStatus, VoteReceipt};
+use staking::StakingPallet;
+use std::collections::HashMap;
+use system::{ensure_signed, Origin};
+use treasury::TreasuryPallet;
+
+pub struct Runtime {
+    pub staking: StakingPallet<Runtime>,
+    pub governance: GovernancePallet<Runtime>,
+    pub treasury: TreasuryPallet<Runtime>,
+    pub events: EventBus<Runtime>,
+    // Next transaction nonce expected from each account
+    nonces: HashMap<u64, u64>,
+}
+
+// A call a signed transaction can dispatch
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuntimeCall {
+    Stake { amount: u64 },
+    Unstake { amount: u64 },
+    CreateProposal { description: String },
+    Vote { proposal_id: u32, choice: bool },
+}
+
+// A full copy of every pallet's state, used to roll back speculative execution
+#[derive(Clone)]
+pub struct RuntimeSnapshot {
+    staking: StakingPallet<Runtime>,
+    governance: GovernancePallet<Runtime>,
+    treasury: TreasuryPallet<Runtime>,
+    events: EventBus<Runtime>,
+    nonces: HashMap<u64, u64>,
+}
+
+// One account's state across the pallets, for dashboards
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountOverview {
+    pub free: u64,
+    pub staked: u64,
+    pub pending_rewards: u64,
+    // Proposals created by the account that are open for votes
+    pub active_proposals: u32,
+    // Yes/no votes cast on stored proposals; abstentions are not counted
+    pub votes_cast: usize,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Runtime {
+    // Fee charged from the sender's free balance for every applied transaction
+    pub const TRANSACTION_FEE: u64 = 1;
+
+    pub fn new() -> Self {
+        Self {
+            staking: StakingPallet::new(),
+            governance: GovernancePallet::new(),
+            treasury: TreasuryPallet::new(),
+            events: EventBus::new(),
+            nonces: HashMap::new(),
+        }
+    }
+
+    // Get the nonce the next transaction from `who` must carry
+    pub fn nonce(&self, who: u64) -> u64 {
+        self.nonces.get(&who).copied().unwrap_or(0)
+    }
+
+    // Check a transaction before it enters the pool, without changing any state: signed
+    // origin, the sender's next nonce, free balance for the fee, and a well-formed call.
+    // Passing does not guarantee the call succeeds once dispatched.
+    pub fn validate_transaction(
+        &self,
+        origin: &Origin<u64>,
+        call: &RuntimeCall,
+        nonce: u64,
+    ) -> Result<(), &'static str> {
+        let who = *ensure_signed(origin)?;
+
+        let expected = self.nonce(who);
+        if nonce < expected {
+            return Err("Stale nonce");
+        }
+        if nonce > expected {
+            return Err("Future nonce");
+        }
+
+        if self.staking.get_free_balance(who) < Self::TRANSACTION_FEE {
+            return Err("Insufficient balance for fee");
+        }
+
+        match call {
+            RuntimeCall::Stake { amount } | RuntimeCall::Unstake { amount } if *amount == 0 => {
+                Err("Amount is zero")
+            }
+            RuntimeCall::CreateProposal { description }
+                if description.len() < <Self as GovernanceConfig>::MIN_DESCRIPTION_LEN
+                    || description.len() > <Self as GovernanceConfig>::MAX_DESCRIPTION_LEN =>
+            {
+                Err("Invalid description length")
+            }
+            RuntimeCall::Vote { proposal_id, .. } if !self.governance.is_active(*proposal_id) => {
+                Err("Proposal is not active")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Validate a transaction, charge its fee into the treasury, consume its nonce and
+    // dispatch the call. The fee and nonce stay consumed even if the call itself fails.
+    #[must_use = "the dispatch error must be handled"]
+    pub fn apply_transaction(
+        &mut self,
+        origin: Origin<u64>,
+        call: RuntimeCall,
+        nonce: u64,
+    ) -> Result<(), &'static str> {
+        self.validate_transaction(&origin, &call, nonce)?;
+        let who = *ensure_signed(&origin)?;
+
+        let free = self.staking.get_free_balance(who);
+        self.treasury.deposit(Self::TRANSACTION_FEE)?;
+        self.staking.set_balance(who, free - Self::TRANSACTION_FEE)?;
+        self.nonces.insert(who, nonce + 1);
+
+        match call {
+            RuntimeCall::Stake { amount } => self.stake(who, amount).map(|_| ()),
+            RuntimeCall::Unstake { amount } => self.unstake(who, amount).map(|_| ()),
+            RuntimeCall::CreateProposal { description } => {
+                self.create_proposal(who, description).map(|_| ())
+            }
+            RuntimeCall::Vote { proposal_id, choice } => {
+                self.vote(who, proposal_id, choice).map(|_| ())
+            }
+        }
+    }
+
+    // Capture the current state of all pallets
+    pub fn snapshot(&self) -> RuntimeSnapshot {
+        RuntimeSnapshot {
+            staking: self.staking.clone(),
+            governance: self.governance.clone(),
+            treasury: self.treasury.clone(),
+            events: self.events.clone(),
+            nonces: self.nonces.clone(),
+        }
+    }
+
+    // Stake through the runtime, recording a Staked event
+    #[must_use = "the new free balance or the staking error must be handled"]
+    pub fn stake(&mut self, who: u64, amount: u64) -> Result<u64, &'static str> {
+        let free = self.staking.stake(who, amount)?;
+        self.events.deposit(RuntimeEvent::Staking(StakingEvent::Staked { who, amount }));
+
+        Ok(free)
+    }
+
+    // Unstake through the runtime, recording an Unstaked event
+    #[must_use = "the new free balance or the unstaking error must be handled"]
+    pub fn unstake(&mut self, who: u64, amount: u64) -> Result<u64, &'static str> {
+        let free = self.staking.unstake(who, amount)?;
+        self.events.deposit(RuntimeEvent::Staking(StakingEvent::Unstaked { who, amount }));
+
+        Ok(free)
+    }
+
+    // Nominate a validator through the runtime, recording a NominationAdded event
+    #[must_use = "a failed nomination leaves the previous nomination in place"]
+    pub fn nominate(&mut self, nominator: u64, validator: u64) -> Result<(), &'static str> {
+        self.staking.nominate(nominator, validator)?;
+        // Backing is bounded by total issuance, which fits in a balance
+        let new_total_backing = self.staking.total_backing(&validator).unwrap_or(u64::MAX);
+        self.events.deposit(RuntimeEvent::Staking(StakingEvent::NominationAdded {
+            nominator,
+            validator,
+            new_total_backing,
+        }));
+
+        Ok(())
+    }
+
+    // Create a proposal through the runtime, recording a Proposed event
+    #[must_use = "the new proposal id or the creation error must be handled"]
+    pub fn create_proposal(&mut self, creator: u64, description: String) -> Result<u32, &'static str> {
+        let proposal_id = self.governance.create_proposal(creator, description)?;
+        self.events.deposit(RuntimeEvent::Governance(GovernanceEvent::Proposed { proposal_id, creator }));
+
+        Ok(proposal_id)
+    }
+
+    // Vote through the runtime, recording a Voted event
+    #[must_use = "the vote receipt or the voting error must be handled"]
+    pub fn vote(&mut self, voter: u64, proposal_id: u32, choice: bool) -> Result<VoteReceipt<u64>, &'static str> {
+        let receipt = self.governance.vote(voter, proposal_id, choice)?;
+        self.events.deposit(RuntimeEvent::Governance(GovernanceEvent::Voted { proposal_id, voter, choice }));
+
+        Ok(receipt)
+    }
+
+    // Gather an account's balances and governance activity in one call
+    pub fn account_overview(&self, who: u64) -> AccountOverview {
+        let active_proposals = (&self.governance)
+            .into_iter()
+            .filter(|(_, proposal)| {
+                *proposal.creator() == who && matches!(proposal.status(), ProposalStatus::Active)
+            })
+            .count() as u32;
+
+        AccountOverview {
+            free: self.staking.get_free_balance(who),
+            staked: self.staking.get_staked_balance(who),
+            pending_rewards: self.staking.get_pending_rewards(who),
+            active_proposals,
+            votes_cast: self.governance.votes.keys().filter(|(voter, _)| *voter == who).count(),
+        }
+    }
+
+    // Take every event emitted since the last drain, in dispatch order
+    pub fn drain_events(&mut self) -> Vec<RuntimeEvent<Runtime>> {
+        self.events.drain()
+    }
+
+    // Take up to `max` of the oldest events, so indexers can process them in bounded chunks
+    pub fn drain_events_paged(&mut self, max: usize) -> Vec<RuntimeEvent<Runtime>> {
+        self.events.drain_paged(max)
+    }
+
+    // Finalize a proposal by its stake-weighted tally and, if approved, enact what it proposes.
+    // Queued deposit forfeitures are moved to the treasury afterwards; ones that fail stay
+    // queued for the next finalization and are reported as a ForfeituresFailed event, never
+    // as an error of this proposal.
+    #[must_use = "the resulting status or finalization error must be handled"]
+    pub fn finalize_proposal(&mut self, proposal_id: u32) -> Result<ProposalStatus, &'static str> {
+        let status = self.governance.finalize_proposal_weighted(&self.staking, proposal_id)?;
+        self.events.deposit(RuntimeEvent::Governance(GovernanceEvent::Finalized {
+            proposal_id,
+            status: status.clone(),
+        }));
+
+        // The proposal stays finalized even if its action fails
+        let enacted = self.enact(proposal_id, &status);
+
+        if let Err(error) = self.governance.process_forfeitures(&mut self.staking, &mut self.treasury) {
+            self.events.deposit(RuntimeEvent::Governance(GovernanceEvent::ForfeituresFailed { error }));
+        }
+
+        enacted.map(|_| status)
+    }
+
+    // Run what a just-finalized proposal does for its outcome
+    fn enact(&mut self, proposal_id: u32, status: &ProposalStatus) -> Result<(), &'static str> {
+        match status {
+            ProposalStatus::Approved => match self.governance[proposal_id].kind().clone() {
+                ProposalKind::SetParameter(parameter) => self.staking.set_parameter(parameter),
+                ProposalKind::Action(action) => action.execute(self)?,
+                ProposalKind::Spend { .. } => self.treasury.spend(
+                    &Origin::Root,
+                    &self.governance,
+                    proposal_id,
+                    &mut self.staking,
+                )?,
+                ProposalKind::Text | ProposalKind::ChooseParameter(_) => {}
+            },
+            ProposalStatus::Rejected => {
+                if let Some(action) = self.governance[proposal_id].on_reject().cloned() {
+                    action.execute(self)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    // Replace the state of all pallets with a previously captured snapshot
+    pub fn restore(&mut self, snapshot: RuntimeSnapshot) {
+        self.staking = snapshot.staking;
+        self.governance = snapshot.governance;
+        self.treasury = snapshot.treasury;
+        self.events = snapshot.events;
+        self.nonces = snapshot.nonces;
+    }
+}
 
 // Implement specific System configuration for the runtime
 impl SystemConfig for Runtime {
     type AccountId = u64;
+    type BlockNumber = u64;
+    const DECIMALS: u32 = 2;
 }
 
 // Implement specific Staking configuration for the runtime
 impl StakingConfig for Runtime {
     type Balance = u64;
+    type Hooks = ();
+    const MAX_SUPPLY: u64 = 1_000_000;
+    const INFLATION_RATE_PPM: u32 = 10_000;
+    const MIN_PAYOUT: u64 = 10;
+    const UNBONDING_PERIOD: u64 = 10;
+    const RESTAKE_COOLDOWN: u64 = 5;
+    const REFERRAL_BONUS_PERCENT: u32 = 5;
+    const MATURITY_BLOCKS_PER_PERCENT: u32 = 1;
 }
 
 // Implement specific Governance configuration for the runtime
 impl GovernanceConfig for Runtime {
-    // No additional types needed at this level
+    const PROPOSAL_DEPOSIT: u64 = 100;
+    const REFUND_DELAY: u64 = 10;
+    const FORFEIT_REJECTED_DEPOSITS: bool = true;
+    const VOTING_PERIOD: u64 = 20;
+    const MIN_VOTING_BLOCKS: u64 = 0;
+    const PROPOSAL_COOLDOWN_BLOCKS: u64 = 0;
+    const MAX_TOTAL_DESC_BYTES: usize = 1024;
+
+    fn deposit_for(kind: &ProposalKind<Self>) -> u64 {
+        match kind {
+            ProposalKind::SetParameter(_) => 1_000,
+            _ => Self::PROPOSAL_DEPOSIT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use governance::ProposalAction;
+    use staking::StakingParameter;
+
+    // Mints `amount` into `to`'s free balance
+    struct MintAction {
+        to: u64,
+        amount: u64,
+    }
+
+    impl ProposalAction<Runtime> for MintAction {
+        fn execute(&self, runtime: &mut Runtime) -> Result<(), &'static str> {
+            let free = runtime.staking.get_free_balance(self.to);
+            runtime.staking.set_balance(self.to, free + self.amount)
+        }
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut runtime = Runtime::new();
+
+        runtime.staking.set_balance(alice, 1000).unwrap();
+        runtime.staking.stake(alice, 400).unwrap();
+        let proposal_id = runtime
+            .governance
+            .create_proposal(alice, "Keep me".to_string())
+            .unwrap();
+
+        let snapshot = runtime.snapshot();
+
+        // Mutate state after the snapshot
+        runtime.staking.set_balance(bob, 50).unwrap();
+        runtime.staking.unstake(alice, 400).unwrap();
+        runtime.governance.vote(bob, proposal_id, true).unwrap();
+        runtime.governance.create_proposal(bob, "Drop me".to_string()).unwrap();
+
+        runtime.restore(snapshot);
+
+        assert_eq!(runtime.staking.get_free_balance(alice), 600u64);
+        assert_eq!(runtime.staking.get_staked_balance(alice), 400u64);
+        assert_eq!(runtime.staking.get_free_balance(bob), 0u64);
+        assert_eq!(runtime.governance.get_proposal(proposal_id).unwrap().yes_votes(), 0);
+        assert!(!runtime.governance.proposal_exists(proposal_id + 1));
+    }
+
+    #[test]
+    fn test_governance_lowers_min_stake() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut runtime = Runtime::new();
+        runtime.staking.set_parameter(StakingParameter::MinStake(100));
+        runtime.staking.set_balance(alice, 1000).unwrap();
+        runtime.staking.set_balance(bob, 1000).unwrap();
+        runtime.staking.stake(alice, 100).unwrap();
+
+        assert_eq!(runtime.staking.stake(bob, 20), Err("Stake below minimum"));
+
+        let proposal_id = runtime
+            .governance
+            .create_parameter_proposal(
+                alice,
+                "Lower minimum stake".to_string(),
+                StakingParameter::MinStake(10),
+            )
+            .unwrap();
+        runtime.governance.vote(alice, proposal_id, true).unwrap();
+        runtime.governance.vote(bob, proposal_id, true).unwrap();
+
+        let status = runtime.finalize_proposal(proposal_id).unwrap();
+        assert!(matches!(status, ProposalStatus::Approved));
+        assert_eq!(runtime.staking.parameters().min_stake, 10u64);
+
+        assert!(runtime.staking.stake(bob, 20).is_ok());
+    }
+
+    #[test]
+    fn test_events_in_dispatch_order() {
+        let alice = 1u64;
+        let mut runtime = Runtime::new();
+        runtime.staking.set_balance(alice, 1000).unwrap();
+
+        runtime.stake(alice, 400).unwrap();
+        let proposal_id = runtime.create_proposal(alice, "Ordered".to_string()).unwrap();
+        runtime.vote(alice, proposal_id, true).unwrap();
+        // A failed dispatch emits nothing
+        assert!(runtime.vote(alice, proposal_id, false).is_err());
+
+        let events = runtime.drain_events();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], RuntimeEvent::Staking(StakingEvent::Staked { who: 1, amount: 400 })));
+        assert!(matches!(
+            events[1],
+            RuntimeEvent::Governance(GovernanceEvent::Proposed { proposal_id: 0, creator: 1 })
+        ));
+        assert!(matches!(
+            events[2],
+            RuntimeEvent::Governance(GovernanceEvent::Voted { proposal_id: 0, voter: 1, choice: true })
+        ));
+        assert!(runtime.drain_events().is_empty());
+    }
+
+    #[test]
+    fn test_approved_action_executes() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.staking.set_balance(alice, 100).unwrap();
+        runtime.staking.stake(alice, 100).unwrap();
+        runtime.staking.set_balance(bob, 50).unwrap();
+
+        let proposal_id = runtime
+            .governance
+            .create_action_proposal(alice, "Mint to bob".to_string(), MintAction { to: bob, amount: 250 })
+            .unwrap();
+        let rejected_id = runtime
+            .governance
+            .create_action_proposal(alice, "Mint again".to_string(), MintAction { to: bob, amount: 1_000 })
+            .unwrap();
+        runtime.vote(alice, proposal_id, true).unwrap();
+        runtime.vote(alice, rejected_id, false).unwrap();
+
+        assert_eq!(runtime.finalize_proposal(proposal_id), Ok(ProposalStatus::Approved));
+        assert_eq!(runtime.finalize_proposal(rejected_id), Ok(ProposalStatus::Rejected));
+        assert_eq!(runtime.staking.get_free_balance(bob), 300u64);
+    }
+
+    #[test]
+    fn test_approved_spend_pays_out_on_finalization() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.staking.set_balance(alice, 100).unwrap();
+        runtime.staking.stake(alice, 100).unwrap();
+        runtime.treasury.deposit(500).unwrap();
+
+        let proposal_id = runtime
+            .governance
+            .create_spend_proposal(alice, "Grant bob".to_string(), bob, 200)
+            .unwrap();
+        runtime.vote(alice, proposal_id, true).unwrap();
+
+        assert_eq!(runtime.finalize_proposal(proposal_id), Ok(ProposalStatus::Approved));
+        assert_eq!(runtime.staking.get_free_balance(bob), 200u64);
+        assert_eq!(runtime.treasury.balance(), 300u64);
+    }
+
+    #[test]
+    fn test_on_reject_runs_only_on_rejection() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.staking.set_balance(alice, 100).unwrap();
+        runtime.staking.stake(alice, 100).unwrap();
+
+        let rejected_id = runtime.create_proposal(alice, "Doomed".to_string()).unwrap();
+        let approved_id = runtime.create_proposal(alice, "Popular".to_string()).unwrap();
+        for proposal_id in [rejected_id, approved_id] {
+            runtime
+                .governance
+                .set_on_reject(alice, proposal_id, MintAction { to: bob, amount: 40 })
+                .unwrap();
+        }
+        assert_eq!(
+            runtime.governance.set_on_reject(bob, approved_id, MintAction { to: bob, amount: 1 }),
+            Err("Only the creator can set the reject action")
+        );
+
+        runtime.vote(alice, rejected_id, false).unwrap();
+        runtime.vote(alice, approved_id, true).unwrap();
+
+        assert_eq!(runtime.finalize_proposal(approved_id), Ok(ProposalStatus::Approved));
+        assert_eq!(runtime.staking.get_free_balance(bob), 0u64);
+
+        assert_eq!(runtime.finalize_proposal(rejected_id), Ok(ProposalStatus::Rejected));
+        assert_eq!(runtime.staking.get_free_balance(bob), 40u64);
+    }
+
+    #[test]
+    fn test_account_overview() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.staking.set_balance(alice, 1_000).unwrap();
+        runtime.staking.set_balance(bob, 1_000).unwrap();
+        runtime.stake(alice, 400).unwrap();
+        runtime.stake(bob, 400).unwrap();
+        runtime.staking.mint_block_reward(1);
+        runtime.staking.accrue_rewards(100).unwrap();
+
+        let first = runtime.create_proposal(alice, "Mine".to_string()).unwrap();
+        let second = runtime.create_proposal(alice, "Also mine".to_string()).unwrap();
+        let theirs = runtime.create_proposal(bob, "Theirs".to_string()).unwrap();
+        runtime.vote(alice, theirs, true).unwrap();
+        runtime.vote(alice, first, false).unwrap();
+        runtime.finalize_proposal(second).unwrap();
+
+        assert_eq!(
+            runtime.account_overview(alice),
+            AccountOverview {
+                free: 600,
+                staked: 400,
+                pending_rewards: 50,
+                active_proposals: 1,
+                votes_cast: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_drain_events_in_pages() {
+        let mut runtime = Runtime::new();
+        runtime.staking.set_balance(1, 1_000).unwrap();
+        for _ in 0..10 {
+            runtime.stake(1, 10).unwrap();
+        }
+
+        let mut pages = Vec::new();
+        loop {
+            let page = runtime.drain_events_paged(3);
+            if page.is_empty() {
+                break;
+            }
+            pages.push(page.len());
+        }
+        assert_eq!(pages, vec![3, 3, 3, 1]);
+
+        // Pages continue where the last one stopped, oldest first
+        for amount in 1..=5u64 {
+            runtime.stake(1, amount).unwrap();
+        }
+        let first = runtime.drain_events_paged(3);
+        let rest = runtime.drain_events_paged(3);
+        let amounts: Vec<u64> = first.iter().chain(rest.iter())
+            .map(|event| match event {
+                RuntimeEvent::Staking(StakingEvent::Staked { amount, .. }) => *amount,
+                _ => 0,
+            })
+            .collect();
+        assert_eq!(amounts, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_validate_transaction() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.staking.set_balance(alice, 1_000).unwrap();
+        let stake = RuntimeCall::Stake { amount: 400 };
+
+        assert_eq!(runtime.validate_transaction(&Origin::Signed(alice), &stake, 0), Ok(()));
+        assert_eq!(
+            runtime.validate_transaction(&Origin::Signed(alice), &stake, 1),
+            Err("Future nonce")
+        );
+        assert_eq!(
+            runtime.validate_transaction(&Origin::Signed(bob), &stake, 0),
+            Err("Insufficient balance for fee")
+        );
+        let vote = RuntimeCall::Vote { proposal_id: 0, choice: true };
+        assert_eq!(
+            runtime.validate_transaction(&Origin::Signed(alice), &vote, 0),
+            Err("Proposal is not active")
+        );
+        assert_eq!(
+            runtime.validate_transaction(&Origin::Root, &stake, 0),
+            Err("Signed origin required")
+        );
+        // Validation alone changes nothing
+        assert_eq!(runtime.nonce(alice), 0);
+
+        runtime.apply_transaction(Origin::Signed(alice), stake.clone(), 0).unwrap();
+        assert_eq!(runtime.nonce(alice), 1);
+        assert_eq!(runtime.staking.get_free_balance(alice), 599);
+        assert_eq!(runtime.treasury.balance(), Runtime::TRANSACTION_FEE);
+
+        // Replaying the same transaction is rejected
+        assert_eq!(
+            runtime.validate_transaction(&Origin::Signed(alice), &stake, 0),
+            Err("Stale nonce")
+        );
+        assert_eq!(runtime.apply_transaction(Origin::Signed(alice), stake, 0), Err("Stale nonce"));
+        assert_eq!(runtime.staking.get_staked_balance(alice), 400);
+    }
+
+    #[test]
+    fn test_nomination_event_carries_total_backing() {
+        let (validator, bob, carol) = (1u64, 2u64, 3u64);
+        let mut runtime = Runtime::new();
+        for (who, stake) in [(validator, 500), (bob, 300), (carol, 200)] {
+            runtime.staking.set_balance(who, 1_000).unwrap();
+            runtime.stake(who, stake).unwrap();
+        }
+        runtime.staking.register_validator(validator).unwrap();
+        runtime.drain_events();
+
+        runtime.nominate(bob, validator).unwrap();
+        runtime.nominate(carol, validator).unwrap();
+        // A failed nomination emits nothing
+        assert!(runtime.nominate(carol, bob).is_err());
+
+        let events = runtime.drain_events();
+        assert_eq!(events.len(), 2);
+        let backing = runtime.staking.total_backing(&validator);
+        assert_eq!(backing, Some(1_000));
+        assert!(matches!(
+            events[1],
+            RuntimeEvent::Staking(StakingEvent::NominationAdded { nominator: 3, validator: 1, new_total_backing })
+                if Some(new_total_backing) == backing
+        ));
+    }
+
+    #[test]
+    fn test_rejected_deposit_forfeited_to_treasury() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.staking.set_balance(alice, 500).unwrap();
+        runtime.staking.set_balance(bob, 100).unwrap();
+        runtime.staking.stake(bob, 100).unwrap();
+        let rejected = runtime.governance
+            .create_proposal_with_deposit(&mut runtime.staking, alice, "Lose".to_string())
+            .unwrap();
+        let approved = runtime.governance
+            .create_proposal_with_deposit(&mut runtime.staking, alice, "Keep".to_string())
+            .unwrap();
+        runtime.vote(bob, rejected, false).unwrap();
+        runtime.vote(bob, approved, true).unwrap();
+
+        assert_eq!(runtime.finalize_proposal(rejected), Ok(ProposalStatus::Rejected));
+        assert_eq!(runtime.treasury.balance(), 100);
+        assert_eq!(runtime.staking.get_reserved_balance(alice), 100);
+        assert!(runtime.governance.forfeit_queue.is_empty());
+
+        // An approved deposit is still refunded after REFUND_DELAY
+        assert_eq!(runtime.finalize_proposal(approved), Ok(ProposalStatus::Approved));
+        assert_eq!(runtime.governance.refund_queue.len(), 1);
+        assert_eq!(runtime.treasury.balance(), 100);
+    }
+
+    #[test]
+    fn test_failed_forfeiture_does_not_block_enactment() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut runtime = Runtime::new();
+        runtime.staking.set_balance(alice, 500).unwrap();
+        runtime.staking.set_balance(bob, 100).unwrap();
+        runtime.staking.stake(bob, 100).unwrap();
+        let rejected = runtime.governance
+            .create_proposal_with_deposit(&mut runtime.staking, alice, "Lose".to_string())
+            .unwrap();
+        let parameter = runtime.governance
+            .create_parameter_proposal(bob, "Lower".to_string(), StakingParameter::MinStake(5))
+            .unwrap();
+        runtime.vote(bob, rejected, false).unwrap();
+        runtime.vote(bob, parameter, true).unwrap();
+
+        // The deposit is no longer reserved, so forfeiting it fails
+        runtime.staking.unreserve(alice, 100).unwrap();
+        assert_eq!(runtime.finalize_proposal(rejected), Ok(ProposalStatus::Rejected));
+        assert_eq!(runtime.governance.forfeit_queue.len(), 1);
+
+        // Later proposals are still finalized and enacted while the forfeiture stays queued
+        runtime.drain_events();
+        assert_eq!(runtime.finalize_proposal(parameter), Ok(ProposalStatus::Approved));
+        assert_eq!(runtime.staking.parameters().min_stake, 5u64);
+        let events = runtime.drain_events();
+        assert!(matches!(
+            events[0],
+            RuntimeEvent::Governance(GovernanceEvent::Finalized { status: ProposalStatus::Approved, .. })
+        ));
+        assert!(matches!(
+            events[1],
+            RuntimeEvent::Governance(GovernanceEvent::ForfeituresFailed { error: "Insufficient reserved balance" })
+        ));
+        assert_eq!(runtime.governance.forfeit_queue.len(), 1);
+    }
 }
@@ -0,0 +1,198 @@
+use crate::staking::StakingConfig;
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Zero};
+use std::fmt::Display;
+
+// Sum balances with checked addition, returning None on overflow instead of wrapping
+pub fn checked_sum<'a, B, I>(iter: I) -> Option<B>
+where
+    B: Zero + CheckedAdd + Copy + 'a,
+    I: IntoIterator<Item = &'a B>,
+{
+    iter.into_iter()
+        .try_fold(B::zero(), |total, amount| total.checked_add(amount))
+}
+
+// Integer square root (rounded down) by Newton's method, for any balance type
+pub fn isqrt<B>(n: B) -> B
+where
+    B: Zero + CheckedAdd + CheckedDiv + From<u32> + Ord + Copy,
+{
+    let two = B::from(2u32);
+    if n < two {
+        return n;
+    }
+
+    // Start at n / 2, which is at least sqrt(n) for n >= 4; n = 2 and 3 land on 1 directly
+    let step = |x: B| n.checked_div(&x)
+        .and_then(|quotient| x.checked_add(&quotient))
+        .and_then(|sum| sum.checked_div(&two));
+
+    let mut x = n.checked_div(&two).unwrap_or(n);
+    while let Some(next) = step(x) {
+        if next >= x {
+            break;
+        }
+        x = next;
+    }
+
+    x
+}
+
+// Compute floor(a * b / c) without overflowing on the intermediate product, returning None
+// if `c` is zero or the result itself does not fit. `a` is split into a multiple of `c`
+// and a remainder below `c`; the remainder's share is built up bit by bit from `b`, keeping
+// every partial value below `c`.
+pub fn mul_div<B>(a: B, b: B, c: B) -> Option<B>
+where
+    B: Zero + CheckedAdd + CheckedSub + CheckedMul + CheckedDiv + From<u32> + Ord + Copy,
+{
+    let one = B::from(1u32);
+    let two = B::from(2u32);
+    let whole = a.checked_div(&c)?;
+    let rest = a.checked_sub(&whole.checked_mul(&c)?)?;
+
+    let mut bits = Vec::new();
+    let mut n = b;
+    while !n.is_zero() {
+        let half = n.checked_div(&two)?;
+        bits.push(!n.checked_sub(&half.checked_mul(&two)?)?.is_zero());
+        n = half;
+    }
+
+    // Invariant: rest * (bits of `b` seen so far) == share * c + carry, with carry < c
+    let (mut share, mut carry) = (B::zero(), B::zero());
+    for bit in bits.into_iter().rev() {
+        share = share.checked_mul(&two)?;
+        let gap = c.checked_sub(&carry)?;
+        if carry >= gap {
+            share = share.checked_add(&one)?;
+            carry = carry.checked_sub(&gap)?;
+        } else {
+            carry = carry.checked_add(&carry)?;
+        }
+
+        if bit {
+            let gap = c.checked_sub(&rest)?;
+            if carry >= gap {
+                share = share.checked_add(&one)?;
+                carry = carry.checked_sub(&gap)?;
+            } else {
+                carry = carry.checked_add(&rest)?;
+            }
+        }
+    }
+
+    whole.checked_mul(&b)?.checked_add(&share)
+}
+
+// Convert a balance to u128 for display or logging, returning None if it does not fit
+pub fn balance_as_u128<B: TryInto<u128>>(balance: B) -> Option<u128> {
+    balance.try_into().ok()
+}
+
+// Render a raw balance in whole units for display, with DECIMALS digits after the point,
+// e.g. 1000 with 2 decimals is "10.00" and 5 is "0.05"
+pub fn format_balance<T: StakingConfig>(balance: T::Balance) -> String
+where
+    T::Balance: Display,
+{
+    let digits = balance.to_string();
+    let decimals = T::DECIMALS as usize;
+    if decimals == 0 {
+        return digits;
+    }
+
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    format!("{}.{}", whole, fraction)
+}
+
+// Advance a SplitMix64 generator and return its next output. Deterministic and fast, for
+// seeded selection; not suitable where unpredictability matters.
+pub fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_sum() {
+        assert_eq!(checked_sum(&[1u64, 2, 3]), Some(6u64));
+        assert_eq!(checked_sum::<u64, _>(&[]), Some(0u64));
+    }
+
+    #[test]
+    fn test_checked_sum_overflow() {
+        assert_eq!(checked_sum(&[u64::MAX, 1]), None);
+        assert_eq!(checked_sum(&[200u8, 50, 10]), None);
+    }
+
+    #[test]
+    fn test_isqrt() {
+        let cases = [
+            (0u64, 0u64), (1, 1), (2, 1), (3, 1), (4, 2), (8, 2), (9, 3),
+            (999_999, 999), (1_000_000, 1_000),
+        ];
+        for (n, root) in cases {
+            assert_eq!(isqrt(n), root, "isqrt({})", n);
+        }
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn test_mul_div() {
+        let cases = [
+            (7u64, 3u64, 2u64), (0, 5, 3), (100, 250, 1_000), (999, 1_000, 1_000), (5, 0, 7),
+        ];
+        for (a, b, c) in cases {
+            assert_eq!(mul_div(a, b, c), Some(a * b / c), "mul_div({}, {}, {})", a, b, c);
+        }
+        assert_eq!(mul_div(u64::MAX, u64::MAX - 1, u64::MAX), Some(u64::MAX - 1));
+        let (a, b, c) = (u64::MAX - 12, u64::MAX / 3, u64::MAX - 5);
+        assert_eq!(mul_div(a, b, c), Some((a as u128 * b as u128 / c as u128) as u64));
+        assert_eq!(mul_div(u64::MAX, 2, 1), None);
+        assert_eq!(mul_div(1u64, 1, 0), None);
+    }
+
+    #[test]
+    fn test_balance_as_u128() {
+        assert_eq!(balance_as_u128(1234u64), Some(1234u128));
+        assert_eq!(balance_as_u128(u128::MAX), Some(u128::MAX));
+    }
+
+    #[test]
+    fn test_balance_as_u128_overflow() {
+        // A balance type wider than u128
+        let wide = num::BigUint::from(u128::MAX) + 1u32;
+        assert_eq!(balance_as_u128(wide), None);
+    }
+
+    #[test]
+    fn test_splitmix64_reference_outputs() {
+        let mut state = 0u64;
+        assert_eq!(splitmix64(&mut state), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(splitmix64(&mut state), 0x6E78_9E6A_A1B9_65F4);
+    }
+
+    crate::test_runtime!(WholeUnitRuntime {
+        AccountId = u64,
+        Balance = u64,
+    });
+
+    #[test]
+    fn test_format_balance() {
+        assert_eq!(format_balance::<crate::Runtime>(1_000), "10.00");
+        assert_eq!(format_balance::<crate::Runtime>(1_234_567), "12345.67");
+        assert_eq!(format_balance::<crate::Runtime>(5), "0.05");
+        assert_eq!(format_balance::<crate::Runtime>(50), "0.50");
+        assert_eq!(format_balance::<crate::Runtime>(0), "0.00");
+        assert_eq!(format_balance::<WholeUnitRuntime>(1_000), "1000");
+    }
+}
@@ -0,0 +1,65 @@
+// Seedable pseudo-random generator for selections that every node must reproduce, such as
+// sampling proposals or picking validators. Built on SplitMix64: the same seed always gives
+// the same sequence. Outputs are predictable from the seed, so it must not guard secrets.
+
+use crate::math::splitmix64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prng {
+    state: u64,
+}
+
+impl Prng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // Get the next output, advancing the generator
+    pub fn next_u64(&mut self) -> u64 {
+        splitmix64(&mut self.state)
+    }
+
+    // Get an output uniformly distributed in `low..high`, None if the range is empty.
+    // Outputs in the short tail that would bias the modulo are redrawn.
+    pub fn next_in_range(&mut self, low: u64, high: u64) -> Option<u64> {
+        let span = high.checked_sub(low).filter(|span| *span > 0)?;
+        // 2^64 mod span: drawing below it would favour the smallest values
+        let threshold = span.wrapping_neg() % span;
+
+        loop {
+            let value = self.next_u64();
+            if value >= threshold {
+                return Some(low + value % span);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let (mut a, mut b) = (Prng::new(42), Prng::new(42));
+        let first: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        assert_eq!(first, second);
+
+        let mut other = Prng::new(43);
+        assert_ne!(other.next_u64(), first[0]);
+    }
+
+    #[test]
+    fn test_next_in_range_respects_bounds() {
+        let mut prng = Prng::new(7);
+        for _ in 0..1_000 {
+            let value = prng.next_in_range(10, 13).unwrap();
+            assert!((10..13).contains(&value));
+        }
+        assert_eq!(prng.next_in_range(5, 6), Some(5));
+        assert_eq!(prng.next_in_range(5, 5), None);
+        assert_eq!(prng.next_in_range(6, 5), None);
+        assert!(prng.next_in_range(0, u64::MAX).is_some());
+    }
+}
@@ -1,34 +1,480 @@
-use crate::system::SystemConfig;
-use num::traits::{CheckedAdd, CheckedSub, Zero};
-use std::collections::HashMap;
+#[cfg(feature = "codec")]
+use crate::codec::{decode_map, encode_map, Decode, Encode};
+use crate::math::{balance_as_u128, checked_sum, mul_div};
+use crate::prng::Prng;
+use crate::system::{blocks_until, has_reached, StorageVersion, SystemConfig};
+use crate::treasury::TreasuryPallet;
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Zero};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, DerefMut};
 
 pub trait StakingConfig: SystemConfig {
     // Define the Balance type with ability to perform checked arithmetic operations
-    type Balance: Zero + CheckedSub + CheckedAdd + Copy;
+    type Balance: Zero
+        + CheckedSub
+        + CheckedAdd
+        + CheckedMul
+        + CheckedDiv
+        + From<u32>
+        + Ord
+        + Copy;
+    // Callbacks fired after successful stake changes; use `()` for none
+    type Hooks: StakingHooks<Self>;
+    // Hard cap on total issuance; block rewards stop once it is reached
+    const MAX_SUPPLY: Self::Balance;
+    // Share of the remaining mintable supply (MAX_SUPPLY - total issuance) minted each block,
+    // in parts per million. Rewards therefore shrink as issuance approaches the cap.
+    const INFLATION_RATE_PPM: u32 = 0;
+    // Smallest reward paid out to a single account; smaller rewards carry over
+    const MIN_PAYOUT: Self::Balance;
+    // Blocks an unbonded amount waits before it can be withdrawn to free balance
+    const UNBONDING_PERIOD: Self::BlockNumber;
+    // Blocks after a full unstake before the account may stake again
+    const RESTAKE_COOLDOWN: Self::BlockNumber;
+    // Percentage of a referred stake minted to the referrer
+    const REFERRAL_BONUS_PERCENT: u32 = 0;
+    // Reward weighting by stake maturity: every MATURITY_BLOCKS_PER_PERCENT blocks staked
+    // add 1% to an account's reward weight, up to MAX_MATURITY_BONUS_PERCENT. 0 disables it.
+    const MATURITY_BLOCKS_PER_PERCENT: u32 = 0;
+    const MAX_MATURITY_BONUS_PERCENT: u32 = 100;
+    // Nominators a single validator may have, bounding per-validator payout loops
+    const MAX_NOMINATORS_PER_VALIDATOR: u32 = 64;
 }
 
+// Reactions to stake changes for downstream pallets. Every hook defaults to a no-op.
+pub trait StakingHooks<T: StakingConfig + ?Sized> {
+    fn on_stake(_who: &T::AccountId, _amount: T::Balance) {}
+    fn on_unstake(_who: &T::AccountId, _amount: T::Balance) {}
+}
+
+impl<T: StakingConfig + ?Sized> StakingHooks<T> for () {}
+
+// Read-only view of account balances, so other pallets can weigh by stake without depending
+// on StakingPallet itself
+pub trait BalanceProvider<T: StakingConfig + ?Sized> {
+    fn staked_balance(&self, who: &T::AccountId) -> T::Balance;
+    fn free_balance(&self, who: &T::AccountId) -> T::Balance;
+}
+
+// Staking parameters that can be changed at runtime, e.g. through governance
+pub struct StakingParameters<T: StakingConfig> {
+    // Smallest total stake an account may hold after staking
+    pub min_stake: T::Balance,
+}
+
+impl<T: StakingConfig> Clone for StakingParameters<T> {
+    fn clone(&self) -> Self {
+        Self {
+            min_stake: self.min_stake,
+        }
+    }
+}
+
+impl<T: StakingConfig> Default for StakingParameters<T> {
+    fn default() -> Self {
+        Self {
+            min_stake: T::Balance::zero(),
+        }
+    }
+}
+
+// A single change to the staking parameters
+pub enum StakingParameter<T: StakingConfig> {
+    MinStake(T::Balance),
+}
+
+// Identifies a staking parameter without a value, e.g. for collectively chosen values
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StakingParameterKey {
+    MinStake,
+}
+
+impl<T: StakingConfig> StakingParameter<T> {
+    // Build the change setting `key` to `value`
+    pub fn with_value(key: StakingParameterKey, value: T::Balance) -> Self {
+        match key {
+            StakingParameterKey::MinStake => StakingParameter::MinStake(value),
+        }
+    }
+}
+
+impl<T: StakingConfig> Clone for StakingParameter<T> {
+    fn clone(&self) -> Self {
+        match self {
+            StakingParameter::MinStake(amount) => StakingParameter::MinStake(*amount),
+        }
+    }
+}
+
+// Deferred work queued by `enqueue_command` and run a bounded amount at a time
+pub enum PalletCommand<T: StakingConfig> {
+    DistributeRewards(T::Balance),
+    AccrueRewards(T::Balance),
+}
+
+impl<T: StakingConfig> Clone for PalletCommand<T> {
+    fn clone(&self) -> Self {
+        match self {
+            PalletCommand::DistributeRewards(pool) => PalletCommand::DistributeRewards(*pool),
+            PalletCommand::AccrueRewards(pool) => PalletCommand::AccrueRewards(*pool),
+        }
+    }
+}
+
+// Progress of a reward distribution spread over several `distribute_rewards_bounded` calls
+#[derive(Clone, Debug, PartialEq)]
+pub enum DistributeProgress<Balance> {
+    // The first `cursor` stakers are done; `remaining_pool` is still to be allocated
+    InProgress { cursor: usize, remaining_pool: Balance },
+    // Every staker is done; `paid` is the total paid out across all calls
+    Complete { paid: Balance },
+}
+
+// A distribution in progress: each staker's share, fixed when the distribution started
+struct RewardDistribution<T: StakingConfig> {
+    pool: T::Balance,
+    shares: Vec<(T::AccountId, T::Balance)>,
+    cursor: usize,
+    remaining: T::Balance,
+    paid: T::Balance,
+}
+
+impl<T: StakingConfig> Clone for RewardDistribution<T> {
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool,
+            shares: self.shares.clone(),
+            cursor: self.cursor,
+            remaining: self.remaining,
+            paid: self.paid,
+        }
+    }
+}
+
+// Aggregate balances across all accounts
+pub struct StakingStats<T: StakingConfig> {
+    pub total_free: T::Balance,
+    pub total_staked: T::Balance,
+    pub total_reserved: T::Balance,
+    // Accounts holding any nonzero free, staked or reserved balance
+    pub account_count: usize,
+    // Accounts with a nonzero stake
+    pub staker_count: usize,
+}
+
+// A conviction lock: (locked amount, block at which it expires)
+pub type ConvictionLock<T> = (<T as StakingConfig>::Balance, <T as SystemConfig>::BlockNumber);
+
+// An unbonding chunk: (amount, block at which it can be withdrawn)
+pub type UnbondingChunk<T> = (<T as StakingConfig>::Balance, <T as SystemConfig>::BlockNumber);
+
+// Every staker's stake when an era started: (account, stake) in `ordered_accounts` order
+pub type EraSnapshot<T> = Vec<(<T as SystemConfig>::AccountId, <T as StakingConfig>::Balance)>;
+
 pub struct StakingPallet<T: StakingConfig> {
     // Track free balances for each account
     pub free_balances: HashMap<T::AccountId, T::Balance>,
     // Track staked balances for each account
     pub staked_balances: HashMap<T::AccountId, T::Balance>,
+    // Track reserved balances (e.g. proposal deposits) for each account
+    pub reserved_balances: HashMap<T::AccountId, T::Balance>,
+    // Track stake locked behind votes: (voter, proposal_id) -> locked amount
+    pub vote_locks: HashMap<(T::AccountId, u32), T::Balance>,
+    // Track conviction locks that outlive their vote, per voter
+    pub conviction_locks: HashMap<T::AccountId, Vec<ConvictionLock<T>>>,
+    // Locks held by live `StakeGuard`s, innermost last
+    scoped_locks: HashMap<T::AccountId, Vec<T::Balance>>,
+    // Track unstaked tokens waiting out UNBONDING_PERIOD, per account, in unbonding order
+    pub unbonding: HashMap<T::AccountId, Vec<UnbondingChunk<T>>>,
+    // Minted rewards waiting to be allocated by `distribute_rewards`
+    reward_pool: T::Balance,
+    // Last block a reward was minted for
+    last_minted_block: Option<T::BlockNumber>,
+    // Runtime-mutable parameters
+    parameters: StakingParameters<T>,
+    // Rewards below MIN_PAYOUT deferred to the next distribution
+    carryover: HashMap<T::AccountId, T::Balance>,
+    // Rewards accrued by `accrue_rewards`, realized on claim or unstake
+    pending_rewards: HashMap<T::AccountId, T::Balance>,
+    // Block at which each current staker first staked
+    stake_since: BTreeMap<T::AccountId, T::BlockNumber>,
+    // Block of each account's last full unstake, while RESTAKE_COOLDOWN may still apply
+    last_full_unstake: HashMap<T::AccountId, T::BlockNumber>,
+    // Index of the current reward era
+    current_era: u32,
+    // Stake snapshots of eras not yet paid out
+    era_snapshots: BTreeMap<u32, EraSnapshot<T>>,
+    // Distribution started by `distribute_rewards_bounded` and not yet complete
+    reward_distribution: Option<RewardDistribution<T>>,
+    // Current block, updated by `on_initialize`
+    current_block: T::BlockNumber,
+    // Deferred commands, oldest first
+    command_queue: Vec<PalletCommand<T>>,
+    // Accounts registered as validators
+    validators: BTreeSet<T::AccountId>,
+    // Stake each nominator puts behind each validator: (nominator, validator) -> amount
+    nominations: BTreeMap<(T::AccountId, T::AccountId), T::Balance>,
+    // Emergency switch: while set, stake and unstake are refused
+    frozen: bool,
+    // Layout version of this state
+    storage_version: StorageVersion,
+}
+
+impl<T: StakingConfig> Clone for StakingPallet<T> {
+    fn clone(&self) -> Self {
+        Self {
+            free_balances: self.free_balances.clone(),
+            staked_balances: self.staked_balances.clone(),
+            reserved_balances: self.reserved_balances.clone(),
+            vote_locks: self.vote_locks.clone(),
+            conviction_locks: self.conviction_locks.clone(),
+            scoped_locks: self.scoped_locks.clone(),
+            unbonding: self.unbonding.clone(),
+            reward_pool: self.reward_pool,
+            last_minted_block: self.last_minted_block,
+            parameters: self.parameters.clone(),
+            carryover: self.carryover.clone(),
+            pending_rewards: self.pending_rewards.clone(),
+            stake_since: self.stake_since.clone(),
+            last_full_unstake: self.last_full_unstake.clone(),
+            current_era: self.current_era,
+            era_snapshots: self.era_snapshots.clone(),
+            reward_distribution: self.reward_distribution.clone(),
+            current_block: self.current_block,
+            command_queue: self.command_queue.clone(),
+            validators: self.validators.clone(),
+            nominations: self.nominations.clone(),
+            frozen: self.frozen,
+            storage_version: self.storage_version,
+        }
+    }
+}
+
+impl<T: StakingConfig> BalanceProvider<T> for StakingPallet<T> {
+    fn staked_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.get_staked_balance_ref(who)
+    }
+
+    fn free_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.get_free_balance_ref(who)
+    }
+}
+
+// Stake locked for as long as the guard lives, released when it is dropped, so a lock taken
+// for a multi-step operation cannot leak through an early return. The pallet is reached
+// through the guard while it is held.
+pub struct StakeGuard<'a, T: StakingConfig> {
+    pallet: &'a mut StakingPallet<T>,
+    who: T::AccountId,
+}
+
+impl<T: StakingConfig> Deref for StakeGuard<'_, T> {
+    type Target = StakingPallet<T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.pallet
+    }
+}
+
+impl<T: StakingConfig> DerefMut for StakeGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.pallet
+    }
+}
+
+impl<T: StakingConfig> Drop for StakeGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Some(locks) = self.pallet.scoped_locks.get_mut(&self.who) {
+            locks.pop();
+            if locks.is_empty() {
+                self.pallet.scoped_locks.remove(&self.who);
+            }
+        }
+    }
+}
+
+impl<T: StakingConfig> Default for StakingPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T: StakingConfig> StakingPallet<T> {
+    // Current layout version of staking state
+    pub const STORAGE_VERSION: StorageVersion = StorageVersion(1);
+
     pub fn new() -> Self {
         Self {
             free_balances: HashMap::new(),
             staked_balances: HashMap::new(),
+            reserved_balances: HashMap::new(),
+            vote_locks: HashMap::new(),
+            conviction_locks: HashMap::new(),
+            scoped_locks: HashMap::new(),
+            unbonding: HashMap::new(),
+            reward_pool: T::Balance::zero(),
+            last_minted_block: None,
+            parameters: StakingParameters::default(),
+            carryover: HashMap::new(),
+            pending_rewards: HashMap::new(),
+            stake_since: BTreeMap::new(),
+            last_full_unstake: HashMap::new(),
+            current_era: 0,
+            era_snapshots: BTreeMap::new(),
+            reward_distribution: None,
+            current_block: T::BlockNumber::zero(),
+            command_queue: Vec::new(),
+            validators: BTreeSet::new(),
+            nominations: BTreeMap::new(),
+            frozen: false,
+            storage_version: Self::STORAGE_VERSION,
+        }
+    }
+
+    // Pause staking mutations; getters keep working.
+    // Authorization of this call belongs to the runtime layer (root/governance).
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    // Resume staking mutations after a `freeze`
+    pub fn thaw(&mut self) {
+        self.frozen = false;
+    }
+
+    // Check whether staking is currently frozen
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    // Get the layout version of this state
+    pub fn storage_version(&self) -> StorageVersion {
+        self.storage_version
+    }
+
+    // Upgrade state stored at version `from` to STORAGE_VERSION.
+    // The staking layout has not changed yet, so only the current version is accepted.
+    #[must_use = "a failed migration leaves the state at its old version"]
+    pub fn migrate(&mut self, from: u16) -> Result<(), &'static str> {
+        if StorageVersion(from) != self.storage_version {
+            return Err("Storage version mismatch");
+        }
+
+        if self.storage_version != Self::STORAGE_VERSION {
+            return Err("Unknown storage version");
+        }
+
+        Ok(())
+    }
+
+    // Block initialization hook: record the block being executed
+    pub fn on_initialize(&mut self, block: T::BlockNumber) {
+        self.current_block = block;
+    }
+
+    // Get how many blocks an account has been staking, zero if it is not staking
+    pub fn maturity(&self, who: &T::AccountId, current_block: T::BlockNumber) -> T::BlockNumber {
+        self.stake_since
+            .get(who)
+            .map(|since| blocks_until(*since, current_block))
+            .unwrap_or(T::BlockNumber::zero())
+    }
+
+    // Get the current staking parameters
+    pub fn parameters(&self) -> &StakingParameters<T> {
+        &self.parameters
+    }
+
+    // Apply a parameter change
+    pub fn set_parameter(&mut self, parameter: StakingParameter<T>) {
+        match parameter {
+            StakingParameter::MinStake(amount) => self.parameters.min_stake = amount,
+        }
+    }
+
+    // Set free balance for an account; setting it to zero reaps the entry
+    #[must_use = "a rejected account keeps its previous balance"]
+    pub fn set_balance(&mut self, who: T::AccountId, amount: T::Balance) -> Result<(), &'static str> {
+        if !T::is_valid_account(&who) {
+            return Err("Invalid account");
+        }
+
+        if amount.is_zero() {
+            self.free_balances.remove(&who);
+        } else {
+            self.free_balances.insert(who, amount);
+        }
+
+        Ok(())
+    }
+
+    // Get an account's free balance entry, inserting a zero entry if it has none, so the
+    // caller can endow it in place. A zero entry left behind is only reaped by the next
+    // `set_balance` to zero, and `has_free_balance` ignores it until it holds funds.
+    #[must_use = "the entry is inserted to be written through"]
+    pub fn ensure_account(&mut self, who: T::AccountId) -> Result<&mut T::Balance, &'static str> {
+        if !T::is_valid_account(&who) {
+            return Err("Invalid account");
         }
+
+        Ok(self.free_balances.entry(who).or_insert(T::Balance::zero()))
+    }
+
+    // Set free balance like `set_balance`, but refuse amounts that would push total issuance
+    // past the balance type's maximum
+    #[must_use = "a rejected account keeps its previous balance"]
+    pub fn checked_set_balance(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<(), &'static str> {
+        let issuance = self.total_issuance().ok_or("Issuance overflow")?;
+        issuance.checked_sub(&self.get_free_balance_ref(&who))
+            .and_then(|others| others.checked_add(&amount))
+            .ok_or("Issuance overflow")?;
+
+        self.set_balance(who, amount)
+    }
+
+    // Stake tokens (move from free to staked), returning the remaining free balance
+    #[must_use = "a failed staking operation leaves balances unchanged"]
+    pub fn stake(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<T::Balance, &'static str> {
+        self.stake_inner(who, amount).map_err(|(error, _)| error)
     }
 
-    // Set free balance for an account
-    pub fn set_balance(&mut self, who: T::AccountId, amount: T::Balance) {
-        self.free_balances.insert(who, amount);
+    // Stake tokens, reporting on failure how much more free balance the account would need.
+    // Failures that a top-up cannot fix (frozen, stake below minimum, overflow) report a zero
+    // shortfall.
+    #[must_use = "a failed staking operation leaves balances unchanged"]
+    pub fn try_stake(&mut self, who: T::AccountId, amount: T::Balance) -> Result<(), T::Balance> {
+        self.stake_inner(who, amount)
+            .map(|_| ())
+            .map_err(|(_, shortfall)| shortfall)
     }
 
-    // Stake tokens (move from free to staked)
-    pub fn stake(&mut self, who: T::AccountId, amount: T::Balance) -> Result<(), &'static str> {
+    // Shared by `stake` and `try_stake`: errors carry both the reason and the balance shortfall
+    fn stake_inner(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<T::Balance, (&'static str, T::Balance)> {
+        if self.frozen {
+            return Err(("Staking is frozen", T::Balance::zero()));
+        }
+
+        if !T::is_valid_account(&who) {
+            return Err(("Invalid account", T::Balance::zero()));
+        }
+
+        self.ensure_restake_allowed(&who).map_err(|error| (error, T::Balance::zero()))?;
+
         let free_balance = self.free_balances.get(&who).copied()
             .unwrap_or(T::Balance::zero());
 
@@ -36,20 +482,91 @@ impl<T: StakingConfig> StakingPallet<T> {
             .unwrap_or(T::Balance::zero());
 
         let new_free = free_balance.checked_sub(&amount)
-            .ok_or("Insufficient balance")?;
-        
+            .ok_or_else(|| ("Insufficient balance", amount - free_balance))?;
+
         let new_staked = staked_balance.checked_add(&amount)
-            .ok_or("Overflow")?;
+            .ok_or(("Overflow", T::Balance::zero()))?;
+
+        if new_staked < self.parameters.min_stake {
+            return Err(("Stake below minimum", T::Balance::zero()));
+        }
 
         self.free_balances.insert(who.clone(), new_free);
-        self.staked_balances.insert(who, new_staked);
+        self.staked_balances.insert(who.clone(), new_staked);
+        self.stake_since.entry(who.clone()).or_insert(self.current_block);
+        self.last_full_unstake.remove(&who);
+
+        T::Hooks::on_stake(&who, amount);
+
+        Ok(new_free)
+    }
+
+    // Check that RESTAKE_COOLDOWN has passed since `who` last unstaked everything
+    fn ensure_restake_allowed(&self, who: &T::AccountId) -> Result<(), &'static str> {
+        if let Some(unstaked_at) = self.last_full_unstake.get(who) {
+            let cooldown_end = unstaked_at.checked_add(&T::RESTAKE_COOLDOWN)
+                .ok_or("Overflow")?;
+            if !has_reached(self.current_block, cooldown_end) {
+                return Err("Restake on cooldown");
+            }
+        }
 
         Ok(())
     }
-    
 
-    // Unstake tokens (move from staked to free)
-    pub fn unstake(&mut self, who: T::AccountId, amount: T::Balance) -> Result<(), &'static str> {
+    // Stake tokens and mint REFERRAL_BONUS_PERCENT of the amount to the referrer's free balance.
+    // Returns the staker's remaining free balance.
+    #[must_use = "a failed staking operation leaves balances unchanged"]
+    pub fn stake_with_referral(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+        referrer: T::AccountId,
+    ) -> Result<T::Balance, &'static str> {
+        if who == referrer {
+            return Err("Cannot refer yourself");
+        }
+
+        let bonus = amount.checked_mul(&T::Balance::from(T::REFERRAL_BONUS_PERCENT))
+            .ok_or("Overflow")?
+            .checked_div(&T::Balance::from(100u32))
+            .ok_or("Overflow")?;
+
+        let new_issuance = self.total_issuance()
+            .and_then(|issuance| issuance.checked_add(&bonus))
+            .ok_or("Overflow")?;
+        if new_issuance > T::MAX_SUPPLY {
+            return Err("Exceeds max supply");
+        }
+
+        if !T::is_valid_account(&referrer) {
+            return Err("Invalid account");
+        }
+
+        let new_referrer_free = self.get_free_balance_ref(&referrer).checked_add(&bonus)
+            .ok_or("Overflow")?;
+
+        let new_free = self.stake(who, amount)?;
+        if !bonus.is_zero() {
+            self.checked_set_balance(referrer, new_referrer_free)?;
+        }
+
+        Ok(new_free)
+    }
+
+    // Unstake tokens (move from staked to free), returning the new free balance.
+    // The unstaked fraction of any pending rewards is realized to free balance too,
+    // rounded down; the rest stays pending. Unstaking everything starts RESTAKE_COOLDOWN.
+    #[must_use = "a failed staking operation leaves balances unchanged"]
+    pub fn unstake(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<T::Balance, &'static str> {
+        if self.frozen {
+            return Err("Staking is frozen");
+        }
+
         let staked_balance = self.staked_balances.get(&who).copied()
             .unwrap_or(T::Balance::zero());
 
@@ -59,78 +576,2478 @@ impl<T: StakingConfig> StakingPallet<T> {
         let new_staked = staked_balance.checked_sub(&amount)
             .ok_or("Insufficient staked balance")?;
 
+        if new_staked < self.locked_for_voting(&who) {
+            return Err("Stake locked by active vote");
+        }
+
+        let pending = self.get_pending_rewards(who.clone());
+        let realized = self.pending_share(&who, amount)?;
+
         let new_free = free_balance.checked_add(&amount)
+            .and_then(|free| free.checked_add(&realized))
+            .ok_or("Overflow")?;
+
+        let new_pending = pending.checked_sub(&realized)
             .ok_or("Overflow")?;
 
         self.staked_balances.insert(who.clone(), new_staked);
-        self.free_balances.insert(who, new_free);
+        self.free_balances.insert(who.clone(), new_free);
+        if new_staked.is_zero() {
+            self.stake_since.remove(&who);
+            if !amount.is_zero() {
+                self.last_full_unstake.insert(who.clone(), self.current_block);
+            }
+        }
+        if !realized.is_zero() {
+            self.pending_rewards.insert(who.clone(), new_pending);
+        }
+
+        T::Hooks::on_unstake(&who, amount);
+
+        Ok(new_free)
+    }
+
+    // Unstake tokens into the unbonding queue; they reach free balance through
+    // `withdraw_unbonded` once UNBONDING_PERIOD blocks have passed.
+    // Pending rewards realized by the unstake are paid to free balance immediately.
+    #[must_use = "a failed staking operation leaves balances unchanged"]
+    pub fn unbond(&mut self, who: T::AccountId, amount: T::Balance) -> Result<(), &'static str> {
+        let unlock_at = self.current_block.checked_add(&T::UNBONDING_PERIOD)
+            .ok_or("Overflow")?;
+
+        checked_sum(self.unbonding_chunks(&who).iter().map(|(amount, _)| amount))
+            .and_then(|total| total.checked_add(&amount))
+            .ok_or("Overflow")?;
+
+        let new_free = self.unstake(who.clone(), amount)?
+            .checked_sub(&amount)
+            .ok_or("Overflow")?;
+
+        self.free_balances.insert(who.clone(), new_free);
+        self.unbonding.entry(who).or_default().push((amount, unlock_at));
 
         Ok(())
     }
 
-    // Get free balance for an account
-    pub fn get_free_balance(&self, who: T::AccountId) -> T::Balance {
-        self.free_balances.get(&who).copied().unwrap_or(T::Balance::zero())
+    // Move every unbonding chunk that has matured by the current block to free balance,
+    // returning the amount withdrawn
+    #[must_use = "a failed withdrawal leaves balances unchanged"]
+    pub fn withdraw_unbonded(&mut self, who: T::AccountId) -> Result<T::Balance, &'static str> {
+        if self.frozen {
+            return Err("Staking is frozen");
+        }
+
+        let now = self.current_block;
+        let Some(chunks) = self.unbonding.get(&who) else {
+            return Ok(T::Balance::zero());
+        };
+
+        let withdrawn = checked_sum(
+            chunks.iter()
+                .filter(|(_, unlock_at)| has_reached(now, *unlock_at))
+                .map(|(amount, _)| amount),
+        )
+        .ok_or("Overflow")?;
+
+        let new_free = self.get_free_balance_ref(&who).checked_add(&withdrawn)
+            .ok_or("Overflow")?;
+
+        self.free_balances.insert(who.clone(), new_free);
+        if let Some(chunks) = self.unbonding.get_mut(&who) {
+            chunks.retain(|(_, unlock_at)| !has_reached(now, *unlock_at));
+            if chunks.is_empty() {
+                self.unbonding.remove(&who);
+            }
+        }
+
+        Ok(withdrawn)
     }
 
-    // Get staked balance for an account
-    pub fn get_staked_balance(&self, who: T::AccountId) -> T::Balance {
-        self.staked_balances.get(&who).copied().unwrap_or(T::Balance::zero())
+    // Cancel part of an in-progress unbond, moving `amount` from the unbonding queue back
+    // into staked balance. Soonest-maturing chunks are rebonded first. Like staking, this
+    // waits out RESTAKE_COOLDOWN after unstaking everything and keeps the total stake at or
+    // above the minimum.
+    #[must_use = "a failed staking operation leaves balances unchanged"]
+    pub fn rebond(&mut self, who: T::AccountId, amount: T::Balance) -> Result<(), &'static str> {
+        if self.frozen {
+            return Err("Staking is frozen");
+        }
+
+        self.ensure_restake_allowed(&who)?;
+
+        if self.unbonding_total(&who) < amount {
+            return Err("Insufficient unbonding balance");
+        }
+
+        let new_staked = self.get_staked_balance_ref(&who).checked_add(&amount)
+            .ok_or("Overflow")?;
+
+        if new_staked < self.parameters.min_stake {
+            return Err("Stake below minimum");
+        }
+
+        let mut chunks = self.unbonding.remove(&who).unwrap_or_default();
+        chunks.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        let mut remaining = amount;
+        chunks.retain_mut(|(chunk, _)| {
+            if remaining.is_zero() {
+                return true;
+            }
+            if *chunk <= remaining {
+                remaining = remaining - *chunk;
+                return false;
+            }
+            *chunk = *chunk - remaining;
+            remaining = T::Balance::zero();
+            true
+        });
+
+        if !chunks.is_empty() {
+            self.unbonding.insert(who.clone(), chunks);
+        }
+        self.staked_balances.insert(who.clone(), new_staked);
+        self.stake_since.entry(who.clone()).or_insert(self.current_block);
+        self.last_full_unstake.remove(&who);
+
+        T::Hooks::on_stake(&who, amount);
+
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Runtime;
+    // Lock staked tokens behind a vote on a proposal
+    #[must_use = "a failed lock leaves the stake unlocked"]
+    pub fn lock_for_vote(
+        &mut self,
+        who: T::AccountId,
+        proposal_id: u32,
+        amount: T::Balance,
+    ) -> Result<(), &'static str> {
+        if amount > self.get_staked_balance_ref(&who) {
+            return Err("Insufficient staked balance");
+        }
 
-    #[test]
-    fn test_staking_should_work() {
-        let alice = 1u64;
-        let mut staking = StakingPallet::<Runtime>::new();
+        self.vote_locks.insert((who, proposal_id), amount);
 
-        // Set initial balance
-        staking.set_balance(alice, 1000);
+        Ok(())
+    }
 
-        // Check free balance
-        assert_eq!(staking.get_free_balance(alice), 1000u64);
-        assert_eq!(staking.get_staked_balance(alice), 0u64);
+    // Release the stake locked behind a vote on a proposal
+    pub fn unlock_vote(&mut self, who: T::AccountId, proposal_id: u32) {
+        self.vote_locks.remove(&(who, proposal_id));
+    }
 
-        // Stake tokens
-        let result = staking.stake(alice, 400);
-        assert!(result.is_ok());
+    // Lock staked tokens until `expires_at`, independently of any proposal
+    #[must_use = "a failed lock leaves the stake unlocked"]
+    pub fn lock_until(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+        expires_at: T::BlockNumber,
+    ) -> Result<(), &'static str> {
+        if amount > self.get_staked_balance_ref(&who) {
+            return Err("Insufficient staked balance");
+        }
 
-        // Check balances after staking
-        assert_eq!(staking.get_free_balance(alice), 600u64);
-        assert_eq!(staking.get_staked_balance(alice), 400u64);
+        self.conviction_locks.entry(who).or_default().push((amount, expires_at));
 
-        // Unstake tokens
-        let result = staking.unstake(alice, 100);
-        assert!(result.is_ok());
+        Ok(())
+    }
 
-        // Check balances after unstaking
-        assert_eq!(staking.get_free_balance(alice), 700u64);
-        assert_eq!(staking.get_staked_balance(alice), 300u64);
+    // Release every conviction lock that has expired by `current_block`.
+    // Returns the number of locks released.
+    pub fn process_vote_locks(&mut self, current_block: T::BlockNumber) -> usize {
+        let mut released = 0;
+        self.conviction_locks.retain(|_, locks| {
+            let before = locks.len();
+            locks.retain(|(_, expires_at)| !has_reached(current_block, *expires_at));
+            released += before - locks.len();
+            !locks.is_empty()
+        });
+
+        released
     }
 
-    #[test]
-    fn test_staking_errors() {
-        let bob = 2u64;
-        let mut staking = StakingPallet::<Runtime>::new();
+    // Lock staked tokens until the returned guard is dropped
+    #[must_use = "the lock is released as soon as the guard is dropped"]
+    pub fn lock_stake_scoped(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<StakeGuard<'_, T>, &'static str> {
+        if amount > self.get_staked_balance_ref(&who) {
+            return Err("Insufficient staked balance");
+        }
 
-        // Set initial balance
-        staking.set_balance(bob, 500);
+        self.scoped_locks.entry(who.clone()).or_default().push(amount);
 
-        // Try to stake more than available
-        let result = staking.stake(bob, 600);
-        assert!(result.is_err());
+        Ok(StakeGuard { pallet: self, who })
+    }
 
-        // Stake valid amount
-        let result = staking.stake(bob, 300);
-        assert!(result.is_ok());
+    // Get the stake locked for voting or by a `StakeGuard`; the same stake backs every lock,
+    // so this is the largest lock
+    pub fn locked_for_voting(&self, who: &T::AccountId) -> T::Balance {
+        let conviction = self.conviction_locks
+            .get(who)
+            .into_iter()
+            .flatten()
+            .map(|(amount, _)| *amount);
+        let scoped = self.scoped_locks.get(who).into_iter().flatten().copied();
 
-        // Try to unstake more than staked
-        let result = staking.unstake(bob, 400);
-        assert!(result.is_err());
+        self.vote_locks
+            .iter()
+            .filter(|((voter, _), _)| voter == who)
+            .map(|(_, amount)| *amount)
+            .chain(conviction)
+            .chain(scoped)
+            .max()
+            .unwrap_or(T::Balance::zero())
+    }
+
+    // Register a staker as a validator that others can nominate
+    #[must_use = "a failed registration leaves the account unregistered"]
+    pub fn register_validator(&mut self, who: T::AccountId) -> Result<(), &'static str> {
+        if self.get_staked_balance_ref(&who).is_zero() {
+            return Err("Validator has no stake");
+        }
+
+        if self.nominations.keys().any(|(nominator, _)| *nominator == who) {
+            return Err("Nominators cannot be validators");
+        }
+
+        self.validators.insert(who);
+
+        Ok(())
+    }
+
+    // Back a validator with the nominator's whole stake, replacing any previous nominations
+    #[must_use = "a failed nomination leaves the previous nomination in place"]
+    pub fn nominate(&mut self, who: T::AccountId, validator: T::AccountId) -> Result<(), &'static str> {
+        let staked = self.get_staked_balance_ref(&who);
+        self.nominate_many(who, vec![(validator, staked)])
+    }
+
+    // Split the nominator's stake across several validators, replacing any previous
+    // nominations. Either every target is applied or, on any error, none is.
+    #[must_use = "a failed nomination leaves the previous nominations in place"]
+    pub fn nominate_many(
+        &mut self,
+        nominator: T::AccountId,
+        targets: Vec<(T::AccountId, T::Balance)>,
+    ) -> Result<(), &'static str> {
+        if self.frozen {
+            return Err("Staking is frozen");
+        }
+
+        if self.validators.contains(&nominator) {
+            return Err("Validators cannot nominate");
+        }
+
+        let staked = self.get_staked_balance_ref(&nominator);
+        if staked.is_zero() {
+            return Err("Nominator has no stake");
+        }
+
+        let requested = checked_sum(targets.iter().map(|(_, amount)| amount))
+            .ok_or("Overflow")?;
+        if requested > staked {
+            return Err("Insufficient staked balance");
+        }
+
+        let mut seen = BTreeSet::new();
+        for (validator, amount) in targets.iter() {
+            if !self.validators.contains(validator) {
+                return Err("Not a validator");
+            }
+
+            if amount.is_zero() {
+                return Err("Nomination amount is zero");
+            }
+
+            if !seen.insert(validator) {
+                return Err("Duplicate nomination target");
+            }
+
+            // Re-nominating a validator keeps the slot already held
+            let holds_slot = self.nominations.contains_key(&(nominator.clone(), validator.clone()));
+            if !holds_slot && self.nominators_of(validator).len() >= T::MAX_NOMINATORS_PER_VALIDATOR as usize {
+                return Err("Validator nominator slots full");
+            }
+        }
+
+        self.nominations.retain(|(who, _), _| *who != nominator);
+        for (validator, amount) in targets {
+            self.nominations.insert((nominator.clone(), validator), amount);
+        }
+
+        Ok(())
+    }
+
+    // Withdraw all of an account's nominations, freeing the validators' slots
+    pub fn withdraw_nomination(&mut self, who: &T::AccountId) {
+        self.nominations.retain(|(nominator, _), _| nominator != who);
+    }
+
+    // Revoke a nomination, settling the share of the nominator's pending rewards that the
+    // nominated stake accounts for (pending * nominated / staked, as `unstake` does) to its
+    // free balance; the rest stays pending. Nominated stake is never moved out of the staked
+    // balance, so once the nomination is gone it is ordinary, unbacked stake again. Returns
+    // the rewards settled.
+    #[must_use = "a failed revocation leaves the nomination in place"]
+    pub fn revoke_nomination(
+        &mut self,
+        nominator: T::AccountId,
+        validator: &T::AccountId,
+    ) -> Result<T::Balance, &'static str> {
+        if self.frozen {
+            return Err("Staking is frozen");
+        }
+
+        let key = (nominator.clone(), validator.clone());
+        if !self.nominations.contains_key(&key) {
+            return Err("Not nominating this validator");
+        }
+
+        let nominated = self.nominations[&key];
+        let settled = self.pending_share(&nominator, nominated)?;
+        if !settled.is_zero() {
+            let new_free = self.get_free_balance_ref(&nominator).checked_add(&settled)
+                .ok_or("Overflow")?;
+            let new_pending = self.get_pending_rewards(nominator.clone()).checked_sub(&settled)
+                .ok_or("Overflow")?;
+            self.free_balances.insert(nominator.clone(), new_free);
+            self.pending_rewards.insert(nominator, new_pending);
+        }
+        self.nominations.remove(&key);
+
+        Ok(settled)
+    }
+
+    // The part of `who`'s pending rewards that goes with `part` of its staked balance,
+    // rounded down. An account with nothing staked has no share to settle, so its pending
+    // rewards stay where they are.
+    fn pending_share(&self, who: &T::AccountId, part: T::Balance) -> Result<T::Balance, &'static str> {
+        let pending = self.get_pending_rewards(who.clone());
+        let staked = self.get_staked_balance_ref(who);
+        if pending.is_zero() || staked.is_zero() {
+            return Ok(T::Balance::zero());
+        }
+        if part > staked {
+            return Err("Insufficient staked balance");
+        }
+
+        mul_div(pending, part, staked).ok_or("Overflow")
+    }
+
+    // Unstake `amount` from a nominator and take it out of its nominations in proportion to
+    // each one's share of the total nominated, so the ratios between validators are kept.
+    // Flooring leaves a remainder that is taken one unit at a time in validator order, so the
+    // reductions sum to exactly `amount`. A nomination reduced to zero is dropped.
+    #[must_use = "a failed unstake leaves balances and nominations unchanged"]
+    pub fn unstake_proportional(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<T::Balance, &'static str> {
+        let nominations: Vec<(T::AccountId, T::Balance)> = self.nominations
+            .iter()
+            .filter(|((nominator, _), _)| *nominator == who)
+            .map(|((_, validator), nominated)| (validator.clone(), *nominated))
+            .collect();
+
+        let total = checked_sum(nominations.iter().map(|(_, nominated)| nominated))
+            .ok_or("Overflow")?;
+        if total.is_zero() {
+            return Err("Not nominating");
+        }
+        if amount > total {
+            return Err("Amount exceeds nominated stake");
+        }
+
+        let mut reductions = Vec::new();
+        for (_, nominated) in nominations.iter() {
+            let reduction = amount.checked_mul(nominated)
+                .ok_or("Overflow")?
+                .checked_div(&total)
+                .ok_or("Overflow")?;
+            reductions.push(reduction);
+        }
+
+        // Flooring loses less than one unit per nomination, and a floored reduction is always
+        // below its nomination, so the remainder fits in one pass
+        let one = T::Balance::from(1u32);
+        let mut remainder = amount.checked_sub(&checked_sum(reductions.iter()).ok_or("Overflow")?)
+            .ok_or("Overflow")?;
+        for ((_, nominated), reduction) in nominations.iter().zip(reductions.iter_mut()) {
+            if remainder.is_zero() {
+                break;
+            }
+            if *reduction < *nominated {
+                *reduction = reduction.checked_add(&one).ok_or("Overflow")?;
+                remainder = remainder.checked_sub(&one).ok_or("Overflow")?;
+            }
+        }
+
+        let free = self.unstake(who.clone(), amount)?;
+
+        for ((validator, nominated), reduction) in nominations.into_iter().zip(reductions) {
+            let key = (who.clone(), validator);
+            match nominated.checked_sub(&reduction) {
+                Some(left) if !left.is_zero() => {
+                    self.nominations.insert(key, left);
+                }
+                _ => {
+                    self.nominations.remove(&key);
+                }
+            }
+        }
+
+        Ok(free)
+    }
+
+    // Get a validator's nominators, sorted by account id
+    pub fn nominators_of(&self, validator: &T::AccountId) -> Vec<T::AccountId> {
+        self.nominations
+            .keys()
+            .filter(|(_, nominated)| nominated == validator)
+            .map(|(nominator, _)| nominator.clone())
+            .collect()
+    }
+
+    // Get the stake behind a validator: its own stake plus what each nominator put behind
+    // it, capped at the nominator's stake. None on overflow.
+    pub fn total_backing(&self, validator: &T::AccountId) -> Option<T::Balance> {
+        let nominated: Vec<T::Balance> = self.nominators_of(validator)
+            .iter()
+            .map(|nominator| {
+                self.get_staked_balance_ref(nominator)
+                    .min(self.nominated_amount(nominator, validator))
+            })
+            .collect();
+
+        checked_sum(nominated.iter())?.checked_add(&self.get_staked_balance_ref(validator))
+    }
+
+    // Get the stake a nominator has put behind a validator, zero if it does not nominate it
+    pub fn nominated_amount(&self, nominator: &T::AccountId, validator: &T::AccountId) -> T::Balance {
+        self.nominations
+            .get(&(nominator.clone(), validator.clone()))
+            .copied()
+            .unwrap_or(T::Balance::zero())
+    }
+
+    // Check whether an account is a registered validator
+    pub fn is_validator(&self, who: &T::AccountId) -> bool {
+        self.validators.contains(who)
+    }
+
+    // Reserve tokens (move from free to reserved)
+    #[must_use = "a failed reservation leaves balances unchanged"]
+    pub fn reserve(&mut self, who: T::AccountId, amount: T::Balance) -> Result<(), &'static str> {
+        if self.frozen {
+            return Err("Staking is frozen");
+        }
+
+        let free_balance = self.free_balances.get(&who).copied()
+            .unwrap_or(T::Balance::zero());
+
+        let reserved_balance = self.reserved_balances.get(&who).copied()
+            .unwrap_or(T::Balance::zero());
+
+        let new_free = free_balance.checked_sub(&amount)
+            .ok_or("Insufficient balance")?;
+
+        let new_reserved = reserved_balance.checked_add(&amount)
+            .ok_or("Overflow")?;
+
+        self.free_balances.insert(who.clone(), new_free);
+        self.reserved_balances.insert(who, new_reserved);
+
+        Ok(())
+    }
+
+    // Unreserve tokens (move from reserved back to free)
+    #[must_use = "a failed unreservation leaves balances unchanged"]
+    pub fn unreserve(&mut self, who: T::AccountId, amount: T::Balance) -> Result<(), &'static str> {
+        if self.frozen {
+            return Err("Staking is frozen");
+        }
+
+        let reserved_balance = self.reserved_balances.get(&who).copied()
+            .unwrap_or(T::Balance::zero());
+
+        let free_balance = self.free_balances.get(&who).copied()
+            .unwrap_or(T::Balance::zero());
+
+        let new_reserved = reserved_balance.checked_sub(&amount)
+            .ok_or("Insufficient reserved balance")?;
+
+        let new_free = free_balance.checked_add(&amount)
+            .ok_or("Overflow")?;
+
+        self.reserved_balances.insert(who.clone(), new_reserved);
+        self.free_balances.insert(who, new_free);
+
+        Ok(())
+    }
+
+    // Move reserved tokens into the treasury, e.g. a forfeited proposal deposit
+    #[must_use = "a failed slash leaves balances unchanged"]
+    pub fn slash_reserved(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+        treasury: &mut TreasuryPallet<T>,
+    ) -> Result<(), &'static str> {
+        let new_reserved = self.get_reserved_balance(who.clone()).checked_sub(&amount)
+            .ok_or("Insufficient reserved balance")?;
+
+        treasury.deposit(amount)?;
+        self.reserved_balances.insert(who, new_reserved);
+
+        Ok(())
+    }
+
+    // Slash staked tokens into the treasury, capped at the slashable stake.
+    // Slashing a validator also slashes its nominators, who share the risk: the total is
+    // split by each account's share of the backing (validator stake plus nominated amounts),
+    // rounding down, and the rounding remainder falls on the validator.
+    // Returns the amount actually slashed.
+    #[must_use = "a failed slash leaves balances unchanged"]
+    pub fn slash(
+        &mut self,
+        who: T::AccountId,
+        amount: T::Balance,
+        treasury: &mut TreasuryPallet<T>,
+    ) -> Result<T::Balance, &'static str> {
+        let mut backers = vec![who.clone()];
+        if self.validators.contains(&who) {
+            backers.extend(self.nominators_of(&who));
+        }
+
+        // A nominator risks only what it put behind this validator
+        let stakes: Vec<T::Balance> = backers.iter()
+            .enumerate()
+            .map(|(index, backer)| {
+                let staked = self.get_staked_balance_ref(backer);
+                if index == 0 {
+                    staked
+                } else {
+                    staked.min(self.nominated_amount(backer, &who))
+                }
+            })
+            .collect();
+        let backing = checked_sum(stakes.iter()).ok_or("Overflow")?;
+        let total = amount.min(backing);
+
+        let mut cuts = Vec::with_capacity(stakes.len());
+        for stake in stakes.iter() {
+            let cut = if backing.is_zero() {
+                T::Balance::zero()
+            } else {
+                total.checked_mul(stake).ok_or("Overflow")? / backing
+            };
+            cuts.push(cut);
+        }
+
+        // The remainder goes to the validator, spilling a unit at a time onto nominators
+        // if the validator's stake cannot absorb it
+        let mut remainder = total - checked_sum(cuts.iter()).ok_or("Overflow")?;
+        let one = T::Balance::from(1u32);
+        let room = stakes[0] - cuts[0];
+        let validator_extra = remainder.min(room);
+        cuts[0] = cuts[0] + validator_extra;
+        remainder = remainder - validator_extra;
+        for (cut, stake) in cuts.iter_mut().zip(stakes.iter()).skip(1) {
+            if remainder.is_zero() {
+                break;
+            }
+            if *cut < *stake {
+                *cut = *cut + one;
+                remainder = remainder - one;
+            }
+        }
+
+        treasury.deposit(total)?;
+        for (index, (backer, cut)) in backers.into_iter().zip(cuts).enumerate() {
+            // The cut comes out of the backer's whole stake; a nominator's nomination shrinks
+            // by the same amount, and is dropped once nothing is left behind the validator
+            let staked = self.get_staked_balance_ref(&backer);
+            self.staked_balances.insert(backer.clone(), staked - cut);
+            if index > 0 {
+                let key = (backer, who.clone());
+                let left = self.nominated_amount(&key.0, &who) - cut;
+                if left.is_zero() {
+                    self.nominations.remove(&key);
+                } else {
+                    self.nominations.insert(key, left);
+                }
+            }
+        }
+
+        Ok(total)
+    }
+
+    // Mint the reward for a block into the reward pool, returning the amount minted.
+    // Each block is minted at most once and issuance never exceeds MAX_SUPPLY.
+    pub fn mint_block_reward(&mut self, block: T::BlockNumber) -> T::Balance {
+        if self.last_minted_block.is_some_and(|last| block <= last) {
+            return T::Balance::zero();
+        }
+
+        let remaining = self.total_issuance()
+            .and_then(|issuance| T::MAX_SUPPLY.checked_sub(&issuance))
+            .unwrap_or(T::Balance::zero());
+
+        let rate = T::Balance::from(T::INFLATION_RATE_PPM);
+        let million = T::Balance::from(1_000_000u32);
+        let reward = remaining.checked_mul(&rate)
+            .and_then(|scaled| scaled.checked_div(&million))
+            .or_else(|| remaining.checked_div(&million).and_then(|unit| unit.checked_mul(&rate)))
+            .unwrap_or(T::Balance::zero());
+
+        // `reward <= remaining`, so the pool cannot pass the cap
+        self.reward_pool = self.reward_pool.checked_add(&reward)
+            .unwrap_or(self.reward_pool);
+        self.last_minted_block = Some(block);
+
+        reward
+    }
+
+    // Get stakers in reward processing order: largest stake first, ties by ascending account id
+    pub fn ordered_accounts(&self) -> Vec<(T::AccountId, T::Balance)> {
+        let mut accounts: Vec<(T::AccountId, T::Balance)> = self.staked_balances
+            .iter()
+            .filter(|(_, stake)| !stake.is_zero())
+            .map(|(who, stake)| (who.clone(), *stake))
+            .collect();
+
+        accounts.sort_by(|(a, a_stake), (b, b_stake)| b_stake.cmp(a_stake).then_with(|| a.cmp(b)));
+        accounts
+    }
+
+    // Pay `pool` out of the reward pool to stakers in proportion to their reward weight
+    // (their stake, scaled by stake maturity when enabled). Stakers are processed in
+    // `ordered_accounts` order, and the rounding remainder is handed out one unit at a time
+    // in that order. Payouts below MIN_PAYOUT are deferred: they stay in the reward pool as
+    // the account's carryover and are added to its next share. Returns the amount paid out.
+    #[must_use = "a failed distribution leaves balances unchanged"]
+    pub fn distribute_rewards(&mut self, pool: T::Balance) -> Result<T::Balance, &'static str> {
+        let accounts = self.ordered_accounts();
+        self.allocate_rewards(accounts, pool, false)
+    }
+
+    // Pay `pool` like `distribute_rewards`, but to at most `max_accounts` stakers per call so
+    // a large staker set can be paid over several blocks. The first call fixes every share
+    // from the stake at that time; later calls with the same pool resume from the cursor.
+    // Each batch is applied atomically; a failed batch can be retried.
+    #[must_use = "the distribution progress or error must be handled"]
+    pub fn distribute_rewards_bounded(
+        &mut self,
+        pool: T::Balance,
+        max_accounts: usize,
+    ) -> Result<DistributeProgress<T::Balance>, &'static str> {
+        if max_accounts == 0 {
+            return Err("Batch size is zero");
+        }
+
+        let mut distribution = match self.reward_distribution.take() {
+            Some(distribution) if distribution.pool != pool => {
+                self.reward_distribution = Some(distribution);
+                return Err("Another distribution is in progress");
+            }
+            Some(distribution) => distribution,
+            None => {
+                let accounts = self.ordered_accounts();
+                let amounts = self.reward_shares(&accounts, pool)?;
+                RewardDistribution {
+                    pool,
+                    shares: accounts.into_iter().map(|(who, _)| who).zip(amounts).collect(),
+                    cursor: 0,
+                    remaining: pool,
+                    paid: T::Balance::zero(),
+                }
+            }
+        };
+
+        let end = distribution.shares.len().min(distribution.cursor + max_accounts);
+        let batch = &distribution.shares[distribution.cursor..end];
+        let result = checked_sum(batch.iter().map(|(_, share)| share))
+            .ok_or("Overflow")
+            .and_then(|allocated| self.pay_shares(batch, false).map(|paid| (allocated, paid)));
+        let (allocated, paid) = match result {
+            Ok(result) => result,
+            Err(error) => {
+                self.reward_distribution = Some(distribution);
+                return Err(error);
+            }
+        };
+
+        distribution.cursor = end;
+        distribution.remaining = distribution.remaining.checked_sub(&allocated).ok_or("Overflow")?;
+        distribution.paid = distribution.paid.checked_add(&paid).ok_or("Overflow")?;
+
+        if distribution.cursor == distribution.shares.len() {
+            return Ok(DistributeProgress::Complete { paid: distribution.paid });
+        }
+
+        let progress = DistributeProgress::InProgress {
+            cursor: distribution.cursor,
+            remaining_pool: distribution.remaining,
+        };
+        self.reward_distribution = Some(distribution);
+
+        Ok(progress)
+    }
+
+    // Allocate `pool` exactly like `distribute_rewards`, but credit each payout to the
+    // account's pending rewards instead of its free balance. Returns the amount accrued.
+    #[must_use = "a failed accrual leaves balances unchanged"]
+    pub fn accrue_rewards(&mut self, pool: T::Balance) -> Result<T::Balance, &'static str> {
+        let accounts = self.ordered_accounts();
+        self.allocate_rewards(accounts, pool, true)
+    }
+
+    // Get the index of the current reward era; 0 until the first `start_new_era`
+    pub fn current_era(&self) -> u32 {
+        self.current_era
+    }
+
+    // Open a new reward era, snapshotting every staker's stake as its reward basis.
+    // Returns the new era's index.
+    pub fn start_new_era(&mut self) -> u32 {
+        self.current_era += 1;
+        self.era_snapshots.insert(self.current_era, self.ordered_accounts());
+        self.current_era
+    }
+
+    // Pay `pool` like `distribute_rewards`, but weighted by the stake snapshotted when `era`
+    // started rather than live balances, so stake moved since then earns nothing extra.
+    // Each era is paid once; its snapshot is dropped after a successful payout.
+    #[must_use = "a failed distribution leaves balances unchanged"]
+    pub fn distribute_era_rewards(
+        &mut self,
+        era: u32,
+        pool: T::Balance,
+    ) -> Result<T::Balance, &'static str> {
+        let accounts = self.era_snapshots.get(&era).cloned()
+            .ok_or("Unknown era")?;
+
+        let paid = self.allocate_rewards(accounts, pool, false)?;
+        self.era_snapshots.remove(&era);
+
+        Ok(paid)
+    }
+
+    // Queue a command to run later through `process_commands`
+    pub fn enqueue_command(&mut self, command: PalletCommand<T>) {
+        self.command_queue.push(command);
+    }
+
+    // Get the number of commands waiting to be processed
+    pub fn queued_commands(&self) -> usize {
+        self.command_queue.len()
+    }
+
+    // Run up to `max` queued commands in order, returning how many ran and the error of the
+    // command that stopped the batch, if any. A failed command leaves balances unchanged and
+    // stays at the head of the queue, so nothing queued behind it runs before it succeeds.
+    #[must_use = "a failed command stays queued and its error must be handled"]
+    pub fn process_commands(&mut self, max: usize) -> (usize, Option<&'static str>) {
+        let mut processed = 0;
+        while processed < max {
+            let Some(command) = self.command_queue.first().cloned() else {
+                break;
+            };
+
+            let result = match command {
+                PalletCommand::DistributeRewards(pool) => self.distribute_rewards(pool),
+                PalletCommand::AccrueRewards(pool) => self.accrue_rewards(pool),
+            };
+            if let Err(error) = result {
+                return (processed, Some(error));
+            }
+
+            self.command_queue.remove(0);
+            processed += 1;
+        }
+
+        (processed, None)
+    }
+
+    // Allocate `pool` across `accounts`, (account, stake) pairs in processing order
+    fn allocate_rewards(
+        &mut self,
+        accounts: EraSnapshot<T>,
+        pool: T::Balance,
+        to_pending: bool,
+    ) -> Result<T::Balance, &'static str> {
+        let shares = self.reward_shares(&accounts, pool)?;
+        let entries: Vec<(T::AccountId, T::Balance)> = accounts
+            .into_iter()
+            .map(|(who, _)| who)
+            .zip(shares)
+            .collect();
+
+        self.pay_shares(&entries, to_pending)
+    }
+
+    // Split `pool` across `accounts` by reward weight, handing the rounding remainder out
+    // one unit at a time in account order. Empty if there is no weight at all.
+    fn reward_shares(
+        &self,
+        accounts: &[(T::AccountId, T::Balance)],
+        pool: T::Balance,
+    ) -> Result<Vec<T::Balance>, &'static str> {
+        let carried = checked_sum(self.carryover.values()).ok_or("Overflow")?;
+        let available = self.reward_pool.checked_sub(&carried)
+            .ok_or("Insufficient reward pool")?;
+        if pool > available {
+            return Err("Insufficient reward pool");
+        }
+
+        let mut weights = Vec::new();
+        for (who, stake) in accounts.iter() {
+            weights.push(self.reward_weight(who, *stake).ok_or("Overflow")?);
+        }
+
+        let total_weight = checked_sum(weights.iter()).ok_or("Overflow")?;
+        if total_weight.is_zero() {
+            return Ok(Vec::new());
+        }
+
+        let mut shares = Vec::new();
+        for weight in weights.iter() {
+            let share = pool.checked_mul(weight)
+                .ok_or("Overflow")?
+                .checked_div(&total_weight)
+                .ok_or("Overflow")?;
+            shares.push(share);
+        }
+
+        // Flooring loses less than one unit per staker, so the remainder fits in one pass
+        let one = T::Balance::from(1u32);
+        let mut remainder = pool.checked_sub(&checked_sum(shares.iter()).ok_or("Overflow")?)
+            .ok_or("Overflow")?;
+        for share in shares.iter_mut() {
+            if remainder.is_zero() {
+                break;
+            }
+            *share = share.checked_add(&one).ok_or("Overflow")?;
+            remainder = remainder.checked_sub(&one).ok_or("Overflow")?;
+        }
+
+        Ok(shares)
+    }
+
+    // Credit each (account, share) plus its carryover, deferring amounts below MIN_PAYOUT
+    // as carryover. Applied atomically; returns the amount paid out.
+    fn pay_shares(
+        &mut self,
+        entries: &[(T::AccountId, T::Balance)],
+        to_pending: bool,
+    ) -> Result<T::Balance, &'static str> {
+        let mut payouts = Vec::new();
+        let mut deferred = Vec::new();
+        let mut paid_out = Vec::new();
+        for (who, share) in entries.iter() {
+            let owed = self.carryover.get(who).copied()
+                .unwrap_or(T::Balance::zero())
+                .checked_add(share)
+                .ok_or("Overflow")?;
+
+            if owed < T::MIN_PAYOUT {
+                deferred.push((who.clone(), owed));
+                continue;
+            }
+
+            let current = if to_pending {
+                self.get_pending_rewards(who.clone())
+            } else {
+                self.get_free_balance_ref(who)
+            };
+            let credited = current.checked_add(&owed)
+                .ok_or("Overflow")?;
+
+            paid_out.push(owed);
+            payouts.push((who.clone(), credited));
+        }
+
+        let paid = checked_sum(paid_out.iter()).ok_or("Overflow")?;
+        let new_pool = self.reward_pool.checked_sub(&paid)
+            .ok_or("Insufficient reward pool")?;
+
+        for (who, _) in payouts.iter() {
+            self.carryover.remove(who);
+        }
+        self.carryover.extend(deferred);
+        if to_pending {
+            self.pending_rewards.extend(payouts);
+        } else {
+            self.free_balances.extend(payouts);
+        }
+        self.reward_pool = new_pool;
+
+        Ok(paid)
+    }
+
+    // Get an account's reward weight: its stake, scaled by the maturity bonus when enabled
+    fn reward_weight(&self, who: &T::AccountId, stake: T::Balance) -> Option<T::Balance> {
+        if T::MATURITY_BLOCKS_PER_PERCENT == 0 {
+            return Some(stake);
+        }
+
+        let blocks: u64 = self.maturity(who, self.current_block).into();
+        let bonus = (blocks / T::MATURITY_BLOCKS_PER_PERCENT as u64)
+            .min(T::MAX_MATURITY_BONUS_PERCENT as u64) as u32;
+
+        stake.checked_mul(&T::Balance::from(100 + bonus))
+    }
+
+    // Move all of an account's pending rewards to its free balance, returning the amount claimed
+    #[must_use = "a failed claim leaves balances unchanged"]
+    pub fn claim_rewards(&mut self, who: T::AccountId) -> Result<T::Balance, &'static str> {
+        if self.frozen {
+            return Err("Staking is frozen");
+        }
+
+        let pending = self.get_pending_rewards(who.clone());
+        let new_free = self.get_free_balance_ref(&who).checked_add(&pending)
+            .ok_or("Overflow")?;
+
+        self.pending_rewards.remove(&who);
+        self.free_balances.insert(who, new_free);
+
+        Ok(pending)
+    }
+
+    // Get the rewards accrued to an account but not yet realized
+    pub fn get_pending_rewards(&self, who: T::AccountId) -> T::Balance {
+        self.pending_rewards.get(&who).copied().unwrap_or(T::Balance::zero())
+    }
+
+    // Get the deferred reward owed to an account
+    pub fn get_carryover(&self, who: T::AccountId) -> T::Balance {
+        self.carryover.get(&who).copied().unwrap_or(T::Balance::zero())
+    }
+
+    // Get the minted rewards not yet distributed
+    pub fn reward_pool(&self) -> T::Balance {
+        self.reward_pool
+    }
+
+    // Get the total staked across all accounts, or None on overflow
+    pub fn total_staked(&self) -> Option<T::Balance> {
+        checked_sum(self.staked_balances.values())
+    }
+
+    // Get all tokens held by this pallet (free, staked, reserved, pending rewards, unbonding
+    // and the reward pool), or None on overflow
+    pub fn total_issuance(&self) -> Option<T::Balance> {
+        checked_sum(
+            self.free_balances
+                .values()
+                .chain(self.staked_balances.values())
+                .chain(self.reserved_balances.values())
+                .chain(self.pending_rewards.values())
+                .chain(self.unbonding.values().flatten().map(|(amount, _)| amount))
+                .chain(std::iter::once(&self.reward_pool)),
+        )
+    }
+
+    // Get aggregate balance statistics in one pass over each map, or None on overflow
+    pub fn stats(&self) -> Option<StakingStats<T>> {
+        let mut accounts = HashSet::new();
+        let mut staker_count = 0;
+
+        let mut sum = |map: &HashMap<T::AccountId, T::Balance>, is_stake: bool| {
+            let mut total = T::Balance::zero();
+            for (who, amount) in map.iter() {
+                if amount.is_zero() {
+                    continue;
+                }
+                total = total.checked_add(amount)?;
+                accounts.insert(who.clone());
+                if is_stake {
+                    staker_count += 1;
+                }
+            }
+            Some(total)
+        };
+
+        let total_free = sum(&self.free_balances, false)?;
+        let total_staked = sum(&self.staked_balances, true)?;
+        let total_reserved = sum(&self.reserved_balances, false)?;
+
+        Some(StakingStats {
+            total_free,
+            total_staked,
+            total_reserved,
+            account_count: accounts.len(),
+            staker_count,
+        })
+    }
+
+    // Get the account with the largest free balance, the lowest account id on ties.
+    // None if no account holds a free balance.
+    pub fn richest_account(&self) -> Option<(T::AccountId, T::Balance)> {
+        Self::largest_entry(&self.free_balances)
+    }
+
+    // Get the account with the largest stake, the lowest account id on ties.
+    // None if nobody stakes.
+    pub fn largest_staker(&self) -> Option<(T::AccountId, T::Balance)> {
+        Self::largest_entry(&self.staked_balances)
+    }
+
+    fn largest_entry(map: &HashMap<T::AccountId, T::Balance>) -> Option<(T::AccountId, T::Balance)> {
+        map.iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .max_by(|(a, a_amount), (b, b_amount)| a_amount.cmp(b_amount).then_with(|| b.cmp(a)))
+            .map(|(who, amount)| (who.clone(), *amount))
+    }
+
+    // Check whether an account holds a nonzero free balance entry
+    pub fn has_free_balance(&self, who: T::AccountId) -> bool {
+        self.free_balances.get(&who).is_some_and(|amount| !amount.is_zero())
+    }
+
+    // Check whether an account holds a nonzero staked balance entry
+    pub fn has_staked_balance(&self, who: T::AccountId) -> bool {
+        self.staked_balances.get(&who).is_some_and(|amount| !amount.is_zero())
+    }
+
+    // Remove every trace of an account, returning the balance removed with it (free, staked,
+    // reserved, unbonding and pending rewards) for the caller to burn or redirect. Deferred
+    // carryover stays in the reward pool for the remaining stakers, as does the account's
+    // unpaid share of an in-progress bounded distribution; later era payouts leave it out
+    // too. A killed validator's nominations are dropped too.
+    #[must_use = "the removed balance is gone from issuance unless redirected"]
+    pub fn kill_account(&mut self, who: &T::AccountId) -> Result<T::Balance, &'static str> {
+        let removed = checked_sum(
+            [
+                &self.free_balances,
+                &self.staked_balances,
+                &self.reserved_balances,
+                &self.pending_rewards,
+            ]
+            .into_iter()
+            .filter_map(|balances| balances.get(who))
+            .chain(self.unbonding.get(who).into_iter().flatten().map(|(amount, _)| amount)),
+        )
+        .ok_or("Overflow")?;
+
+        if let Some(distribution) = self.reward_distribution.as_mut() {
+            if let Some(index) = distribution.shares.iter().position(|(account, _)| account == who) {
+                if index < distribution.cursor {
+                    distribution.cursor -= 1;
+                } else {
+                    let share = distribution.shares[index].1;
+                    distribution.remaining = distribution.remaining.checked_sub(&share)
+                        .ok_or("Overflow")?;
+                }
+                distribution.shares.remove(index);
+            }
+        }
+        for accounts in self.era_snapshots.values_mut() {
+            accounts.retain(|(account, _)| account != who);
+        }
+
+        self.free_balances.remove(who);
+        self.staked_balances.remove(who);
+        self.reserved_balances.remove(who);
+        self.pending_rewards.remove(who);
+        self.carryover.remove(who);
+        self.stake_since.remove(who);
+        self.last_full_unstake.remove(who);
+        self.conviction_locks.remove(who);
+        self.unbonding.remove(who);
+        self.vote_locks.retain(|(voter, _), _| voter != who);
+        self.validators.remove(who);
+        self.nominations.retain(|(nominator, validator), _| nominator != who && validator != who);
+
+        Ok(removed)
+    }
+
+    // Drop zero entries from the balance maps; getters already treat missing accounts as zero.
+    // Run before persisting or serializing state so empty accounts take no space.
+    pub fn compact(&mut self) {
+        self.free_balances.retain(|_, balance| !balance.is_zero());
+        self.staked_balances.retain(|_, balance| !balance.is_zero());
+        self.reserved_balances.retain(|_, balance| !balance.is_zero());
+    }
+
+    // Get free balance for an account
+    // Prefer `get_free_balance_ref` when the caller only holds a reference to the account id
+    pub fn get_free_balance(&self, who: T::AccountId) -> T::Balance {
+        self.get_free_balance_ref(&who)
+    }
+
+    // Get free balance for an account without taking ownership of its id
+    pub fn get_free_balance_ref(&self, who: &T::AccountId) -> T::Balance {
+        self.free_balances.get(who).copied().unwrap_or(T::Balance::zero())
+    }
+
+    // Get staked balance for an account
+    // Prefer `get_staked_balance_ref` when the caller only holds a reference to the account id
+    pub fn get_staked_balance(&self, who: T::AccountId) -> T::Balance {
+        self.get_staked_balance_ref(&who)
+    }
+
+    // Get staked balance for an account without taking ownership of its id
+    pub fn get_staked_balance_ref(&self, who: &T::AccountId) -> T::Balance {
+        self.staked_balances.get(who).copied().unwrap_or(T::Balance::zero())
+    }
+
+    // Get an account's unbonding chunks in unbonding order, e.g. to display a withdrawal
+    // schedule
+    pub fn unbonding_chunks(&self, who: &T::AccountId) -> &[UnbondingChunk<T>] {
+        self.unbonding.get(who).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    // Get the total an account has unbonding, matured or not.
+    // `unbond` refuses chunks that would overflow this sum.
+    pub fn unbonding_total(&self, who: &T::AccountId) -> T::Balance {
+        checked_sum(self.unbonding_chunks(who).iter().map(|(amount, _)| amount))
+            .unwrap_or(T::Balance::zero())
+    }
+
+    // Get the block at which an account's soonest-maturing chunk unlocks, if any
+    pub fn next_unlock(&self, who: &T::AccountId) -> Option<T::BlockNumber> {
+        self.unbonding_chunks(who).iter()
+            .map(|(_, unlock_at)| *unlock_at)
+            .reduce(|soonest, unlock_at| if unlock_at < soonest { unlock_at } else { soonest })
+    }
+
+    // Get reserved balance for an account
+    pub fn get_reserved_balance(&self, who: T::AccountId) -> T::Balance {
+        self.reserved_balances.get(&who).copied().unwrap_or(T::Balance::zero())
+    }
+}
+
+impl<T: StakingConfig> StakingPallet<T>
+where
+    T::Balance: Hash,
+{
+    // Commit to the free, staked and reserved balances: equal balances give equal roots.
+    // Entries are hashed in account order and zero balances are skipped, so an account
+    // holding zero and an absent account commit the same. The root is the SHA-256 of that
+    // encoding, with every integer written little-endian.
+    pub fn state_root(&self) -> [u8; 32] {
+        let sections = [&self.free_balances, &self.staked_balances, &self.reserved_balances];
+        let mut hasher = Sha256Writer(Sha256::new());
+
+        for (section, balances) in sections.iter().enumerate() {
+            let mut entries: Vec<_> = balances
+                .iter()
+                .filter(|(_, balance)| !balance.is_zero())
+                .collect();
+            entries.sort_by_key(|(who, _)| *who);
+
+            section.hash(&mut hasher);
+            entries.len().hash(&mut hasher);
+            for (who, balance) in entries {
+                who.hash(&mut hasher);
+                balance.hash(&mut hasher);
+            }
+        }
+
+        hasher.0.finalize().into()
+    }
+}
+
+// Feeds `Hash` output into SHA-256 with integers written little-endian and `usize` widened
+// to 64 bits, so the digest depends on neither std's hasher nor the platform
+struct Sha256Writer(Sha256);
+
+impl Hasher for Sha256Writer {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.0.update([n]);
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.0.update(n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0.update(n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0.update(n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.0.update(n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    // Only the 32-byte digest is used; this is its first eight bytes
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().unwrap_or_default())
+    }
+}
+
+impl<T: StakingConfig> StakingPallet<T>
+where
+    T::Balance: TryInto<u128>,
+{
+    // Pick a block author among the validators, with probability proportional to each one's
+    // `total_backing`. The same seed (e.g. a block hash) always picks the same author.
+    // None if there is no backed validator or the backing does not fit in a u128.
+    pub fn select_author(&self, seed: u64) -> Option<T::AccountId> {
+        let mut weights = Vec::with_capacity(self.validators.len());
+        let mut total = 0u128;
+        for validator in self.validators.iter() {
+            let weight = balance_as_u128(self.total_backing(validator)?)?;
+            total = total.checked_add(weight)?;
+            weights.push((validator, weight));
+        }
+
+        if total == 0 {
+            return None;
+        }
+
+        let mut prng = Prng::new(seed);
+        let draw = (u128::from(prng.next_u64()) << 64 | u128::from(prng.next_u64())) % total;
+
+        let mut cumulative = 0u128;
+        weights.into_iter().find_map(|(validator, weight)| {
+            cumulative += weight;
+            (draw < cumulative).then(|| validator.clone())
+        })
+    }
+}
+
+#[cfg(feature = "codec")]
+impl<T: StakingConfig> StakingPallet<T>
+where
+    T::AccountId: Encode + Decode,
+    T::Balance: Encode + Decode,
+{
+    // Encode the free, staked and reserved balance maps as `compact` would leave them, so
+    // states that differ only in zero entries encode alike. Locks, rewards, nominations and
+    // the rest of the pallet's bookkeeping are not included.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for balances in [&self.free_balances, &self.staked_balances, &self.reserved_balances] {
+            let nonzero: HashMap<T::AccountId, T::Balance> = balances
+                .iter()
+                .filter(|(_, balance)| !balance.is_zero())
+                .map(|(who, balance)| (who.clone(), *balance))
+                .collect();
+            encode_map(&nonzero, &mut out);
+        }
+
+        out
+    }
+
+    // Rebuild a pallet from `encode` output; everything that was not encoded starts empty
+    pub fn decode(bytes: &[u8]) -> Result<Self, &'static str> {
+        let mut input = bytes;
+        let mut pallet = Self::new();
+        pallet.free_balances = decode_map(&mut input)?;
+        pallet.staked_balances = decode_map(&mut input)?;
+        pallet.reserved_balances = decode_map(&mut input)?;
+
+        if !input.is_empty() {
+            return Err("Trailing bytes");
+        }
+
+        Ok(pallet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Runtime;
+    use std::cell::Cell;
+
+    thread_local! {
+        static STAKE_CALLS: Cell<u32> = const { Cell::new(0) };
+        static UNSTAKE_CALLS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    struct CountingHooks;
+
+    impl StakingHooks<HookedRuntime> for CountingHooks {
+        fn on_stake(_who: &u64, _amount: u64) {
+            STAKE_CALLS.with(|calls| calls.set(calls.get() + 1));
+        }
+
+        fn on_unstake(_who: &u64, _amount: u64) {
+            UNSTAKE_CALLS.with(|calls| calls.set(calls.get() + 1));
+        }
+    }
+
+    struct HookedRuntime;
+
+    impl SystemConfig for HookedRuntime {
+        type AccountId = u64;
+        type BlockNumber = u64;
+    }
+
+    impl StakingConfig for HookedRuntime {
+        type Balance = u64;
+        type Hooks = CountingHooks;
+        const MAX_SUPPLY: u64 = u64::MAX;
+        const MIN_PAYOUT: u64 = 0;
+        const UNBONDING_PERIOD: u64 = 0;
+        const RESTAKE_COOLDOWN: u64 = 0;
+    }
+
+    #[test]
+    fn test_staking_should_work() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+
+        // Set initial balance
+        staking.set_balance(alice, 1000).unwrap();
+
+        // Check free balance
+        assert_eq!(staking.get_free_balance(alice), 1000u64);
+        assert_eq!(staking.get_staked_balance(alice), 0u64);
+
+        // Stake tokens
+        let result = staking.stake(alice, 400);
+        assert!(result.is_ok());
+
+        // Check balances after staking
+        assert_eq!(staking.get_free_balance(alice), 600u64);
+        assert_eq!(staking.get_staked_balance(alice), 400u64);
+
+        // Unstake tokens
+        let result = staking.unstake(alice, 100);
+        assert!(result.is_ok());
+
+        // Check balances after unstaking
+        assert_eq!(staking.get_free_balance(alice), 700u64);
+        assert_eq!(staking.get_staked_balance(alice), 300u64);
+    }
+
+    #[test]
+    fn test_staking_errors() {
+        let bob = 2u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+
+        // Set initial balance
+        staking.set_balance(bob, 500).unwrap();
+
+        // Try to stake more than available
+        let result = staking.stake(bob, 600);
+        assert!(result.is_err());
+
+        // Stake valid amount
+        let result = staking.stake(bob, 300);
+        assert!(result.is_ok());
+
+        // Try to unstake more than staked
+        let result = staking.unstake(bob, 400);
+        assert!(result.is_err());
+    }
+
+    // Results of mutating calls are `#[must_use]`: propagate them with `?` or inspect them
+    #[test]
+    fn test_staking_results_are_handled() {
+        fn stake_then_unstake(
+            staking: &mut StakingPallet<Runtime>,
+            who: u64,
+        ) -> Result<(), &'static str> {
+            staking.stake(who, 200)?;
+            staking.unstake(who, 50)?;
+            Ok(())
+        }
+
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 100).unwrap();
+
+        // Not enough balance: the error surfaces at the caller instead of being dropped
+        assert_eq!(stake_then_unstake(&mut staking, alice), Err("Insufficient balance"));
+
+        staking.set_balance(alice, 200).unwrap();
+        assert_eq!(stake_then_unstake(&mut staking, alice), Ok(()));
+        assert_eq!(staking.get_staked_balance(alice), 150u64);
+    }
+
+    #[test]
+    fn test_stake_and_unstake_return_free_balance() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 1000).unwrap();
+
+        let free = staking.stake(alice, 250).unwrap();
+        assert_eq!(free, 750u64);
+        assert_eq!(free, staking.get_free_balance(alice));
+
+        let free = staking.unstake(alice, 100).unwrap();
+        assert_eq!(free, 850u64);
+        assert_eq!(free, staking.get_free_balance(alice));
+    }
+
+    #[test]
+    fn test_reserve_and_unreserve() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 100).unwrap();
+
+        assert_eq!(staking.reserve(alice, 150), Err("Insufficient balance"));
+
+        staking.reserve(alice, 60).unwrap();
+        assert_eq!(staking.get_free_balance(alice), 40u64);
+        assert_eq!(staking.get_reserved_balance(alice), 60u64);
+
+        assert_eq!(staking.unreserve(alice, 70), Err("Insufficient reserved balance"));
+
+        staking.unreserve(alice, 60).unwrap();
+        assert_eq!(staking.get_free_balance(alice), 100u64);
+        assert_eq!(staking.get_reserved_balance(alice), 0u64);
+    }
+
+    #[test]
+    fn test_hooks_fire_once_per_successful_operation() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<HookedRuntime>::new();
+        staking.set_balance(alice, 100).unwrap();
+
+        staking.stake(alice, 60).unwrap();
+        assert!(staking.stake(alice, 60).is_err());
+        assert_eq!(STAKE_CALLS.with(Cell::get), 1);
+
+        staking.unstake(alice, 10).unwrap();
+        assert!(staking.unstake(alice, 100).is_err());
+        assert_eq!(UNSTAKE_CALLS.with(Cell::get), 1);
+    }
+
+    #[test]
+    fn test_block_rewards_decrease_towards_max_supply() {
+        let mut staking = StakingPallet::<Runtime>::new();
+
+        // 1% of the remaining 1_000_000 mintable supply per block
+        assert_eq!(staking.mint_block_reward(1), 10_000u64);
+        assert_eq!(staking.mint_block_reward(2), 9_900u64);
+        assert_eq!(staking.mint_block_reward(3), 9_801u64);
+        assert_eq!(staking.reward_pool(), 29_701u64);
+
+        // A block is only minted once
+        assert_eq!(staking.mint_block_reward(3), 0u64);
+        assert_eq!(staking.total_issuance(), Some(29_701u64));
+    }
+
+    #[test]
+    fn test_minting_stops_at_max_supply() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 1_000_000).unwrap();
+
+        assert_eq!(staking.mint_block_reward(1), 0u64);
+        assert_eq!(staking.reward_pool(), 0u64);
+        assert_eq!(staking.total_issuance(), Some(1_000_000u64));
+    }
+
+    #[test]
+    fn test_distribute_rewards_pro_rata() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 300).unwrap();
+        staking.set_balance(bob, 100).unwrap();
+        staking.stake(alice, 300).unwrap();
+        staking.stake(bob, 100).unwrap();
+
+        assert_eq!(staking.mint_block_reward(1), 9_996u64);
+        assert_eq!(staking.distribute_rewards(20_000), Err("Insufficient reward pool"));
+
+        assert_eq!(staking.distribute_rewards(1_000), Ok(1_000u64));
+        assert_eq!(staking.get_free_balance(alice), 750u64);
+        assert_eq!(staking.get_free_balance(bob), 250u64);
+        assert_eq!(staking.reward_pool(), 8_996u64);
+    }
+
+    #[test]
+    fn test_bounded_distribution_matches_unbounded() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        for who in 1..=5u64 {
+            staking.set_balance(who, who * 100).unwrap();
+            staking.stake(who, who * 100).unwrap();
+        }
+        assert!(staking.mint_block_reward(1) > 1_001);
+        let mut unbounded = staking.clone();
+        assert_eq!(unbounded.distribute_rewards(1_001), Ok(1_001));
+
+        assert_eq!(staking.distribute_rewards_bounded(1_001, 0), Err("Batch size is zero"));
+        let first = staking.distribute_rewards_bounded(1_001, 2).unwrap();
+        assert!(matches!(first, DistributeProgress::InProgress { cursor: 2, .. }));
+        assert_eq!(
+            staking.distribute_rewards_bounded(500, 2),
+            Err("Another distribution is in progress")
+        );
+        let second = staking.distribute_rewards_bounded(1_001, 2).unwrap();
+        assert!(matches!(second, DistributeProgress::InProgress { cursor: 4, .. }));
+        assert_eq!(
+            staking.distribute_rewards_bounded(1_001, 2),
+            Ok(DistributeProgress::Complete { paid: 1_001 })
+        );
+
+        for who in 1..=5u64 {
+            assert_eq!(staking.get_free_balance(who), unbounded.get_free_balance(who));
+        }
+        assert_eq!(staking.reward_pool(), unbounded.reward_pool());
+
+        // Once complete, the next call starts a fresh distribution
+        assert!(matches!(
+            staking.distribute_rewards_bounded(100, 2),
+            Ok(DistributeProgress::InProgress { cursor: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_richest_account_and_largest_staker() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        assert_eq!(staking.richest_account(), None);
+        assert_eq!(staking.largest_staker(), None);
+
+        for (who, free) in [(4u64, 700), (2, 900), (3, 900), (1, 500)] {
+            staking.set_balance(who, free).unwrap();
+        }
+        assert_eq!(staking.largest_staker(), None);
+        // Accounts 2 and 3 tie; the lower id wins
+        assert_eq!(staking.richest_account(), Some((2, 900)));
+
+        staking.stake(4, 600).unwrap();
+        staking.stake(3, 200).unwrap();
+        staking.stake(1, 500).unwrap();
+        assert_eq!(staking.largest_staker(), Some((4, 600)));
+        assert_eq!(staking.richest_account(), Some((2, 900)));
+
+        staking.stake(2, 800).unwrap();
+        assert_eq!(staking.largest_staker(), Some((2, 800)));
+        assert_eq!(staking.richest_account(), Some((3, 700)));
+    }
+
+    #[test]
+    fn test_ensure_account_materializes_entry() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        assert!(!staking.free_balances.contains_key(&alice));
+
+        assert_eq!(staking.ensure_account(alice).map(|free| *free), Ok(0));
+        assert!(staking.free_balances.contains_key(&alice));
+
+        *staking.ensure_account(alice).unwrap() += 50;
+        assert!(staking.has_free_balance(alice));
+        // An existing entry is returned as is
+        assert_eq!(staking.ensure_account(alice).map(|free| *free), Ok(50));
+    }
+
+    #[test]
+    fn test_min_stake_parameter() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 1000).unwrap();
+        staking.set_parameter(StakingParameter::MinStake(100));
+
+        assert_eq!(staking.stake(alice, 50), Err("Stake below minimum"));
+        assert!(staking.stake(alice, 100).is_ok());
+
+        // The minimum applies to the total stake, so top-ups of any size are allowed
+        assert!(staking.stake(alice, 1).is_ok());
+    }
+
+    #[test]
+    fn test_dust_rewards_carry_over_until_min_payout() {
+        let whale = 1u64;
+        let minnow = 2u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(whale, 990).unwrap();
+        staking.set_balance(minnow, 10).unwrap();
+        staking.stake(whale, 990).unwrap();
+        staking.stake(minnow, 10).unwrap();
+        staking.mint_block_reward(1);
+
+        // The minnow's 5 is below MIN_PAYOUT = 10 and is deferred
+        assert_eq!(staking.distribute_rewards(500), Ok(495u64));
+        assert_eq!(staking.get_free_balance(minnow), 0u64);
+        assert_eq!(staking.get_carryover(minnow), 5u64);
+
+        // The deferred amount is still owed, so it cannot be distributed again
+        let pool = staking.reward_pool();
+        assert_eq!(staking.distribute_rewards(pool), Err("Insufficient reward pool"));
+
+        // Next round the carried 5 plus a new 5 clears the threshold
+        assert_eq!(staking.distribute_rewards(500), Ok(505u64));
+        assert_eq!(staking.get_free_balance(minnow), 10u64);
+        assert_eq!(staking.get_carryover(minnow), 0u64);
+        assert_eq!(staking.get_free_balance(whale), 990u64);
+    }
+
+    #[test]
+    fn test_reward_processing_order_is_deterministic() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        for (who, stake) in [(3u64, 100u64), (1, 200), (2, 100)] {
+            staking.set_balance(who, stake).unwrap();
+            staking.stake(who, stake).unwrap();
+        }
+
+        // Largest stake first, then the tie between 2 and 3 broken by account id
+        assert_eq!(staking.ordered_accounts(), vec![(1, 200), (2, 100), (3, 100)]);
+
+        // 101 splits into 50.5 / 25.25 / 25.25: the leftover unit goes to the first account
+        staking.mint_block_reward(1);
+        assert_eq!(staking.distribute_rewards(101), Ok(101u64));
+        assert_eq!(staking.get_free_balance(1), 51u64);
+        assert_eq!(staking.get_free_balance(2), 25u64);
+        assert_eq!(staking.get_free_balance(3), 25u64);
+    }
+
+    #[test]
+    fn test_stake_with_referral_credits_referrer() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 1000).unwrap();
+        staking.set_balance(bob, 10).unwrap();
+
+        // REFERRAL_BONUS_PERCENT = 5
+        assert_eq!(staking.stake_with_referral(alice, 400, bob), Ok(600u64));
+        assert_eq!(staking.get_staked_balance(alice), 400u64);
+        assert_eq!(staking.get_free_balance(bob), 30u64);
+        assert_eq!(staking.total_issuance(), Some(1030u64));
+
+        // A bonus that rounds down to zero leaves no entry behind for the referrer
+        let carol = 3u64;
+        assert_eq!(staking.stake_with_referral(alice, 10, carol), Ok(590u64));
+        assert!(!staking.free_balances.contains_key(&carol));
+    }
+
+    #[test]
+    fn test_stake_with_self_referral_rejected() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 1000).unwrap();
+
+        assert_eq!(
+            staking.stake_with_referral(alice, 400, alice),
+            Err("Cannot refer yourself")
+        );
+        assert_eq!(staking.get_staked_balance(alice), 0u64);
+        assert_eq!(staking.get_free_balance(alice), 1000u64);
+    }
+
+    #[test]
+    fn test_stats_snapshot() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(1, 1000).unwrap();
+        staking.set_balance(2, 500).unwrap();
+        staking.set_balance(3, 0).unwrap();
+        staking.set_balance(4, 50).unwrap();
+        staking.stake(1, 400).unwrap();
+        staking.stake(2, 500).unwrap();
+        staking.reserve(4, 20).unwrap();
+
+        let stats = staking.stats().unwrap();
+        assert_eq!(stats.total_free, 630u64);
+        assert_eq!(stats.total_staked, 900u64);
+        assert_eq!(stats.total_reserved, 20u64);
+        // Account 3 only holds a zero balance
+        assert_eq!(stats.account_count, 3);
+        assert_eq!(stats.staker_count, 2);
+    }
+
+    #[test]
+    fn test_partial_unstake_realizes_pro_rata_rewards() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 400).unwrap();
+        staking.stake(alice, 400).unwrap();
+        staking.mint_block_reward(1);
+
+        assert_eq!(staking.accrue_rewards(101), Ok(101u64));
+        assert_eq!(staking.get_free_balance(alice), 0u64);
+        assert_eq!(staking.get_pending_rewards(alice), 101u64);
+
+        // Unstaking half realizes half the pending reward, rounded down
+        assert_eq!(staking.unstake(alice, 200), Ok(250u64));
+        assert_eq!(staking.get_pending_rewards(alice), 51u64);
+
+        assert_eq!(staking.claim_rewards(alice), Ok(51u64));
+        assert_eq!(staking.get_free_balance(alice), 301u64);
+        assert_eq!(staking.get_pending_rewards(alice), 0u64);
+    }
+
+    #[test]
+    fn test_unstake_realizes_rewards_without_overflow() {
+        let (alice, bob) = (1u64, 2u64);
+        let mut staking = StakingPallet::<Runtime>::new();
+        let large = 1u64 << 40;
+        staking.set_balance(alice, large).unwrap();
+        staking.stake(alice, large).unwrap();
+        staking.pending_rewards.insert(alice, large + 1);
+
+        // pending * amount is far past u64::MAX, but the share itself fits
+        assert_eq!(staking.unstake(alice, large / 2), Ok(large));
+        assert_eq!(staking.get_pending_rewards(alice), large / 2 + 1);
+
+        // With nothing staked there is no share to realize, and the rewards stay pending
+        staking.pending_rewards.insert(bob, 50);
+        assert_eq!(staking.unstake(bob, 0), Ok(0u64));
+        assert_eq!(staking.get_pending_rewards(bob), 50u64);
+    }
+
+    #[test]
+    fn test_balance_membership() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+
+        assert!(!staking.has_free_balance(alice));
+        staking.set_balance(alice, 100).unwrap();
+        assert!(staking.has_free_balance(alice));
+        assert!(!staking.has_staked_balance(alice));
+
+        staking.stake(alice, 100).unwrap();
+        assert!(staking.has_staked_balance(alice));
+        // Staking everything leaves a zero free entry, which does not count
+        assert!(!staking.has_free_balance(alice));
+
+        // Setting a balance to zero reaps the entry, while the getter still returns zero
+        staking.set_balance(bob, 50).unwrap();
+        staking.set_balance(bob, 0).unwrap();
+        assert!(!staking.has_free_balance(bob));
+        assert!(!staking.free_balances.contains_key(&bob));
+        assert_eq!(staking.get_free_balance(bob), 0u64);
+    }
+
+    #[test]
+    fn test_maturity_tracks_first_stake_and_resets() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 1000).unwrap();
+
+        staking.on_initialize(10);
+        staking.stake(alice, 100).unwrap();
+        staking.on_initialize(30);
+        staking.stake(alice, 100).unwrap();
+        assert_eq!(staking.maturity(&alice, 40), 30);
+
+        // Partial unstakes keep maturity, a full unstake resets it
+        staking.unstake(alice, 50).unwrap();
+        assert_eq!(staking.maturity(&alice, 40), 30);
+        staking.unstake(alice, 150).unwrap();
+        assert_eq!(staking.maturity(&alice, 40), 0);
+    }
+
+    #[test]
+    fn test_older_stake_earns_more() {
+        let old = 1u64;
+        let new = 2u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(old, 100).unwrap();
+        staking.set_balance(new, 100).unwrap();
+
+        staking.stake(old, 100).unwrap();
+        staking.on_initialize(50);
+        staking.stake(new, 100).unwrap();
+        staking.on_initialize(100);
+        staking.mint_block_reward(100);
+
+        // Weights 200% vs 150% of equal stakes
+        assert_eq!(staking.distribute_rewards(350), Ok(350u64));
+        assert_eq!(staking.get_free_balance(old), 200u64);
+        assert_eq!(staking.get_free_balance(new), 150u64);
+    }
+
+    #[test]
+    fn test_balance_getters_by_reference() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 400).unwrap();
+
+        assert_eq!(staking.get_free_balance_ref(&alice), 600);
+        assert_eq!(staking.get_staked_balance_ref(&alice), 400);
+        assert_eq!(staking.get_free_balance_ref(&2), 0);
+        assert_eq!(staking.get_staked_balance_ref(&2), 0);
+        assert_eq!(staking.get_free_balance_ref(&alice), staking.get_free_balance(alice));
+    }
+
+    // Locks stake for a two-step operation whose second step fails
+    fn unstake_under_guard(staking: &mut StakingPallet<Runtime>, who: u64) -> Result<(), &'static str> {
+        let mut guard = staking.lock_stake_scoped(who, 300)?;
+        assert_eq!(guard.locked_for_voting(&who), 300u64);
+        guard.unstake(who, 200)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_stake_guard_releases_on_early_return() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(alice, 400).unwrap();
+        staking.stake(alice, 400).unwrap();
+        assert_eq!(
+            staking.lock_stake_scoped(alice, 500).err(),
+            Some("Insufficient staked balance")
+        );
+
+        assert_eq!(unstake_under_guard(&mut staking, alice), Err("Stake locked by active vote"));
+        assert_eq!(staking.locked_for_voting(&alice), 0u64);
+        assert!(staking.unstake(alice, 200).is_ok());
+    }
+
+    #[test]
+    fn test_conviction_lock_expires_at_block() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 500).unwrap();
+
+        assert_eq!(staking.lock_until(alice, 600, 10), Err("Insufficient staked balance"));
+        staking.lock_until(alice, 300, 10).unwrap();
+        assert_eq!(staking.locked_for_voting(&alice), 300u64);
+
+        // Not yet expired: the lock stays and still blocks unstaking
+        assert_eq!(staking.process_vote_locks(9), 0);
+        assert_eq!(staking.unstake(alice, 300), Err("Stake locked by active vote"));
+
+        assert_eq!(staking.process_vote_locks(10), 1);
+        assert_eq!(staking.locked_for_voting(&alice), 0u64);
+        assert!(staking.unstake(alice, 500).is_ok());
+    }
+
+    #[test]
+    fn test_try_stake_reports_shortfall() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 500).unwrap();
+
+        assert_eq!(staking.try_stake(alice, 600), Err(100));
+        assert_eq!(staking.get_free_balance(alice), 500u64);
+
+        assert_eq!(staking.try_stake(alice, 500), Ok(()));
+        assert_eq!(staking.get_staked_balance(alice), 500u64);
+    }
+
+    #[test]
+    fn test_compact_drops_zero_entries() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let (alice, bob, carol) = (1u64, 2u64, 3u64);
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 1_000).unwrap();
+        staking.set_balance(bob, 300).unwrap();
+        staking.reserve(bob, 300).unwrap();
+        staking.unreserve(bob, 300).unwrap();
+        staking.staked_balances.insert(carol, 0);
+        staking.reserved_balances.insert(carol, 0);
+
+        staking.compact();
+
+        assert!(!staking.free_balances.contains_key(&alice));
+        assert_eq!(staking.staked_balances.keys().collect::<Vec<_>>(), vec![&alice]);
+        assert_eq!(staking.free_balances.keys().collect::<Vec<_>>(), vec![&bob]);
+        assert!(staking.reserved_balances.is_empty());
+
+        assert_eq!(staking.get_free_balance(alice), 0u64);
+        assert_eq!(staking.get_staked_balance(alice), 1_000u64);
+        assert_eq!(staking.get_free_balance(bob), 300u64);
+        assert_eq!(staking.get_reserved_balance(bob), 0u64);
+        assert_eq!(staking.get_staked_balance(carol), 0u64);
+    }
+
+    #[test]
+    fn test_kill_account_removes_all_traces() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let (alice, bob) = (1u64, 2u64);
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 400).unwrap();
+        staking.reserve(alice, 100).unwrap();
+        staking.lock_for_vote(alice, 0, 400).unwrap();
+        staking.set_balance(bob, 250).unwrap();
+
+        assert_eq!(staking.total_issuance(), Some(1_250));
+        assert_eq!(staking.kill_account(&alice), Ok(1_000));
+
+        assert!(!staking.free_balances.contains_key(&alice));
+        assert!(!staking.staked_balances.contains_key(&alice));
+        assert!(!staking.reserved_balances.contains_key(&alice));
+        assert_eq!(staking.locked_for_voting(&alice), 0u64);
+        assert_eq!(staking.maturity(&alice, 100), 0u64);
+        assert_eq!(staking.total_issuance(), Some(250));
+
+        assert_eq!(staking.kill_account(&alice), Ok(0));
+    }
+
+    #[test]
+    fn test_kill_account_leaves_pending_payouts() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        for who in 1..=5u64 {
+            staking.set_balance(who, who * 100).unwrap();
+            staking.stake(who, who * 100).unwrap();
+        }
+        assert!(staking.mint_block_reward(1) > 1_001);
+        let mut unbounded = staking.clone();
+        unbounded.distribute_rewards(1_001).unwrap();
+        let era = staking.start_new_era();
+
+        // Accounts 5 and 4 are paid first; 5 is killed after its payout, 2 before its own
+        staking.distribute_rewards_bounded(1_001, 2).unwrap();
+        staking.kill_account(&5).unwrap();
+        staking.kill_account(&2).unwrap();
+        let unpaid = unbounded.get_free_balance(2);
+        assert_eq!(
+            staking.distribute_rewards_bounded(1_001, 2),
+            Ok(DistributeProgress::Complete { paid: 1_001 - unpaid })
+        );
+        for who in [1u64, 3, 4] {
+            assert_eq!(staking.get_free_balance(who), unbounded.get_free_balance(who));
+        }
+        assert_eq!(staking.get_free_balance(2), 0u64);
+
+        // The era snapshot no longer pays the killed accounts either
+        staking.distribute_era_rewards(era, 100).unwrap();
+        assert!(!staking.free_balances.contains_key(&2));
+        assert!(!staking.free_balances.contains_key(&5));
+    }
+
+    #[test]
+    fn test_state_root_commits_to_balances() {
+        let mut first = StakingPallet::<Runtime>::new();
+        let mut second = StakingPallet::<Runtime>::new();
+
+        // Same state reached in a different order, with a zero entry on one side
+        first.set_balance(1, 1_000).unwrap();
+        first.set_balance(2, 500).unwrap();
+        first.stake(1, 400).unwrap();
+        second.set_balance(2, 500).unwrap();
+        second.set_balance(1, 1_000).unwrap();
+        second.stake(1, 400).unwrap();
+        second.reserved_balances.insert(3, 0);
+        assert_eq!(first.state_root(), second.state_root());
+
+        second.set_balance(2, 501).unwrap();
+        assert_ne!(first.state_root(), second.state_root());
+    }
+
+    #[test]
+    fn test_state_root_is_stable() {
+        // SHA-256 of three (section, zero length) pairs, each a little-endian u64
+        let expected = "93af9093edc211a9a941bde5ef5640fd395604257f3d945f93c11ba9e918cc74";
+        let root = StakingPallet::<Runtime>::new().state_root();
+        let hex: String = root.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(hex, expected);
+    }
+
+    #[test]
+    fn test_commands_processed_incrementally() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 1_000).unwrap();
+        staking.mint_block_reward(1);
+
+        for _ in 0..5 {
+            staking.enqueue_command(PalletCommand::DistributeRewards(100));
+        }
+
+        assert_eq!(staking.process_commands(2), (2, None));
+        assert_eq!(staking.get_free_balance(alice), 200u64);
+        assert_eq!(staking.queued_commands(), 3);
+
+        assert_eq!(staking.process_commands(2), (2, None));
+        assert_eq!(staking.process_commands(2), (1, None));
+        assert_eq!(staking.process_commands(2), (0, None));
+        assert_eq!(staking.get_free_balance(alice), 500u64);
+    }
+
+    #[test]
+    fn test_failed_command_stops_the_batch() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 1_000).unwrap();
+        staking.mint_block_reward(1);
+        let minted = staking.reward_pool();
+
+        // The second payout overdraws the pool; the accrual queued behind it must wait
+        staking.enqueue_command(PalletCommand::DistributeRewards(100));
+        staking.enqueue_command(PalletCommand::DistributeRewards(minted));
+        staking.enqueue_command(PalletCommand::AccrueRewards(100));
+        assert_eq!(staking.process_commands(3), (1, Some("Insufficient reward pool")));
+        assert_eq!(staking.queued_commands(), 2);
+        assert_eq!(staking.get_free_balance(alice), 100u64);
+        assert_eq!(staking.get_pending_rewards(alice), 0u64);
+
+        // Retrying runs into the same command at the head
+        assert_eq!(staking.process_commands(3), (0, Some("Insufficient reward pool")));
+        assert_eq!(staking.queued_commands(), 2);
+    }
+
+    struct SlotRuntime;
+
+    impl SystemConfig for SlotRuntime {
+        type AccountId = u64;
+        type BlockNumber = u64;
+    }
+
+    impl StakingConfig for SlotRuntime {
+        type Balance = u64;
+        type Hooks = ();
+        const MAX_SUPPLY: u64 = u64::MAX;
+        const MIN_PAYOUT: u64 = 0;
+        const UNBONDING_PERIOD: u64 = 0;
+        const RESTAKE_COOLDOWN: u64 = 0;
+        const MAX_NOMINATORS_PER_VALIDATOR: u32 = 2;
+    }
+
+    #[test]
+    fn test_validator_nominator_slots_capped() {
+        let mut staking = StakingPallet::<SlotRuntime>::new();
+        for who in 1..=5u64 {
+            staking.set_balance(who, 100).unwrap();
+            staking.stake(who, 100).unwrap();
+        }
+        let validator = 1u64;
+        staking.register_validator(validator).unwrap();
+        assert_eq!(staking.nominate(2, 9), Err("Not a validator"));
+
+        staking.nominate(2, validator).unwrap();
+        staking.nominate(3, validator).unwrap();
+        assert_eq!(staking.nominate(4, validator), Err("Validator nominator slots full"));
+        // Re-nominating the same validator does not take another slot
+        assert!(staking.nominate(3, validator).is_ok());
+
+        staking.withdraw_nomination(&2);
+        staking.nominate(4, validator).unwrap();
+        assert_eq!(staking.nominators_of(&validator), vec![3, 4]);
+
+        // Killing the validator drops its nominations
+        staking.kill_account(&validator).unwrap();
+        assert!(!staking.is_validator(&validator));
+        assert!(staking.nominators_of(&validator).is_empty());
+    }
+
+    #[test]
+    fn test_freeze_blocks_staking_until_thaw() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 400).unwrap();
+
+        staking.freeze();
+        assert!(staking.is_frozen());
+        assert_eq!(staking.stake(alice, 100), Err("Staking is frozen"));
+        assert_eq!(staking.unstake(alice, 100), Err("Staking is frozen"));
+        assert_eq!(staking.stake_with_referral(alice, 100, 2), Err("Staking is frozen"));
+        assert_eq!(staking.withdraw_unbonded(alice), Err("Staking is frozen"));
+        assert_eq!(staking.nominate(alice, 3), Err("Staking is frozen"));
+        assert_eq!(staking.nominate_many(alice, vec![(3, 100)]), Err("Staking is frozen"));
+        assert_eq!(staking.reserve(alice, 100), Err("Staking is frozen"));
+        assert_eq!(staking.unreserve(alice, 100), Err("Staking is frozen"));
+        assert_eq!(staking.claim_rewards(alice), Err("Staking is frozen"));
+        assert_eq!(staking.get_free_balance(alice), 600u64);
+        assert_eq!(staking.get_staked_balance(alice), 400u64);
+        assert_eq!(staking.get_reserved_balance(alice), 0u64);
+        assert_eq!(staking.get_free_balance(2), 0u64);
+
+        staking.thaw();
+        assert_eq!(staking.stake(alice, 100), Ok(500));
+        assert_eq!(staking.unstake(alice, 100), Ok(600));
+    }
+
+    #[test]
+    fn test_slash_validator_shares_with_nominators() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut treasury = TreasuryPallet::<Runtime>::new();
+        let (validator, nominator_a, nominator_b) = (1u64, 2u64, 3u64);
+        for (who, stake) in [(validator, 500u64), (nominator_a, 300), (nominator_b, 200)] {
+            staking.set_balance(who, stake).unwrap();
+            staking.stake(who, stake).unwrap();
+        }
+        staking.register_validator(validator).unwrap();
+        staking.nominate(nominator_a, validator).unwrap();
+        staking.nominate(nominator_b, validator).unwrap();
+
+        // Backing is 1_000: shares are 50% / 30% / 20%
+        assert_eq!(staking.slash(validator, 100, &mut treasury), Ok(100));
+        assert_eq!(staking.get_staked_balance(validator), 450u64);
+        assert_eq!(staking.get_staked_balance(nominator_a), 270u64);
+        assert_eq!(staking.get_staked_balance(nominator_b), 180u64);
+
+        // 7 over a backing of 900 rounds down to cuts of 3 / 2 / 1; the validator takes the
+        // remaining unit
+        assert_eq!(staking.slash(validator, 7, &mut treasury), Ok(7));
+        assert_eq!(staking.get_staked_balance(validator), 446u64);
+        assert_eq!(staking.get_staked_balance(nominator_a), 268u64);
+        assert_eq!(staking.get_staked_balance(nominator_b), 179u64);
+        assert_eq!(treasury.balance(), 107u64);
+
+        // Slashing a nominator touches only its own stake
+        assert_eq!(staking.slash(nominator_b, 1_000, &mut treasury), Ok(179));
+        assert_eq!(staking.get_staked_balance(validator), 446u64);
+    }
+
+    #[test]
+    fn test_slash_keeps_stake_outside_a_partial_nomination() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut treasury = TreasuryPallet::<Runtime>::new();
+        let (validator, nominator) = (1u64, 2u64);
+        for who in [validator, nominator] {
+            staking.set_balance(who, 1_000).unwrap();
+            staking.stake(who, 1_000).unwrap();
+        }
+        staking.register_validator(validator).unwrap();
+        staking.nominate_many(nominator, vec![(validator, 250)]).unwrap();
+
+        // Backing is 1_250: the nominator risks only its 250 nominated, a fifth of the total
+        assert_eq!(staking.slash(validator, 100, &mut treasury), Ok(100));
+        assert_eq!(staking.get_staked_balance(validator), 920u64);
+        assert_eq!(staking.get_staked_balance(nominator), 980u64);
+        assert_eq!(staking.nominated_amount(&nominator, &validator), 230u64);
+        assert_eq!(treasury.balance(), 100u64);
+    }
+
+    #[test]
+    fn test_revoke_nomination_settles_rewards() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let (validator, nominator) = (1u64, 2u64);
+        for who in [validator, nominator] {
+            staking.set_balance(who, 1_000).unwrap();
+            staking.stake(who, 1_000).unwrap();
+        }
+        staking.register_validator(validator).unwrap();
+        staking.nominate(nominator, validator).unwrap();
+
+        staking.mint_block_reward(1);
+        staking.accrue_rewards(200).unwrap();
+        assert_eq!(staking.get_pending_rewards(nominator), 100u64);
+
+        assert_eq!(staking.revoke_nomination(nominator, &3), Err("Not nominating this validator"));
+        assert_eq!(staking.revoke_nomination(nominator, &validator), Ok(100));
+
+        assert_eq!(staking.get_free_balance(nominator), 100u64);
+        assert_eq!(staking.get_pending_rewards(nominator), 0u64);
+        assert_eq!(staking.get_staked_balance(nominator), 1_000u64);
+        assert!(staking.nominators_of(&validator).is_empty());
+        assert!(staking.unstake(nominator, 1_000).is_ok());
+    }
+
+    #[test]
+    fn test_revoke_nomination_settles_only_nominated_share() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let (validator, nominator) = (1u64, 2u64);
+        for who in [validator, nominator] {
+            staking.set_balance(who, 1_000).unwrap();
+            staking.stake(who, 1_000).unwrap();
+        }
+        staking.register_validator(validator).unwrap();
+        staking.nominate_many(nominator, vec![(validator, 250)]).unwrap();
+
+        staking.mint_block_reward(1);
+        staking.accrue_rewards(200).unwrap();
+        assert_eq!(staking.get_pending_rewards(nominator), 100u64);
+
+        assert_eq!(staking.revoke_nomination(nominator, &validator), Ok(25));
+        assert_eq!(staking.get_free_balance(nominator), 25u64);
+        assert_eq!(staking.get_pending_rewards(nominator), 75u64);
+        assert!(staking.nominators_of(&validator).is_empty());
+    }
+
+    #[test]
+    fn test_nominate_many_is_atomic() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let nominator = 9u64;
+        for who in [1u64, 2, 3, nominator] {
+            staking.set_balance(who, 1_000).unwrap();
+            staking.stake(who, 1_000).unwrap();
+        }
+        staking.register_validator(1).unwrap();
+        staking.register_validator(2).unwrap();
+
+        // Account 3 is not a validator, so none of the three targets is applied
+        assert_eq!(
+            staking.nominate_many(nominator, vec![(1, 300), (2, 300), (3, 300)]),
+            Err("Not a validator")
+        );
+        assert!(staking.nominators_of(&1).is_empty());
+        assert!(staking.nominators_of(&2).is_empty());
+
+        assert_eq!(
+            staking.nominate_many(nominator, vec![(1, 600), (2, 600)]),
+            Err("Insufficient staked balance")
+        );
+
+        staking.nominate_many(nominator, vec![(1, 600), (2, 400)]).unwrap();
+        assert_eq!(staking.nominated_amount(&nominator, &1), 600u64);
+        assert_eq!(staking.nominated_amount(&nominator, &2), 400u64);
+        assert_eq!(staking.nominators_of(&2), vec![nominator]);
+    }
+
+    #[test]
+    fn test_unstake_proportional_keeps_ratios() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let nominator = 9u64;
+        for who in [1u64, 2, nominator] {
+            staking.set_balance(who, 1_000).unwrap();
+            staking.stake(who, 1_000).unwrap();
+        }
+        staking.register_validator(1).unwrap();
+        staking.register_validator(2).unwrap();
+        assert_eq!(staking.unstake_proportional(nominator, 100), Err("Not nominating"));
+
+        staking.nominate_many(nominator, vec![(1, 300), (2, 100)]).unwrap();
+        assert_eq!(
+            staking.unstake_proportional(nominator, 500),
+            Err("Amount exceeds nominated stake")
+        );
+
+        staking.unstake_proportional(nominator, 100).unwrap();
+        assert_eq!(staking.get_staked_balance(nominator), 900u64);
+        assert_eq!(staking.nominated_amount(&nominator, &1), 225u64);
+        assert_eq!(staking.nominated_amount(&nominator, &2), 75u64);
+
+        // 2.25 and 0.75 floor to 2 and 0; the leftover unit comes off the first validator
+        staking.unstake_proportional(nominator, 3).unwrap();
+        assert_eq!(staking.nominated_amount(&nominator, &1), 222u64);
+        assert_eq!(staking.nominated_amount(&nominator, &2), 75u64);
+    }
+
+    #[test]
+    fn test_unbonding_schedule_and_next_unlock() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 1_000).unwrap();
+        assert_eq!(staking.next_unlock(&alice), None);
+
+        staking.on_initialize(5);
+        staking.unbond(alice, 300).unwrap();
+        staking.on_initialize(8);
+        staking.unbond(alice, 200).unwrap();
+
+        assert_eq!(staking.unbonding_chunks(&alice), &[(300, 15), (200, 18)]);
+        assert_eq!(staking.unbonding_total(&alice), 500u64);
+        assert_eq!(staking.next_unlock(&alice), Some(15));
+        assert_eq!(staking.get_staked_balance(alice), 500u64);
+        assert_eq!(staking.get_free_balance(alice), 0u64);
+        assert_eq!(staking.total_issuance(), Some(1_000));
+
+        // Only the first chunk has matured at block 15
+        staking.on_initialize(15);
+        assert_eq!(staking.withdraw_unbonded(alice), Ok(300));
+        assert_eq!(staking.unbonding_chunks(&alice), &[(200, 18)]);
+        assert_eq!(staking.next_unlock(&alice), Some(18));
+        assert_eq!(staking.get_free_balance(alice), 300u64);
+    }
+
+    #[test]
+    fn test_rebond_takes_soonest_chunks_first() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 1_000).unwrap();
+
+        staking.on_initialize(5);
+        staking.unbond(alice, 300).unwrap();
+        staking.on_initialize(8);
+        staking.unbond(alice, 200).unwrap();
+
+        assert_eq!(staking.rebond(alice, 600), Err("Insufficient unbonding balance"));
+
+        // The whole 300 chunk and 50 of the later one go back into stake
+        staking.rebond(alice, 350).unwrap();
+        assert_eq!(staking.get_staked_balance(alice), 850u64);
+        assert_eq!(staking.unbonding_total(&alice), 150u64);
+        assert_eq!(staking.unbonding_chunks(&alice), &[(150, 18)]);
+
+        staking.rebond(alice, 150).unwrap();
+        assert_eq!(staking.get_staked_balance(alice), 1_000u64);
+        assert_eq!(staking.next_unlock(&alice), None);
+    }
+
+    #[test]
+    fn test_full_unstake_starts_restake_cooldown() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 500).unwrap();
+
+        // A partial unstake leaves staking open
+        staking.on_initialize(10);
+        staking.unstake(alice, 200).unwrap();
+        assert!(staking.stake(alice, 100).is_ok());
+
+        staking.unstake(alice, 400).unwrap();
+        assert_eq!(staking.stake(alice, 100), Err("Restake on cooldown"));
+
+        staking.on_initialize(14);
+        assert_eq!(staking.stake(alice, 100), Err("Restake on cooldown"));
+
+        staking.on_initialize(15);
+        assert!(staking.stake(alice, 100).is_ok());
+        assert_eq!(staking.get_staked_balance(alice), 100u64);
+    }
+
+    #[test]
+    fn test_rebond_waits_out_restake_cooldown() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 500).unwrap();
+
+        // Unbonding everything is a full unstake too
+        staking.on_initialize(10);
+        staking.unbond(alice, 500).unwrap();
+        assert_eq!(staking.rebond(alice, 500), Err("Restake on cooldown"));
+
+        staking.on_initialize(15);
+        staking.rebond(alice, 500).unwrap();
+        assert_eq!(staking.get_staked_balance(alice), 500u64);
+    }
+
+    #[test]
+    fn test_rebond_respects_min_stake() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let alice = 1u64;
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.stake(alice, 500).unwrap();
+        staking.unbond(alice, 500).unwrap();
+        staking.on_initialize(5);
+        staking.set_parameter(StakingParameter::MinStake(100));
+
+        // Like staking, the minimum applies to the resulting total stake
+        assert_eq!(staking.rebond(alice, 50), Err("Stake below minimum"));
+        assert_eq!(staking.unbonding_total(&alice), 500u64);
+        staking.rebond(alice, 100).unwrap();
+        assert_eq!(staking.get_staked_balance(alice), 100u64);
+        staking.rebond(alice, 1).unwrap();
+        assert_eq!(staking.get_staked_balance(alice), 101u64);
+    }
+
+    #[test]
+    fn test_era_rewards_follow_era_start_snapshot() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let (alice, bob) = (1u64, 2u64);
+        staking.set_balance(alice, 1_000).unwrap();
+        staking.set_balance(bob, 1_000).unwrap();
+        staking.stake(alice, 300).unwrap();
+        staking.stake(bob, 100).unwrap();
+        staking.mint_block_reward(1);
+
+        assert_eq!(staking.start_new_era(), 1);
+        assert_eq!(staking.current_era(), 1);
+
+        // Bob piles on stake just before payout; the era still pays 3:1
+        staking.stake(bob, 900).unwrap();
+        assert_eq!(staking.distribute_era_rewards(2, 1_000), Err("Unknown era"));
+        assert_eq!(staking.distribute_era_rewards(1, 1_000), Ok(1_000u64));
+        assert_eq!(staking.get_free_balance(alice), 1_450u64);
+        assert_eq!(staking.get_free_balance(bob), 250u64);
+
+        assert_eq!(staking.distribute_era_rewards(1, 1_000), Err("Unknown era"));
+    }
+
+    #[test]
+    fn test_select_author_follows_backing() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        assert_eq!(staking.select_author(7), None);
+
+        // Validator 1 is backed 300 + 150 nominated, validator 2 only 150
+        for (who, stake) in [(1u64, 300u64), (2, 150), (3, 150)] {
+            staking.set_balance(who, stake).unwrap();
+            staking.stake(who, stake).unwrap();
+        }
+        staking.register_validator(1).unwrap();
+        staking.register_validator(2).unwrap();
+        staking.nominate(3, 1).unwrap();
+        assert_eq!(staking.total_backing(&1), Some(450));
+
+        assert_eq!(staking.select_author(42), staking.select_author(42));
+
+        let rounds = 10_000;
+        let picked_first = (0..rounds)
+            .filter(|seed| staking.select_author(*seed) == Some(1))
+            .count();
+
+        // Expect 7_500 for a 3:1 split; allow a generous margin
+        assert!((7_200..=7_800).contains(&picked_first), "picked {} times", picked_first);
+    }
+
+    #[test]
+    fn test_checked_set_balance_guards_issuance() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let (alice, bob) = (1u64, 2u64);
+        staking.checked_set_balance(alice, u64::MAX - 10).unwrap();
+
+        assert_eq!(staking.checked_set_balance(bob, 11), Err("Issuance overflow"));
+        assert_eq!(staking.get_free_balance(bob), 0u64);
+        staking.checked_set_balance(bob, 10).unwrap();
+        assert_eq!(staking.total_issuance(), Some(u64::MAX));
+
+        // Replacing a balance only counts the difference
+        staking.checked_set_balance(alice, u64::MAX - 10).unwrap();
+        assert_eq!(staking.checked_set_balance(alice, u64::MAX - 9), Err("Issuance overflow"));
+    }
+
+    #[cfg(feature = "codec")]
+    #[test]
+    fn test_codec_round_trip() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        for who in 1..=4u64 {
+            staking.set_balance(who, who * 1_000).unwrap();
+            staking.stake(who, who * 100).unwrap();
+        }
+        staking.reserve(2, 50).unwrap();
+
+        let bytes = staking.encode();
+        let decoded = StakingPallet::<Runtime>::decode(&bytes).unwrap();
+        assert_eq!(decoded.free_balances, staking.free_balances);
+        assert_eq!(decoded.staked_balances, staking.staked_balances);
+        assert_eq!(decoded.reserved_balances, staking.reserved_balances);
+        assert_eq!(decoded.encode(), bytes);
+    }
+
+    #[cfg(feature = "codec")]
+    #[test]
+    fn test_codec_ignores_zero_entries() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(1, 1_000).unwrap();
+        staking.stake(1, 400).unwrap();
+        let clean = staking.clone();
+
+        // Zero entries left behind by `ensure_account` and a full unstake
+        let _ = staking.ensure_account(2).unwrap();
+        staking.set_balance(3, 100).unwrap();
+        staking.stake(3, 100).unwrap();
+        staking.unstake(3, 100).unwrap();
+        staking.set_balance(3, 0).unwrap();
+        assert!(staking.staked_balances.contains_key(&3));
+
+        assert_eq!(staking.encode(), clean.encode());
+        assert_eq!(staking.state_root(), clean.state_root());
+    }
+
+    #[cfg(feature = "codec")]
+    #[test]
+    fn test_codec_rejects_malformed_bytes() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        staking.set_balance(1, 1_000).unwrap();
+        let bytes = staking.encode();
+
+        assert_eq!(
+            StakingPallet::<Runtime>::decode(&bytes[..bytes.len() - 1]).err(),
+            Some("Unexpected end of input")
+        );
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert_eq!(StakingPallet::<Runtime>::decode(&padded).err(), Some("Trailing bytes"));
     }
 }
@@ -1,6 +1,95 @@
+use num::traits::{CheckedAdd, CheckedSub, Zero};
 use std::hash::Hash;
 
 pub trait SystemConfig {
     // Define the account identifier type
-    type AccountId: Eq + Hash + Clone;
+    type AccountId: Eq + Hash + Ord + Clone;
+    // Define the block number type used for delays and deadlines
+    type BlockNumber: Zero + CheckedAdd + CheckedSub + PartialOrd + Into<u64> + Copy;
+    // Decimal places of the native token: a raw balance of 10^DECIMALS is one whole unit
+    const DECIMALS: u32 = 0;
+
+    // Whether an account id may hold balances or act, e.g. to reject a zero address
+    fn is_valid_account(_who: &Self::AccountId) -> bool {
+        true
+    }
+}
+
+// Blocks from `from` until `to`, or zero once `to` has been reached or passed
+pub fn blocks_until<B: Zero + CheckedSub + Copy>(from: B, to: B) -> B {
+    to.checked_sub(&from).unwrap_or(B::zero())
+}
+
+// Whether block `now` has reached `deadline`; every deadline comparison goes through here
+pub fn has_reached<B: Zero + CheckedSub + Copy>(now: B, deadline: B) -> bool {
+    blocks_until(now, deadline).is_zero()
+}
+
+// Who is dispatching a call: a signed account, or the privileged root authority
+#[derive(Clone, Debug, PartialEq)]
+pub enum Origin<AccountId> {
+    Signed(AccountId),
+    Root,
+}
+
+// Get the account that signed a call
+pub fn ensure_signed<AccountId>(origin: &Origin<AccountId>) -> Result<&AccountId, &'static str> {
+    match origin {
+        Origin::Signed(signer) => Ok(signer),
+        Origin::Root => Err("Signed origin required"),
+    }
+}
+
+// Check that a call is signed by `who`
+pub fn ensure_signed_by<AccountId: PartialEq>(
+    origin: &Origin<AccountId>,
+    who: &AccountId,
+) -> Result<(), &'static str> {
+    match origin {
+        Origin::Signed(signer) if signer == who => Ok(()),
+        Origin::Signed(_) => Err("Signer does not match account"),
+        Origin::Root => Err("Signed origin required"),
+    }
+}
+
+// Check that a call comes from root
+pub fn ensure_root<AccountId>(origin: &Origin<AccountId>) -> Result<(), &'static str> {
+    match origin {
+        Origin::Root => Ok(()),
+        Origin::Signed(_) => Err("Root origin required"),
+    }
+}
+
+// Layout version of a pallet's stored state, bumped whenever that layout changes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StorageVersion(pub u16);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_until() {
+        assert_eq!(blocks_until(10u64, 25), 15);
+        assert_eq!(blocks_until(10u64, 10), 0);
+        // Saturates instead of underflowing once the target has passed
+        assert_eq!(blocks_until(25u64, 10), 0);
+        assert_eq!(blocks_until(u32::MAX, 0), 0);
+
+        assert!(!has_reached(9u64, 10));
+        assert!(has_reached(10u64, 10));
+        assert!(has_reached(11u64, 10));
+    }
+
+    #[test]
+    fn test_origin_checks() {
+        assert_eq!(ensure_signed_by(&Origin::Signed(1u64), &1), Ok(()));
+        assert_eq!(ensure_signed_by(&Origin::Signed(2u64), &1), Err("Signer does not match account"));
+        assert_eq!(ensure_signed_by(&Origin::Root, &1u64), Err("Signed origin required"));
+        assert_eq!(ensure_signed(&Origin::Signed(3u64)), Ok(&3));
+        assert_eq!(ensure_signed(&Origin::<u64>::Root), Err("Signed origin required"));
+
+        assert_eq!(ensure_root(&Origin::<u64>::Root), Ok(()));
+        assert_eq!(ensure_root(&Origin::Signed(1u64)), Err("Root origin required"));
+    }
 }
@@ -0,0 +1,192 @@
+// Helpers for exercising the generic pallets against configurations other than `Runtime`.
+
+// Define a unit struct implementing every config trait, for tests and property checks.
+//
+// `AccountId` and `Balance` are required; `Balance` must be a primitive integer. The block
+// number is `u64`, `MAX_SUPPLY` is the balance type's maximum, every account is valid, and
+// everything that costs or delays (`MIN_PAYOUT`, `UNBONDING_PERIOD`, `RESTAKE_COOLDOWN`,
+// `PROPOSAL_DEPOSIT`, `REFUND_DELAY`, `VOTING_PERIOD`, `MIN_VOTING_BLOCKS`,
+// `PROPOSAL_COOLDOWN_BLOCKS`) is zero. `PROPOSAL_DEPOSIT`, `VOTING_PERIOD`,
+// `MIN_VOTING_BLOCKS`, `PROPOSAL_COOLDOWN_BLOCKS` and any other governance constant can be
+// overridden by name, `is_valid_account` and any governance hook by defining the function.
+// Every entry ends with a comma:
+//
+//     test_runtime!(MyRuntime {
+//         AccountId = [u8; 32],
+//         Balance = u128,
+//         VOTING_PERIOD: u64 = 20,
+//         APPROVAL_THRESHOLD_PERCENT: u32 = 66,
+//         fn is_valid_account(who: &[u8; 32]) -> bool { *who != [0; 32] },
+//     });
+#[macro_export]
+macro_rules! test_runtime {
+    (@munch $name:ident, $account:ty, $balance:ty,
+        system: [$($system:tt)*],
+        defaults: [$($deposit:tt)*] [$($period:tt)*] [$($min:tt)*] [$($cooldown:tt)*],
+        governance: [$($governance:tt)*];
+        PROPOSAL_DEPOSIT: $ty:ty = $value:expr, $($rest:tt)*
+    ) => {
+        $crate::test_runtime!(@munch $name, $account, $balance,
+            system: [$($system)*],
+            defaults: [const PROPOSAL_DEPOSIT: $ty = $value;]
+                [$($period)*] [$($min)*] [$($cooldown)*],
+            governance: [$($governance)*];
+            $($rest)*
+        );
+    };
+    (@munch $name:ident, $account:ty, $balance:ty,
+        system: [$($system:tt)*],
+        defaults: [$($deposit:tt)*] [$($period:tt)*] [$($min:tt)*] [$($cooldown:tt)*],
+        governance: [$($governance:tt)*];
+        VOTING_PERIOD: $ty:ty = $value:expr, $($rest:tt)*
+    ) => {
+        $crate::test_runtime!(@munch $name, $account, $balance,
+            system: [$($system)*],
+            defaults: [$($deposit)*] [const VOTING_PERIOD: $ty = $value;]
+                [$($min)*] [$($cooldown)*],
+            governance: [$($governance)*];
+            $($rest)*
+        );
+    };
+    (@munch $name:ident, $account:ty, $balance:ty,
+        system: [$($system:tt)*],
+        defaults: [$($deposit:tt)*] [$($period:tt)*] [$($min:tt)*] [$($cooldown:tt)*],
+        governance: [$($governance:tt)*];
+        MIN_VOTING_BLOCKS: $ty:ty = $value:expr, $($rest:tt)*
+    ) => {
+        $crate::test_runtime!(@munch $name, $account, $balance,
+            system: [$($system)*],
+            defaults: [$($deposit)*] [$($period)*]
+                [const MIN_VOTING_BLOCKS: $ty = $value;] [$($cooldown)*],
+            governance: [$($governance)*];
+            $($rest)*
+        );
+    };
+    (@munch $name:ident, $account:ty, $balance:ty,
+        system: [$($system:tt)*],
+        defaults: [$($deposit:tt)*] [$($period:tt)*] [$($min:tt)*] [$($cooldown:tt)*],
+        governance: [$($governance:tt)*];
+        PROPOSAL_COOLDOWN_BLOCKS: $ty:ty = $value:expr, $($rest:tt)*
+    ) => {
+        $crate::test_runtime!(@munch $name, $account, $balance,
+            system: [$($system)*],
+            defaults: [$($deposit)*] [$($period)*] [$($min)*]
+                [const PROPOSAL_COOLDOWN_BLOCKS: $ty = $value;],
+            governance: [$($governance)*];
+            $($rest)*
+        );
+    };
+    (@munch $name:ident, $account:ty, $balance:ty,
+        system: [$($system:tt)*],
+        defaults: [$($deposit:tt)*] [$($period:tt)*] [$($min:tt)*] [$($cooldown:tt)*],
+        governance: [$($governance:tt)*];
+        fn is_valid_account $params:tt -> $ret:ty $body:block, $($rest:tt)*
+    ) => {
+        $crate::test_runtime!(@munch $name, $account, $balance,
+            system: [$($system)* fn is_valid_account $params -> $ret $body],
+            defaults: [$($deposit)*] [$($period)*] [$($min)*] [$($cooldown)*],
+            governance: [$($governance)*];
+            $($rest)*
+        );
+    };
+    (@munch $name:ident, $account:ty, $balance:ty,
+        system: [$($system:tt)*],
+        defaults: [$($deposit:tt)*] [$($period:tt)*] [$($min:tt)*] [$($cooldown:tt)*],
+        governance: [$($governance:tt)*];
+        fn $function:ident $params:tt -> $ret:ty $body:block, $($rest:tt)*
+    ) => {
+        $crate::test_runtime!(@munch $name, $account, $balance,
+            system: [$($system)*],
+            defaults: [$($deposit)*] [$($period)*] [$($min)*] [$($cooldown)*],
+            governance: [$($governance)* fn $function $params -> $ret $body];
+            $($rest)*
+        );
+    };
+    (@munch $name:ident, $account:ty, $balance:ty,
+        system: [$($system:tt)*],
+        defaults: [$($deposit:tt)*] [$($period:tt)*] [$($min:tt)*] [$($cooldown:tt)*],
+        governance: [$($governance:tt)*];
+        $constant:ident : $ty:ty = $value:expr, $($rest:tt)*
+    ) => {
+        $crate::test_runtime!(@munch $name, $account, $balance,
+            system: [$($system)*],
+            defaults: [$($deposit)*] [$($period)*] [$($min)*] [$($cooldown)*],
+            governance: [$($governance)* const $constant: $ty = $value;];
+            $($rest)*
+        );
+    };
+    (@munch $name:ident, $account:ty, $balance:ty,
+        system: [$($system:tt)*],
+        defaults: [$($deposit:tt)*] [$($period:tt)*] [$($min:tt)*] [$($cooldown:tt)*],
+        governance: [$($governance:tt)*];
+    ) => {
+        struct $name;
+
+        impl $crate::SystemConfig for $name {
+            type AccountId = $account;
+            type BlockNumber = u64;
+            $($system)*
+        }
+
+        impl $crate::StakingConfig for $name {
+            type Balance = $balance;
+            type Hooks = ();
+            const MAX_SUPPLY: $balance = <$balance>::MAX;
+            const MIN_PAYOUT: $balance = 0;
+            const UNBONDING_PERIOD: u64 = 0;
+            const RESTAKE_COOLDOWN: u64 = 0;
+        }
+
+        impl $crate::GovernanceConfig for $name {
+            $($deposit)*
+            const REFUND_DELAY: u64 = 0;
+            $($period)*
+            $($min)*
+            $($cooldown)*
+            $($governance)*
+        }
+    };
+    ($name:ident {
+        AccountId = $account:ty,
+        Balance = $balance:ty
+        $(, $($entries:tt)*)?
+    }) => {
+        $crate::test_runtime!(@munch $name, $account, $balance,
+            system: [],
+            defaults: [const PROPOSAL_DEPOSIT: $balance = 0;] [const VOTING_PERIOD: u64 = 0;]
+                [const MIN_VOTING_BLOCKS: u64 = 0;] [const PROPOSAL_COOLDOWN_BLOCKS: u64 = 0;],
+            governance: [];
+            $($($entries)*)?
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::governance::{GovernancePallet, ProposalStatus};
+    use crate::staking::StakingPallet;
+
+    test_runtime!(WideRuntime {
+        AccountId = [u8; 32],
+        Balance = u128,
+    });
+
+    #[test]
+    fn test_pallets_with_array_account_ids() {
+        let alice = [1u8; 32];
+        let bob = [2u8; 32];
+        let mut staking = StakingPallet::<WideRuntime>::new();
+
+        staking.set_balance(alice, 1_000_000_000_000_000_000_000).unwrap();
+        staking.stake(alice, 400_000_000_000_000_000_000).unwrap();
+        assert_eq!(staking.get_free_balance_ref(&alice), 600_000_000_000_000_000_000);
+        assert_eq!(staking.get_staked_balance_ref(&alice), 400_000_000_000_000_000_000);
+        assert_eq!(staking.unstake(bob, 1), Err("Insufficient staked balance"));
+
+        let mut governance = GovernancePallet::<WideRuntime>::new();
+        let proposal_id = governance.create_proposal(alice, "Wide ids".to_string()).unwrap();
+        governance.vote(alice, proposal_id, true).unwrap();
+        governance.vote(bob, proposal_id, true).unwrap();
+        assert_eq!(governance.finalize_proposal(proposal_id), Ok(ProposalStatus::Approved));
+    }
+}
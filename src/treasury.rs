@@ -0,0 +1,180 @@
+use crate::governance::{GovernanceConfig, GovernancePallet, ProposalKind, ProposalStatus};
+use crate::staking::{StakingConfig, StakingPallet};
+use crate::system::{ensure_root, Origin};
+use num::traits::{CheckedAdd, CheckedSub, Zero};
+use std::collections::HashSet;
+
+pub struct TreasuryPallet<T: StakingConfig> {
+    // Funds collected from slashes and forfeitures
+    balance: T::Balance,
+    // Approved proposals that already triggered a spend
+    spent_proposals: HashSet<u32>,
+}
+
+impl<T: StakingConfig> Clone for TreasuryPallet<T> {
+    fn clone(&self) -> Self {
+        Self {
+            balance: self.balance,
+            spent_proposals: self.spent_proposals.clone(),
+        }
+    }
+}
+
+impl<T: StakingConfig> Default for TreasuryPallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: StakingConfig> TreasuryPallet<T> {
+    pub fn new() -> Self {
+        Self {
+            balance: T::Balance::zero(),
+            spent_proposals: HashSet::new(),
+        }
+    }
+
+    // Get the current treasury balance
+    pub fn balance(&self) -> T::Balance {
+        self.balance
+    }
+
+    // Credit funds to the treasury (slashes, fees, forfeited deposits)
+    #[must_use = "a failed deposit leaves the treasury unchanged"]
+    pub fn deposit(&mut self, amount: T::Balance) -> Result<(), &'static str> {
+        self.balance = self.balance.checked_add(&amount)
+            .ok_or("Overflow")?;
+
+        Ok(())
+    }
+}
+
+impl<T: GovernanceConfig> TreasuryPallet<T> {
+    // Pay out an approved Spend proposal to the account and amount it names, once. Root only:
+    // the runtime enacts it when the proposal is finalized.
+    #[must_use = "a rejected spend leaves the treasury unchanged"]
+    pub fn spend(
+        &mut self,
+        origin: &Origin<T::AccountId>,
+        governance: &GovernancePallet<T>,
+        proposal_id: u32,
+        staking: &mut StakingPallet<T>,
+    ) -> Result<(), &'static str> {
+        ensure_root(origin)?;
+
+        let proposal = governance.get_proposal(proposal_id)
+            .ok_or("Proposal does not exist")?;
+
+        let (to, amount) = match proposal.kind() {
+            ProposalKind::Spend { to, amount } => (to.clone(), *amount),
+            _ => return Err("Proposal is not a spend"),
+        };
+
+        if !matches!(proposal.status(), ProposalStatus::Approved) {
+            return Err("Proposal is not approved");
+        }
+
+        if self.spent_proposals.contains(&proposal_id) {
+            return Err("Proposal already spent");
+        }
+
+        let new_balance = self.balance.checked_sub(&amount)
+            .ok_or("Insufficient treasury balance")?;
+
+        let new_free = staking.get_free_balance_ref(&to).checked_add(&amount)
+            .ok_or("Overflow")?;
+
+        staking.set_balance(to, new_free)?;
+        self.balance = new_balance;
+        self.spent_proposals.insert(proposal_id);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Runtime;
+
+    #[test]
+    fn test_slash_funds_treasury_and_approved_spend_disburses() {
+        let alice = 1u64;
+        let bob = 2u64;
+        let charlie = 3u64;
+
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let mut treasury = TreasuryPallet::<Runtime>::new();
+
+        staking.set_balance(alice, 1000).unwrap();
+        staking.stake(alice, 500).unwrap();
+
+        // Slashing moves staked funds into the treasury
+        let slashed = staking.slash(alice, 200, &mut treasury).unwrap();
+        assert_eq!(slashed, 200u64);
+        assert_eq!(staking.get_staked_balance(alice), 300u64);
+        assert_eq!(treasury.balance(), 200u64);
+
+        // Spending without an approved proposal fails
+        let proposal_id = governance
+            .create_spend_proposal(bob, "Fund Charlie".to_string(), charlie, 150)
+            .unwrap();
+        assert_eq!(
+            treasury.spend(&Origin::Root, &governance, proposal_id, &mut staking),
+            Err("Proposal is not approved")
+        );
+
+        governance.vote(alice, proposal_id, true).unwrap();
+        governance.vote(bob, proposal_id, true).unwrap();
+        governance.finalize_proposal(proposal_id).unwrap();
+
+        // Only root can pay out, and only the recipient and amount the proposal names
+        assert_eq!(
+            treasury.spend(&Origin::Signed(bob), &governance, proposal_id, &mut staking),
+            Err("Root origin required")
+        );
+        treasury.spend(&Origin::Root, &governance, proposal_id, &mut staking).unwrap();
+        assert_eq!(treasury.balance(), 50u64);
+        assert_eq!(staking.get_free_balance(charlie), 150u64);
+
+        // The same proposal cannot be spent twice
+        assert_eq!(
+            treasury.spend(&Origin::Root, &governance, proposal_id, &mut staking),
+            Err("Proposal already spent")
+        );
+    }
+
+    #[test]
+    fn test_spend_refuses_other_proposal_kinds() {
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut governance = GovernancePallet::<Runtime>::new();
+        let mut treasury = TreasuryPallet::<Runtime>::new();
+        treasury.deposit(100).unwrap();
+
+        let proposal_id = governance.create_proposal(1, "Grant".to_string()).unwrap();
+        governance.vote(1, proposal_id, true).unwrap();
+        governance.finalize_proposal(proposal_id).unwrap();
+
+        assert_eq!(
+            treasury.spend(&Origin::Root, &governance, proposal_id, &mut staking),
+            Err("Proposal is not a spend")
+        );
+        assert_eq!(treasury.balance(), 100u64);
+    }
+
+    #[test]
+    fn test_slash_is_capped_at_staked_balance() {
+        let alice = 1u64;
+        let mut staking = StakingPallet::<Runtime>::new();
+        let mut treasury = TreasuryPallet::<Runtime>::new();
+
+        staking.set_balance(alice, 100).unwrap();
+        staking.stake(alice, 80).unwrap();
+
+        let slashed = staking.slash(alice, 500, &mut treasury).unwrap();
+        assert_eq!(slashed, 80u64);
+        assert_eq!(staking.get_staked_balance(alice), 0u64);
+        assert_eq!(treasury.balance(), 80u64);
+    }
+}